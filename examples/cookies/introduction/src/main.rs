@@ -6,60 +6,57 @@ extern crate hyper;
 extern crate mime;
 
 use hyper::{Response, StatusCode};
-use hyper::header::{Cookie, Headers, SetCookie};
 
+use gotham::http::cookie::{Cookie, CookieJar};
 use gotham::http::response::create_response;
+use gotham::router::Router;
+use gotham::router::builder::*;
 use gotham::state::{FromState, State};
 
 /// The first request will set a cookie, and subsequent requests will echo it back.
-fn handler(state: State) -> (State, Response) {
+fn handler(mut state: State) -> (State, Response) {
     // Define a narrow scope so that state can be borrowed/moved later in the function.
-    let adjective_from_cookie = {
-        // Get the request headers.
-        let headers: &Headers = Headers::borrow_from(&state);
-        // Get the Cookie header from the request.
-        let maybe_cookie = headers.get::<Cookie>();
+    let adjective = {
+        // The `Router` stores the cookies from the request in a `CookieJar`.
+        let jar = CookieJar::borrow_mut_from(&mut state);
+
         // Get the value of the "adjective" cookie, if set.
-        maybe_cookie.and_then(|cookie| cookie.get("adjective").map(|s| s.to_owned()))
-    };
+        let adjective = jar.get("adjective")
+            .map(|cookie| cookie.value().to_owned())
+            .unwrap_or("first time".to_owned());
 
-    let adjective = adjective_from_cookie.unwrap_or("first time".to_owned());
-
-    let mut response = {
-        create_response(
-            &state,
-            StatusCode::Ok,
-            Some((
-                format!("Hello {} visitor\n", adjective).as_bytes().to_vec(),
-                mime::TEXT_PLAIN,
-            )),
-        )
+        // Add a new cookie to the jar. The `Router` sends it to the client in a `Set-Cookie`
+        // header once the response is complete.
+        let cookie = Cookie::build("adjective", "repeat").http_only(true).finish();
+        jar.add(cookie);
+
+        adjective
     };
-    {
-        // Make a new cookie. This is currently one of the less type-safe corners of Gotham.
-        let cookie = "adjective=repeat; HttpOnly".to_owned();
-        set_cookie(cookie, &mut response);
-    }
+
+    let response = create_response(
+        &state,
+        StatusCode::Ok,
+        Some((
+            format!("Hello {} visitor\n", adjective).as_bytes().to_vec(),
+            mime::TEXT_PLAIN,
+        )),
+    );
+
     (state, response)
 }
 
-fn set_cookie(cookie: String, response: &mut Response) {
-    // Get the response headers.
-    let headers = response.headers_mut();
-    if let Some(existing_cookies) = headers.get_mut::<SetCookie>() {
-        // If some cookies are already being set (e.g. by some middleware), append to that list.
-        existing_cookies.push(cookie);
-        return;
-    }
-    // Else create a new SetCookie header.
-    headers.set::<SetCookie>(SetCookie(vec![cookie]));
+/// Create a `Router`, which manages the `CookieJar` for each request.
+fn router() -> Router {
+    build_simple_router(|route| {
+        route.get("/").to(handler);
+    })
 }
 
 /// Start a server and use a `Router` to dispatch requests
 pub fn main() {
     let addr = "127.0.0.1:7878";
     println!("Listening for requests at http://{}", addr);
-    gotham::start(addr, || Ok(handler))
+    gotham::start(addr, router())
 }
 
 #[cfg(test)]
@@ -70,7 +67,7 @@ mod tests {
 
     #[test]
     fn cookie_is_set_and_counter_increments() {
-        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let test_server = TestServer::new(router()).unwrap();
         let response = test_server
             .client()
             .get("http://localhost/")
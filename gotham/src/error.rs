@@ -0,0 +1,106 @@
+//! Defines the error type returned when a Gotham application cannot be started.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Describes why a Gotham application could not be started.
+///
+/// Returned by `gotham::try_start` and the related functions, which allow a failure to start (for
+/// example, when the port is already in use) to be reported without a panic.
+///
+/// ```rust,no_run
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::State;
+/// # use gotham::StartError;
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Accepted))
+/// # }
+/// #
+/// # fn main() {
+/// match gotham::try_start("127.0.0.1:7878", || Ok(my_handler)) {
+///     Ok(()) => (),
+///     Err(StartError::Bind(addr, _)) => eprintln!("unable to listen on {}", addr),
+///     Err(e) => eprintln!("unable to start: {}", e),
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum StartError {
+    /// The listener address could not be resolved into a `SocketAddr`.
+    AddressResolution(io::Error),
+
+    /// The listener could not be bound to the resolved address, typically because the address is
+    /// already in use or requires additional privileges.
+    Bind(SocketAddr, io::Error),
+
+    /// The listener could not be bound to the Unix domain socket at the given path, typically
+    /// because a file already exists at that path.
+    BindUnix(PathBuf, io::Error),
+
+    /// No listeners were provided, so the application would never accept a connection.
+    NoListeners,
+
+    /// The application was configured to run on zero threads, so no connection would be served.
+    InvalidThreadCount,
+
+    /// A tokio reactor could not be created, or the listener could not be registered with it.
+    Reactor(io::Error),
+
+    /// The `NewHandler` failed to create a `Handler`.
+    NewHandler(io::Error),
+}
+
+impl Display for StartError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            StartError::AddressResolution(ref e) => {
+                write!(f, "unable to resolve listener address: {}", e)
+            }
+            StartError::Bind(ref addr, ref e) => {
+                write!(f, "unable to open TCP listener on {}: {}", addr, e)
+            }
+            StartError::BindUnix(ref path, ref e) => write!(
+                f,
+                "unable to open Unix domain socket listener on {}: {}",
+                path.display(),
+                e
+            ),
+            StartError::NoListeners => write!(f, "no listeners were provided"),
+            StartError::InvalidThreadCount => write!(f, "at least one thread is required"),
+            StartError::Reactor(ref e) => write!(f, "unable to spawn tokio reactor: {}", e),
+            StartError::NewHandler(ref e) => write!(f, "unable to create handler: {}", e),
+        }
+    }
+}
+
+impl Error for StartError {
+    fn description(&self) -> &str {
+        match *self {
+            StartError::AddressResolution(_) => "unable to resolve listener address",
+            StartError::Bind(..) => "unable to open TCP listener",
+            StartError::BindUnix(..) => "unable to open Unix domain socket listener",
+            StartError::NoListeners => "no listeners were provided",
+            StartError::InvalidThreadCount => "at least one thread is required",
+            StartError::Reactor(_) => "unable to spawn tokio reactor",
+            StartError::NewHandler(_) => "unable to create handler",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            StartError::AddressResolution(ref e)
+            | StartError::Bind(_, ref e)
+            | StartError::BindUnix(_, ref e)
+            | StartError::Reactor(ref e)
+            | StartError::NewHandler(ref e) => Some(e),
+            StartError::NoListeners | StartError::InvalidThreadCount => None,
+        }
+    }
+}
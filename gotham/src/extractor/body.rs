@@ -0,0 +1,342 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str;
+
+use futures::{future, Future, Stream};
+use hyper::{Body, Headers, Response, StatusCode};
+use hyper::header::{ContentLength, ContentType};
+use mime;
+use serde::Deserialize;
+use serde_json;
+
+use extractor::internal;
+use extractor::refusal::Refusal;
+use http::request::query_string;
+use router::response::extender::StaticResponseExtender;
+use state::{FromState, State, StateData};
+
+/// The largest request body which `with_body_extractor` reads, in bytes. A larger body is
+/// refused with `413 Payload Too Large`. Routes which accept larger bodies can set their own
+/// limit with `with_body_extractor_limit`.
+pub const DEFAULT_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Defines a binding for storing the deserialized `Request` body in `State`. The body is read and
+/// deserialized after the pipelines of the route have been invoked, immediately before the
+/// `Handler`, according to the `Content-Type` of the request:
+///
+/// * `application/json`, and media types with a `+json` suffix, are deserialized as JSON.
+/// * `application/x-www-form-urlencoded` is deserialized in the same way as a query string, as
+///   for a `QueryStringExtractor`.
+///
+/// On failure, the request is refused with `415 Unsupported Media Type` for any other
+/// `Content-Type`, `413 Payload Too Large` for a body larger than the limit of the route, or `400
+/// Bad Request` for a body which can't be deserialized. As the body is read after the
+/// middleware of the pipelines, a smaller limit set by a `BodyLimitMiddleware` in the pipelines,
+/// or by `ServerBuilder::with_max_body_size`, also applies. The `RequestBodyError` describing the
+/// failure is stored in `State`, and the `StaticResponseExtender` implementation then extends the
+/// `Response`, so a manual implementation can change the status or add a body.
+///
+/// This trait is automatically implemented when the struct implements the `Deserialize`,
+/// `StateData` and `StaticResponseExtender` traits. These traits can be derived, or implemented
+/// manually for greater control.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// # extern crate serde;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{FromState, State};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Deserialize, StateData, StaticResponseExtender)]
+/// struct NewUser {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// fn handler(mut state: State) -> (State, Response) {
+///     let NewUser { name, age } = NewUser::take_from(&mut state);
+///     let body = format!("{} is {}", name, age);
+///
+///     let response = create_response(
+///         &state,
+///         StatusCode::Created,
+///         Some((body.into_bytes(), mime::TEXT_PLAIN)),
+///     );
+///
+///     (state, response)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .post("/users")
+///             .with_body_extractor::<NewUser>()
+///             .to(handler);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .post(
+/// #           "http://example.com/users",
+/// #           r#"{"name": "Alice", "age": 30}"#,
+/// #           mime::APPLICATION_JSON,
+/// #       )
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Created);
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "Alice is 30");
+/// #
+/// #   let response = test_server
+/// #       .client()
+/// #       .post(
+/// #           "http://example.com/users",
+/// #           "name=Bob&age=many",
+/// #           mime::APPLICATION_WWW_FORM_URLENCODED,
+/// #       )
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::BadRequest);
+/// # }
+/// ```
+pub trait RequestBodyExtractor
+    : for<'de> Deserialize<'de> + StaticResponseExtender + StateData {
+}
+
+impl<T> RequestBodyExtractor for T
+where
+    for<'de> T: Deserialize<'de> + StaticResponseExtender + StateData,
+{
+}
+
+/// Describes why a `RequestBodyExtractor` could not be extracted from the request body. This is
+/// stored in `State` before the `StaticResponseExtender` of the extractor is invoked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequestBodyError {
+    /// The `Content-Type` of the request is missing, or is not a supported media type.
+    UnsupportedMediaType,
+
+    /// The request body is larger than the limit of the route.
+    PayloadTooLarge,
+
+    /// The request body could not be read, or could not be deserialized.
+    Invalid(String),
+}
+
+impl RequestBodyError {
+    /// The status which the request is refused with, unless the `StaticResponseExtender` of the
+    /// extractor changes it.
+    pub fn status(&self) -> StatusCode {
+        match *self {
+            RequestBodyError::UnsupportedMediaType => StatusCode::UnsupportedMediaType,
+            RequestBodyError::PayloadTooLarge => StatusCode::PayloadTooLarge,
+            RequestBodyError::Invalid(_) => StatusCode::BadRequest,
+        }
+    }
+}
+
+impl Display for RequestBodyError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RequestBodyError::UnsupportedMediaType => out.write_str("unsupported media type"),
+            RequestBodyError::PayloadTooLarge => out.write_str("request body is too large"),
+            RequestBodyError::Invalid(ref message) => {
+                write!(out, "invalid request body: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for RequestBodyError {
+    fn description(&self) -> &str {
+        "request body extraction failed"
+    }
+}
+
+impl StateData for RequestBodyError {}
+
+/// The formats which a request body can be deserialized from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BodyFormat {
+    Json,
+    FormUrlEncoded,
+}
+
+impl BodyFormat {
+    fn from_headers(headers: &Headers) -> Option<BodyFormat> {
+        let mime = match headers.get::<ContentType>() {
+            Some(&ContentType(ref mime)) => mime,
+            None => return None,
+        };
+
+        if mime.type_() != mime::APPLICATION {
+            None
+        } else if mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON) {
+            Some(BodyFormat::Json)
+        } else if mime.subtype() == mime::WWW_FORM_URLENCODED {
+            Some(BodyFormat::FormUrlEncoded)
+        } else {
+            None
+        }
+    }
+}
+
+/// The `RequestBodyExtractor` of a route, which is run by the `DispatcherImpl` after the
+/// pipelines.
+#[derive(Clone, Copy)]
+pub(crate) struct BodyExtraction {
+    limit: usize,
+    deserialize: fn(&mut State, BodyFormat, &[u8]) -> Result<(), RequestBodyError>,
+    refusal: Refusal,
+}
+
+impl BodyExtraction {
+    /// Creates a `BodyExtraction` for `T`, which refuses bodies larger than `limit` bytes.
+    pub(crate) fn new<T>(limit: usize) -> BodyExtraction
+    where
+        T: RequestBodyExtractor,
+    {
+        BodyExtraction {
+            limit,
+            deserialize: deserialize::<T>,
+            refusal: Refusal::new::<T>(),
+        }
+    }
+
+    /// Reads and deserializes the request body, storing the result in `State`. On failure, the
+    /// response refusing the request is returned instead.
+    pub(crate) fn extract(
+        self,
+        mut state: State,
+    ) -> Box<Future<Item = State, Error = (State, Response)>> {
+        let format = match BodyFormat::from_headers(Headers::borrow_from(&state)) {
+            Some(format) => format,
+            None => {
+                let error = RequestBodyError::UnsupportedMediaType;
+                return Box::new(future::err(self.refuse(state, error)));
+            }
+        };
+
+        let content_length = Headers::borrow_from(&state)
+            .get::<ContentLength>()
+            .map(|&ContentLength(len)| len);
+        if content_length.map_or(false, |len| len > self.limit as u64) {
+            return Box::new(future::err(self.refuse(state, RequestBodyError::PayloadTooLarge)));
+        }
+
+        let body = state.try_take::<Body>().unwrap_or_else(Body::empty);
+        let limit = self.limit;
+        let f = body.map_err(invalid)
+            .fold(Vec::new(), move |mut bytes, chunk| {
+                if bytes.len() + chunk.len() > limit {
+                    return Err(RequestBodyError::PayloadTooLarge);
+                }
+
+                bytes.extend_from_slice(&chunk);
+                Ok(bytes)
+            })
+            .then(move |result| {
+                match result.and_then(|bytes| (self.deserialize)(&mut state, format, &bytes)) {
+                    Ok(()) => Ok(state),
+                    Err(e) => Err(self.refuse(state, e)),
+                }
+            });
+
+        Box::new(f)
+    }
+
+    /// Creates the response refusing a request because of `error`.
+    fn refuse(&self, state: State, error: RequestBodyError) -> (State, Response) {
+        self.refusal.refuse(state, error.status(), error)
+    }
+}
+
+fn deserialize<T>(
+    state: &mut State,
+    format: BodyFormat,
+    bytes: &[u8],
+) -> Result<(), RequestBodyError>
+where
+    T: RequestBodyExtractor,
+{
+    let result = match format {
+        BodyFormat::Json => serde_json::from_slice::<T>(bytes).map_err(invalid),
+        BodyFormat::FormUrlEncoded => {
+            let body = str::from_utf8(bytes).map_err(invalid)?;
+            let mapping = query_string::split(Some(body));
+            internal::from_query_string_mapping::<T>(&mapping).map_err(invalid)
+        }
+    };
+
+    result.map(|val| state.put(val))
+}
+
+fn invalid<E>(e: E) -> RequestBodyError
+where
+    E: Display,
+{
+    RequestBodyError::Invalid(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::Async;
+    use hyper::header::ContentType;
+
+    use extractor::fixtures::{request_state, NewUser};
+
+    fn extract(limit: usize, content_type: &str, body: &str) -> Result<State, StatusCode> {
+        let mut headers = Headers::new();
+        headers.set(ContentType(content_type.parse().unwrap()));
+        let state = request_state(headers, Body::from(body.to_owned()));
+
+        match BodyExtraction::new::<NewUser>(limit).extract(state).poll() {
+            Ok(Async::Ready(state)) => Ok(state),
+            Ok(Async::NotReady) => panic!("expected future to be completed already"),
+            Err((_state, res)) => Err(res.status()),
+        }
+    }
+
+    #[test]
+    fn body_formats_are_chosen_by_content_type() {
+        let json = r#"{"name":"a","age":1}"#;
+        let mut state = extract(64, "application/json", json).unwrap();
+        assert_eq!(state.take::<NewUser>().name, "a");
+
+        let form = "application/x-www-form-urlencoded";
+        let mut state = extract(64, form, "name=b%21&age=2").unwrap();
+        let user = state.take::<NewUser>();
+        assert_eq!((user.name.as_str(), user.age), ("b!", 2));
+
+        assert_eq!(
+            extract(64, "text/json", json).err(),
+            Some(StatusCode::UnsupportedMediaType)
+        );
+    }
+
+    #[test]
+    fn bodies_without_content_length_are_limited() {
+        let json = r#"{"name":"a","age":1}"#;
+        assert!(extract(json.len(), "application/json", json).is_ok());
+        assert_eq!(
+            extract(json.len() - 1, "application/json", json).err(),
+            Some(StatusCode::PayloadTooLarge)
+        );
+    }
+}
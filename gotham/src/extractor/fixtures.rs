@@ -0,0 +1,67 @@
+//! Extractors and a handler shared by the tests of the header and body extractors, and of the
+//! routes which use them.
+
+use hyper::{Body, Headers, Response, StatusCode};
+
+use extractor::{optional_header, required_header, HeaderExtractor, HeaderExtractorError};
+use router::response::extender::StaticResponseExtender;
+use state::{set_request_id, State, StateData};
+
+/// A `RequestBodyExtractor`, which is implemented for any deserializable `StateData`.
+#[derive(Deserialize)]
+pub(crate) struct NewUser {
+    pub(crate) name: String,
+    pub(crate) age: u8,
+}
+
+impl StateData for NewUser {}
+
+impl StaticResponseExtender for NewUser {
+    fn extend(_: &mut State, _: &mut Response) {}
+}
+
+/// A `HeaderExtractor` which reads a required and an optional header, as the implementation
+/// derived by `#[derive(HeaderExtractor)]` does for `String` and `Option<u32>` fields.
+pub(crate) struct ApiHeaders {
+    pub(crate) key: String,
+    pub(crate) page: Option<u32>,
+}
+
+impl HeaderExtractor for ApiHeaders {
+    fn extract(headers: &Headers) -> Result<Self, HeaderExtractorError> {
+        Ok(ApiHeaders {
+            key: required_header(headers, "Authorization")?,
+            page: optional_header(headers, "X-Page")?,
+        })
+    }
+}
+
+impl StateData for ApiHeaders {}
+
+impl StaticResponseExtender for ApiHeaders {
+    fn extend(_: &mut State, _: &mut Response) {}
+}
+
+/// Creates the `State` of a request with the given headers and body.
+pub(crate) fn request_state(headers: Headers, body: Body) -> State {
+    let mut state = State::new();
+    state.put(headers);
+    state.put(body);
+    set_request_id(&mut state);
+    state
+}
+
+/// Describes the `NewUser` extracted from the request body, and the key from the `ApiHeaders` if
+/// the route has a header extractor.
+pub(crate) fn create_user(mut state: State) -> (State, Response) {
+    let user = state.take::<NewUser>();
+    let body = match state.try_take::<ApiHeaders>() {
+        Some(headers) => format!("{} is {}, by {}", user.name, user.age, headers.key),
+        None => format!("{} is {}", user.name, user.age),
+    };
+
+    let response = Response::new()
+        .with_status(StatusCode::Created)
+        .with_body(body);
+    (state, response)
+}
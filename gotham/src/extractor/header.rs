@@ -0,0 +1,261 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::{self, FromStr};
+
+use hyper::{Headers, Response, StatusCode};
+
+use extractor::refusal::Refusal;
+use router::response::extender::StaticResponseExtender;
+use state::{FromState, State, StateData};
+
+/// Defines a binding for storing values from the `Request` headers in `State`. The extractor is
+/// created before the request is dispatched to the pipelines and `Handler` of the route, and a
+/// request with a required header which is missing or can't be parsed is refused with `400 Bad
+/// Request`. The `HeaderExtractorError` describing the failure is stored in `State`, and the
+/// `StaticResponseExtender` implementation then extends the `Response`.
+///
+/// This trait is usually derived with `#[derive(HeaderExtractor)]` from `gotham_derive`, which
+/// maps each field of a struct to a header:
+///
+/// * The header name is the field name with each word capitalized and joined by `-`, so `api_key`
+///   is read from `Api-Key`. Header names are not case sensitive. A different name can be given
+///   with `#[header(rename = "X-Api-Key")]`.
+/// * The value is parsed with `FromStr`. A header which is given more than once is combined into
+///   a single value, separated by `, `.
+/// * Fields of type `Option<T>` are optional, and are `None` when the header is missing.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # #[macro_use]
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{FromState, State};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::test::TestServer;
+/// #
+/// # header! { (XApiKey, "X-Api-Key") => [String] }
+/// # header! { (XPage, "X-Page") => [u32] }
+/// #
+/// #[derive(HeaderExtractor, StateData, StaticResponseExtender)]
+/// struct ApiHeaders {
+///     #[header(rename = "X-Api-Key")]
+///     key: String,
+///     #[header(rename = "X-Page")]
+///     page: Option<u32>,
+/// }
+///
+/// fn handler(state: State) -> (State, Response) {
+///     let page = {
+///         let headers = ApiHeaders::borrow_from(&state);
+/// #       assert_eq!(headers.key, "secret");
+///         headers.page.unwrap_or(1)
+///     };
+///
+///     let res = Response::new().with_body(format!("page {}", page));
+///     (state, res)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route
+///             .get("/items")
+///             .with_header_extractor::<ApiHeaders>()
+///             .to(handler);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://example.com/items")
+/// #       .with_header(XApiKey("secret".to_owned()))
+/// #       .with_header(XPage(2))
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Ok);
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "page 2");
+/// #
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://example.com/items")
+/// #       .with_header(XPage(2))
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::BadRequest);
+/// # }
+/// ```
+pub trait HeaderExtractor: StaticResponseExtender + StateData + Sized {
+    /// Creates the extractor from the request headers.
+    fn extract(headers: &Headers) -> Result<Self, HeaderExtractorError>;
+}
+
+/// Describes why a `HeaderExtractor` could not be created from the request headers. This is
+/// stored in `State` before the `StaticResponseExtender` of the extractor is invoked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeaderExtractorError {
+    /// The named header is required, but was not given.
+    Missing(String),
+
+    /// The value of the named header could not be parsed.
+    Invalid(String),
+}
+
+impl Display for HeaderExtractorError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeaderExtractorError::Missing(ref name) => write!(out, "missing header `{}`", name),
+            HeaderExtractorError::Invalid(ref name) => write!(out, "invalid header `{}`", name),
+        }
+    }
+}
+
+impl Error for HeaderExtractorError {
+    fn description(&self) -> &str {
+        "header extraction failed"
+    }
+}
+
+impl StateData for HeaderExtractorError {}
+
+/// Parses the value of the header `name`, which is required. This is used by
+/// `#[derive(HeaderExtractor)]`, and is useful when implementing `HeaderExtractor` manually.
+pub fn required_header<T>(headers: &Headers, name: &str) -> Result<T, HeaderExtractorError>
+where
+    T: FromStr,
+{
+    optional_header(headers, name)?.ok_or_else(|| HeaderExtractorError::Missing(name.to_owned()))
+}
+
+/// Parses the value of the header `name`, or returns `None` if it was not given.
+pub fn optional_header<T>(headers: &Headers, name: &str) -> Result<Option<T>, HeaderExtractorError>
+where
+    T: FromStr,
+{
+    let raw = match headers.get_raw(name) {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
+
+    let invalid = || HeaderExtractorError::Invalid(name.to_owned());
+    let lines = raw.iter()
+        .map(|line| str::from_utf8(line).map(str::trim))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+
+    lines.join(", ").parse().map(Some).map_err(|_| invalid())
+}
+
+/// The `HeaderExtractor` of a route, which is run by the `RouteImpl` before the pipelines.
+#[derive(Clone, Copy)]
+pub(crate) struct HeaderExtraction {
+    extract: fn(&mut State) -> Result<(), HeaderExtractorError>,
+    refusal: Refusal,
+}
+
+impl HeaderExtraction {
+    /// Creates a `HeaderExtraction` for `T`.
+    pub(crate) fn new<T>() -> HeaderExtraction
+    where
+        T: HeaderExtractor,
+    {
+        HeaderExtraction {
+            extract: extract::<T>,
+            refusal: Refusal::new::<T>(),
+        }
+    }
+
+    /// Creates the extractor and stores it in `State`. On failure, the request is refused with
+    /// `400 Bad Request`.
+    pub(crate) fn extract(self, mut state: State) -> Result<State, (State, Response)> {
+        match (self.extract)(&mut state) {
+            Ok(()) => Ok(state),
+            Err(error) => Err(self.refusal.refuse(state, StatusCode::BadRequest, error)),
+        }
+    }
+}
+
+fn extract<T>(state: &mut State) -> Result<(), HeaderExtractorError>
+where
+    T: HeaderExtractor,
+{
+    let val = T::extract(Headers::borrow_from(state))?;
+    state.put(val);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Body;
+
+    use extractor::fixtures::{request_state, ApiHeaders};
+
+    fn extract(headers: Headers) -> Result<ApiHeaders, HeaderExtractorError> {
+        HeaderExtraction::new::<ApiHeaders>()
+            .extract(request_state(headers, Body::empty()))
+            .map(|mut state| state.take::<ApiHeaders>())
+            .map_err(|(mut state, res)| {
+                assert_eq!(res.status(), StatusCode::BadRequest);
+                state.take::<HeaderExtractorError>()
+            })
+    }
+
+    #[test]
+    fn headers_are_extracted() {
+        let mut headers = Headers::new();
+        headers.set_raw("authorization", "k1");
+        headers.set_raw("x-page", " 3 ");
+
+        let api = extract(headers).ok().unwrap();
+        assert_eq!(api.key, "k1");
+        assert_eq!(api.page, Some(3));
+    }
+
+    #[test]
+    fn optional_headers_may_be_missing() {
+        let mut headers = Headers::new();
+        headers.set_raw("Authorization", "k1");
+
+        assert_eq!(extract(headers).ok().unwrap().page, None);
+    }
+
+    #[test]
+    fn missing_headers_are_refused() {
+        let mut headers = Headers::new();
+        headers.set_raw("X-Page", "3");
+
+        assert_eq!(
+            extract(headers).err(),
+            Some(HeaderExtractorError::Missing("Authorization".to_owned()))
+        );
+    }
+
+    #[test]
+    fn unparseable_headers_are_refused() {
+        let mut headers = Headers::new();
+        headers.set_raw("Authorization", "k1");
+        headers.set_raw("X-Page", "three");
+        assert_eq!(
+            extract(headers).err(),
+            Some(HeaderExtractorError::Invalid("X-Page".to_owned()))
+        );
+
+        // Repeated headers are combined, which is not a valid number.
+        let mut headers = Headers::new();
+        headers.set_raw("Authorization", "k1");
+        headers.append_raw("X-Page", "3");
+        headers.append_raw("X-Page", "4");
+        assert_eq!(
+            extract(headers).err(),
+            Some(HeaderExtractorError::Invalid("X-Page".to_owned()))
+        );
+    }
+}
@@ -2,6 +2,7 @@
 //! type is populated by the `Router` while traversing the tree, and the `Route` implementation
 //! performs deserialization before dispatching to the `Handler`.
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
@@ -258,6 +259,18 @@ where
     from_data_source(IteratorAdaptor { iter })
 }
 
+/// Deserializes a value of type `T` from a set of form fields whose values have already been
+/// decoded, such as the text fields of a `multipart/form-data` body.
+pub(crate) fn from_form_fields<'de, T>(
+    fields: &'de HashMap<String, Vec<String>>,
+) -> Result<T, ExtractorError>
+where
+    T: Deserialize<'de>,
+{
+    let iter = fields.iter().map(|(k, v)| (k.as_str(), v));
+    from_data_source(IteratorAdaptor { iter })
+}
+
 /// Implements a `Deserializer` for the full set of extracted path segments. This is the top level
 /// of the serde side of path extraction. Primarily, we're only checking that we're deserializing
 /// into a supported type. In the "normal" case, `deserialize_struct` is the only thing invoked
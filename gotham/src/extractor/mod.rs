@@ -1,7 +1,8 @@
 //! Extracts request data into type-safe structs using Serde.
 //!
-//! Extractors are added to route definitions when defining a `Router`. The `PathExtractor` and
-//! `QueryStringExtractor` traits provide usage examples.
+//! Extractors are added to route definitions when defining a `Router`. The `PathExtractor`,
+//! `QueryStringExtractor`, `HeaderExtractor` and `RequestBodyExtractor` traits provide usage
+//! examples.
 //!
 //! The request data is extracted by the `Route` implementation when dispatching the request. The
 //! application-provided data structure which implements the extractor trait is used to deserialize
@@ -10,7 +11,18 @@
 
 mod query_string;
 mod path;
+mod header;
+mod body;
+mod refusal;
 pub(crate) mod internal;
+#[cfg(test)]
+pub(crate) mod fixtures;
 
 pub use self::query_string::*;
 pub use self::path::*;
+pub use self::header::{optional_header, required_header, HeaderExtractor,
+                       HeaderExtractorError};
+pub use self::body::{RequestBodyError, RequestBodyExtractor, DEFAULT_BODY_LIMIT};
+
+pub(crate) use self::header::HeaderExtraction;
+pub(crate) use self::body::BodyExtraction;
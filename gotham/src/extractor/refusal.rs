@@ -0,0 +1,46 @@
+//! Defines `Refusal`, which responds to a request whose headers or body could not be extracted.
+
+use std::fmt::Display;
+
+use hyper::{Response, StatusCode};
+
+use http::response::create_response;
+use router::response::extender::StaticResponseExtender;
+use state::{request_id, State, StateData};
+
+/// Refuses a request for which a `HeaderExtractor` or `RequestBodyExtractor` could not be
+/// extracted. The `StaticResponseExtender` of the extractor is chosen when the route is built, and
+/// its type is erased so that routes with different extractors can be held together.
+#[derive(Clone, Copy)]
+pub(crate) struct Refusal {
+    extend: fn(&mut State, &mut Response),
+}
+
+impl Refusal {
+    /// Creates a `Refusal` which extends the response with `T`.
+    pub(crate) fn new<T>() -> Refusal
+    where
+        T: StaticResponseExtender,
+    {
+        Refusal { extend: T::extend }
+    }
+
+    /// Creates the response refusing the request with `status`. The `error` describing why is
+    /// stored in `State` before the `StaticResponseExtender` extends the response.
+    pub(crate) fn refuse<E>(
+        self,
+        mut state: State,
+        status: StatusCode,
+        error: E,
+    ) -> (State, Response)
+    where
+        E: Display + StateData,
+    {
+        debug!("[{}] extractor failed: {}", request_id(&state), error);
+
+        let mut res = create_response(&state, status, None);
+        state.put(error);
+        (self.extend)(&mut state, &mut res);
+        (state, res)
+    }
+}
@@ -0,0 +1,283 @@
+//! Defines `CookieJar`, which holds the cookies of a request and the changes made to them while
+//! handling it.
+
+use cookie;
+use hyper::Headers;
+use hyper::header::{Cookie as CookieHeader, SetCookie};
+
+use state::StateData;
+
+pub use cookie::{Cookie, CookieBuilder, Key, PrivateJar, SameSite, SignedJar};
+
+/// The types of the `Max-Age` and `Expires` attributes of a `Cookie`, from the version of the
+/// `time` crate used by `Cookie`, so that an application doesn't need to depend on it directly.
+pub mod time {
+    pub use time::{Duration, OffsetDateTime};
+}
+
+/// The cookies sent with a request, and the cookies added or removed while handling it.
+///
+/// The `Router` stores a `CookieJar` in `State` before dispatching the request, populated from
+/// the `Cookie` header. Every cookie which is added to or removed from the jar is sent to the
+/// client in a `Set-Cookie` header when the `Router` finalizes the response, after any
+/// `Set-Cookie` headers which were already added to the response, such as the session cookie of
+/// the `SessionMiddleware`. A `Router` which is delegated to uses the `CookieJar` of the `Router`
+/// delegating to it.
+///
+/// Cookies are created with `Cookie::new` or `Cookie::build`, which also sets attributes such as
+/// `Max-Age`, with a `time::Duration`, and `Expires`, with a `time::OffsetDateTime`.
+///
+/// When the `Router` is given a `Key` with `RouterBuilder::set_cookie_key`, cookies can also be
+/// signed with `signed`, so that the client can read but not change their values, or encrypted
+/// with `private`, so that the client can neither read nor change them.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::{Cookie as CookieHeader, SetCookie};
+/// # use gotham::http::cookie::{Cookie, CookieJar, Key};
+/// # use gotham::http::cookie::time::{Duration, OffsetDateTime};
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(mut state: State) -> (State, Response) {
+///     let visits = {
+///         let jar = CookieJar::borrow_mut_from(&mut state);
+///         let visits = jar.signed()
+///             .get("visits")
+///             .and_then(|cookie| cookie.value().parse().ok())
+///             .unwrap_or(0) + 1;
+///
+///         let cookie = Cookie::build("visits", visits.to_string())
+///             .max_age(Duration::days(7))
+///             .http_only(true)
+///             .finish();
+///         jar.signed().add(cookie);
+///
+///         let cookie = Cookie::build("consent", "yes")
+///             .expires(OffsetDateTime::from_unix_timestamp(2_000_000_000))
+///             .finish();
+///         jar.add(cookie);
+///         jar.remove(Cookie::named("legacy"));
+///         visits
+///     };
+///
+///     let res = Response::new().with_body(format!("visit {}", visits));
+///     (state, res)
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.set_cookie_key(Key::from_master(&[0x2a; 32]));
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let mut cookies = CookieHeader::new();
+/// cookies.append("legacy", "1");
+///
+/// let response = test_server.client()
+///     .get("http://example.com/")
+///     .with_header(cookies)
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.status(), StatusCode::Ok);
+///
+/// let set_cookie = response.headers().get::<SetCookie>().unwrap().clone();
+/// assert_eq!(set_cookie.len(), 3);
+/// assert!(set_cookie.iter().any(|cookie| cookie.starts_with("legacy=;")));
+/// assert!(set_cookie.iter().any(|cookie| {
+///     cookie == "consent=yes; Expires=Wed, 18 May 2033 03:33:20 GMT"
+/// }));
+///
+/// let signed = set_cookie.iter().find(|cookie| cookie.contains("visits=")).unwrap();
+/// assert!(signed.contains("Max-Age=604800"));
+///
+/// let (name, value) = {
+///     let pair = signed.split(';').next().unwrap();
+///     let mut parts = pair.splitn(2, '=');
+///     (parts.next().unwrap().to_owned(), parts.next().unwrap().to_owned())
+/// };
+/// let mut cookies = CookieHeader::new();
+/// cookies.append(name, value);
+///
+/// let response = test_server.client()
+///     .get("http://example.com/")
+///     .with_header(cookies)
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.read_utf8_body().unwrap(), "visit 2");
+/// # }
+/// ```
+pub struct CookieJar {
+    jar: cookie::CookieJar,
+    key: Option<Key>,
+}
+
+impl CookieJar {
+    /// Creates a `CookieJar` holding the cookies from the `Cookie` header in `headers`, which uses
+    /// `key` for signed and private cookies.
+    pub(crate) fn from_headers(headers: &Headers, key: Option<Key>) -> CookieJar {
+        let mut jar = cookie::CookieJar::new();
+
+        if let Some(cookies) = headers.get::<CookieHeader>() {
+            for (name, value) in cookies.iter() {
+                jar.add_original(Cookie::new(name.to_owned(), value.to_owned()));
+            }
+        }
+
+        CookieJar { jar, key }
+    }
+
+    /// Returns the cookie named `name`, whether it was sent with the request or added since.
+    /// Cookies which have been removed are not returned.
+    pub fn get(&self, name: &str) -> Option<&Cookie<'static>> {
+        self.jar.get(name)
+    }
+
+    /// Adds `cookie`, replacing any cookie with the same name. The cookie is sent to the client.
+    pub fn add(&mut self, cookie: Cookie<'static>) {
+        self.jar.add(cookie)
+    }
+
+    /// Removes the cookie with the name of `cookie`. When the cookie was sent with the request,
+    /// the client is told to remove it, using the `Path` and `Domain` of `cookie`, which must
+    /// match the ones it was added with.
+    pub fn remove(&mut self, cookie: Cookie<'static>) {
+        self.jar.remove(cookie)
+    }
+
+    /// Iterates over the cookies in the jar, which excludes cookies which have been removed.
+    pub fn iter(&self) -> cookie::Iter {
+        self.jar.iter()
+    }
+
+    /// Returns a view of the jar in which cookies are signed when added, and verified when
+    /// read, so that a cookie which was changed by the client is not returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Router` was not given a `Key` with `RouterBuilder::set_cookie_key`.
+    pub fn signed(&mut self) -> SignedJar {
+        let key = self.key.as_ref().expect(NO_KEY);
+        self.jar.signed(key)
+    }
+
+    /// Returns a view of the jar in which cookies are encrypted when added, and decrypted and
+    /// verified when read, so that their values are hidden from the client and can't be changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Router` was not given a `Key` with `RouterBuilder::set_cookie_key`.
+    pub fn private(&mut self) -> PrivateJar {
+        let key = self.key.as_ref().expect(NO_KEY);
+        self.jar.private(key)
+    }
+
+    /// Adds a `Set-Cookie` header for each cookie which was added or removed.
+    pub(crate) fn write_changes(&self, headers: &mut Headers) {
+        let changes = self.jar
+            .delta()
+            .map(|cookie| cookie.to_string())
+            .collect::<Vec<_>>();
+
+        if changes.is_empty() {
+            return;
+        }
+
+        if let Some(existing) = headers.get_mut::<SetCookie>() {
+            existing.extend(changes);
+            return;
+        }
+
+        headers.set(SetCookie(changes));
+    }
+}
+
+impl StateData for CookieJar {}
+
+const NO_KEY: &str = "signed and private cookies require a key, which is set with \
+                      `RouterBuilder::set_cookie_key`";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jar(cookies: &[(&str, &str)], key: Option<Key>) -> CookieJar {
+        let mut header = CookieHeader::new();
+        for &(name, value) in cookies {
+            header.append(name.to_owned(), value.to_owned());
+        }
+
+        let mut headers = Headers::new();
+        headers.set(header);
+        CookieJar::from_headers(&headers, key)
+    }
+
+    fn changes(jar: &CookieJar) -> Vec<String> {
+        let mut headers = Headers::new();
+        headers.set(SetCookie(vec!["existing=1".to_owned()]));
+        jar.write_changes(&mut headers);
+
+        let mut changes = headers.get::<SetCookie>().unwrap().0.clone();
+        changes.sort();
+        changes
+    }
+
+    #[test]
+    fn only_changes_are_written() {
+        let mut jar = jar(&[("a", "1"), ("b", "2")], None);
+        assert_eq!(jar.get("a").map(Cookie::value), Some("1"));
+        assert_eq!(changes(&jar), vec!["existing=1"]);
+
+        jar.add(Cookie::build("c", "3").path("/").finish());
+        jar.remove(Cookie::named("a"));
+        assert!(jar.get("a").is_none());
+        assert_eq!(jar.iter().count(), 2);
+
+        let changes = changes(&jar);
+        assert_eq!(changes.len(), 3);
+        assert!(changes[0].starts_with("a=; Max-Age=0;"));
+        assert_eq!(changes[1], "c=3; Path=/");
+        assert_eq!(changes[2], "existing=1");
+    }
+
+    #[test]
+    fn signed_and_private_cookies_are_verified() {
+        let key = Key::from_master(&[7; 32]);
+        let mut written = jar(&[], Some(key.clone()));
+        written.signed().add(Cookie::new("signed", "a"));
+        written.private().add(Cookie::new("private", "b"));
+
+        let signed = written.get("signed").unwrap().value().to_owned();
+        let private = written.get("private").unwrap().value().to_owned();
+        assert!(signed.ends_with("a"));
+        assert_ne!(private, "b");
+
+        let mut read = jar(
+            &[("signed", &signed), ("private", &private)],
+            Some(key.clone()),
+        );
+        assert_eq!(read.signed().get("signed").unwrap().value(), "a");
+        assert_eq!(read.private().get("private").unwrap().value(), "b");
+
+        let tampered = format!("{}b", &signed[..signed.len() - 1]);
+        let mut read = jar(&[("signed", &tampered), ("private", "b")], Some(key));
+        assert!(read.signed().get("signed").is_none());
+        assert!(read.private().get("private").is_none());
+
+        let mut read = jar(&[("signed", &signed)], Some(Key::from_master(&[8; 32])));
+        assert!(read.signed().get("signed").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "RouterBuilder::set_cookie_key")]
+    fn signed_cookies_require_a_key() {
+        jar(&[], None).signed();
+    }
+}
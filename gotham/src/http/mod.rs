@@ -3,6 +3,7 @@
 pub mod request;
 pub mod response;
 pub mod header;
+pub mod cookie;
 
 use std;
 use url::percent_encoding::percent_decode;
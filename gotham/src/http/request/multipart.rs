@@ -0,0 +1,746 @@
+//! Defines a parser for `multipart/form-data` request bodies, which streams each part of the body
+//! without buffering the whole request.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str;
+use std::sync::{Arc, Mutex};
+
+use futures::{Async, Future, Poll, Stream};
+use hyper::{self, Body, Chunk, Headers, StatusCode};
+use hyper::header::ContentType;
+use mime::{self, Mime};
+use serde::Deserialize;
+
+use extractor::internal;
+use state::{FromState, State};
+
+/// The largest part which a `Multipart` accepts by default, in bytes.
+pub const DEFAULT_PART_LIMIT: usize = 16 * 1024 * 1024;
+
+/// The largest body which a `Multipart` accepts by default, in bytes.
+pub const DEFAULT_TOTAL_LIMIT: usize = 64 * 1024 * 1024;
+
+/// The largest header section of a single part, in bytes.
+const HEADERS_LIMIT: usize = 8 * 1024;
+
+/// A `Stream` of the parts of a `multipart/form-data` request body.
+///
+/// Each `Part` is yielded once its headers have been read, and its body is then read as a
+/// `Stream` of `Chunk` values, so a large upload can be written to disk without being held in
+/// memory. The body of a part must be read before the next part is yielded, and any part of it
+/// which isn't read is skipped.
+///
+/// A part larger than the part limit is refused with `MultipartError::PartTooLarge`, and a body
+/// larger than the total limit with `MultipartError::PayloadTooLarge`. Both limits are counted
+/// while the body is read, so they don't depend on a `Content-Length` header.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use futures::{future, Future, Stream};
+/// # use hyper::StatusCode;
+/// # use gotham::handler::HandlerFuture;
+/// # use gotham::http::request::multipart::{Multipart, MultipartError};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn upload(mut state: State) -> Box<HandlerFuture> {
+///     let multipart = match Multipart::from_state(&mut state) {
+///         Ok(multipart) => multipart.with_part_limit(1024),
+///         Err(e) => {
+///             let res = create_response(&state, e.status(), None);
+///             return Box::new(future::ok((state, res)));
+///         }
+///     };
+///
+///     let f = multipart
+///         .and_then(|part| {
+///             let name = part.name().unwrap_or("").to_owned();
+///
+///             // Each chunk could be written to a file here instead.
+///             part.into_body()
+///                 .fold(0, |len, chunk| Ok::<_, MultipartError>(len + chunk.len()))
+///                 .map(move |len| format!("{}: {} bytes", name, len))
+///         })
+///         .collect()
+///         .then(move |result| {
+///             let res = match result {
+///                 Ok(lines) => {
+///                     let body = lines.join("\n").into_bytes();
+///                     create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)))
+///                 }
+///                 Err(e) => create_response(&state, e.status(), None),
+///             };
+///             Ok((state, res))
+///         });
+///
+///     Box::new(f)
+/// }
+/// #
+/// # fn main() {
+/// #   let router = build_simple_router(|route| route.post("/upload").to(upload));
+/// #   let test_server = TestServer::new(router).unwrap();
+/// #   let body = "--XYZ\r\n\
+/// #               Content-Disposition: form-data; name=\"title\"\r\n\
+/// #               \r\n\
+/// #               Holiday\r\n\
+/// #               --XYZ\r\n\
+/// #               Content-Disposition: form-data; name=\"photo\"; filename=\"beach.jpg\"\r\n\
+/// #               Content-Type: image/jpeg\r\n\
+/// #               \r\n\
+/// #               0123456789\r\n\
+/// #               --XYZ--\r\n";
+/// #   let response = test_server
+/// #       .client()
+/// #       .post(
+/// #           "http://example.com/upload",
+/// #           body,
+/// #           "multipart/form-data; boundary=XYZ".parse().unwrap(),
+/// #       )
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Ok);
+/// #   assert_eq!(
+/// #       response.read_utf8_body().unwrap(),
+/// #       "title: 7 bytes\nphoto: 10 bytes"
+/// #   );
+/// # }
+/// ```
+pub struct Multipart {
+    parser: Arc<Mutex<Parser>>,
+}
+
+impl Multipart {
+    /// Creates a `Multipart` which reads the parts of `body`, which are separated by `boundary`.
+    pub fn new(body: Body, boundary: &str) -> Multipart {
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+
+        let parser = Parser {
+            body,
+            eof: false,
+            // The first delimiter may not be preceded by a line break, so one is added to
+            // allow it to be found in the same way as the others.
+            buf: b"\r\n".to_vec(),
+            delimiter,
+            stage: Stage::Preamble,
+            part: 0,
+            part_len: 0,
+            total_len: 0,
+            part_limit: DEFAULT_PART_LIMIT,
+            total_limit: DEFAULT_TOTAL_LIMIT,
+        };
+
+        Multipart {
+            parser: Arc::new(Mutex::new(parser)),
+        }
+    }
+
+    /// Takes the request `Body` from `State`, and creates a `Multipart` using the boundary given
+    /// in the `Content-Type` of the request.
+    ///
+    /// Returns `MultipartError::UnsupportedMediaType` when the request doesn't have a
+    /// `multipart/form-data` body with a boundary, in which case `State` is unchanged.
+    pub fn from_state(state: &mut State) -> Result<Multipart, MultipartError> {
+        let boundary = boundary(Headers::borrow_from(state))
+            .ok_or(MultipartError::UnsupportedMediaType)?;
+        let body = state.try_take::<Body>().unwrap_or_else(Body::empty);
+        Ok(Multipart::new(body, &boundary))
+    }
+
+    /// Sets the largest part which is accepted, in bytes. This includes text fields.
+    pub fn with_part_limit(self, limit: usize) -> Multipart {
+        self.parser.lock().unwrap().part_limit = limit;
+        self
+    }
+
+    /// Sets the largest body which is accepted, in bytes. This includes the headers of each part
+    /// and the boundaries between them.
+    pub fn with_total_limit(self, limit: usize) -> Multipart {
+        self.parser.lock().unwrap().total_limit = limit;
+        self
+    }
+
+    /// Reads every part, and deserializes the text fields into `T` in the same way as a
+    /// `QueryStringExtractor`. The bodies of file uploads, which are the parts with a filename,
+    /// are skipped. Use `MultipartFields` to handle file uploads and text fields together.
+    pub fn read_fields<T>(self) -> Box<Future<Item = T, Error = MultipartError> + Send>
+    where
+        T: for<'de> Deserialize<'de> + Send + 'static,
+    {
+        let f = self.fold(MultipartFields::new(), |mut fields, part| {
+            let f: Box<Future<Item = MultipartFields, Error = MultipartError> + Send> =
+                match (part.name().map(str::to_owned), part.filename().is_some()) {
+                    (Some(name), false) => Box::new(part.read_to_string().map(move |value| {
+                        fields.insert(&name, value);
+                        fields
+                    })),
+                    _ => Box::new(part.into_body().for_each(|_| Ok(())).map(|()| fields)),
+                };
+            f
+        }).and_then(|fields| fields.deserialize());
+
+        Box::new(f)
+    }
+}
+
+impl Stream for Multipart {
+    type Item = Part;
+    type Error = MultipartError;
+
+    fn poll(&mut self) -> Poll<Option<Part>, MultipartError> {
+        let mut parser = self.parser.lock().unwrap();
+        match try_ready!(parser.poll_part()) {
+            Some(headers) => Ok(Async::Ready(Some(Part::new(
+                headers,
+                PartBody {
+                    parser: self.parser.clone(),
+                    part: parser.part,
+                },
+            )))),
+            None => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// A single part of a `multipart/form-data` body, which is yielded by `Multipart`.
+pub struct Part {
+    headers: Headers,
+    name: Option<String>,
+    filename: Option<String>,
+    body: PartBody,
+}
+
+impl Part {
+    fn new(headers: Headers, body: PartBody) -> Part {
+        let (name, filename) = {
+            let disposition = headers
+                .get_raw("Content-Disposition")
+                .and_then(|raw| raw.one())
+                .and_then(|value| str::from_utf8(value).ok());
+
+            match disposition {
+                Some(disposition) => (
+                    disposition_param(disposition, "name"),
+                    disposition_param(disposition, "filename"),
+                ),
+                None => (None, None),
+            }
+        };
+
+        Part {
+            headers,
+            name,
+            filename,
+            body,
+        }
+    }
+
+    /// The headers of this part.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// The name of the form field, from the `Content-Disposition` header of this part.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    /// The filename of an uploaded file, from the `Content-Disposition` header of this part. This
+    /// is provided by the client, so it shouldn't be used as a path without being checked.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_ref().map(String::as_str)
+    }
+
+    /// The media type from the `Content-Type` header of this part, or `None` when the part has no
+    /// such header, in which case RFC 7578 defines its media type as `text/plain`.
+    pub fn content_type(&self) -> Option<Mime> {
+        self.headers
+            .get::<ContentType>()
+            .map(|&ContentType(ref mime)| mime.clone())
+    }
+
+    /// The body of this part.
+    pub fn into_body(self) -> PartBody {
+        self.body
+    }
+
+    /// Reads the body of this part as UTF-8 text.
+    pub fn read_to_string(self) -> Box<Future<Item = String, Error = MultipartError> + Send> {
+        let f = self.body.concat2().and_then(|chunk| {
+            String::from_utf8(chunk.to_vec())
+                .map_err(|e| MultipartError::Malformed(e.to_string()))
+        });
+
+        Box::new(f)
+    }
+}
+
+/// The body of a single `Part`, which is read as a `Stream` of `Chunk` values. The stream ends
+/// early if the next `Part` has already been requested from the `Multipart`.
+pub struct PartBody {
+    parser: Arc<Mutex<Parser>>,
+    part: usize,
+}
+
+impl Stream for PartBody {
+    type Item = Chunk;
+    type Error = MultipartError;
+
+    fn poll(&mut self) -> Poll<Option<Chunk>, MultipartError> {
+        let mut parser = self.parser.lock().unwrap();
+        if parser.part != self.part {
+            return Ok(Async::Ready(None));
+        }
+
+        parser.poll_body(true).map(|ready| ready.map(|chunk| chunk.map(Chunk::from)))
+    }
+}
+
+/// Collects the text fields of a `multipart/form-data` body, so that they can be deserialized
+/// together in the same way as a `QueryStringExtractor`. This allows file uploads to be streamed
+/// elsewhere while the text fields are read.
+#[derive(Debug, Default)]
+pub struct MultipartFields {
+    fields: HashMap<String, Vec<String>>,
+}
+
+impl MultipartFields {
+    /// Creates an empty set of fields.
+    pub fn new() -> MultipartFields {
+        MultipartFields::default()
+    }
+
+    /// Adds the value of a field. A field which is given more than once can be deserialized into
+    /// a `Vec`.
+    pub fn insert(&mut self, name: &str, value: String) {
+        self.fields
+            .entry(name.to_owned())
+            .or_insert_with(Vec::new)
+            .push(value);
+    }
+
+    /// Deserializes the fields into `T`.
+    pub fn deserialize<'de, T>(&'de self) -> Result<T, MultipartError>
+    where
+        T: Deserialize<'de>,
+    {
+        internal::from_form_fields(&self.fields).map_err(|e| MultipartError::Invalid(e.to_string()))
+    }
+}
+
+/// Describes why a `multipart/form-data` body couldn't be read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MultipartError {
+    /// The request doesn't have a `multipart/form-data` body with a boundary.
+    UnsupportedMediaType,
+
+    /// A part is larger than the part limit.
+    PartTooLarge,
+
+    /// The body is larger than the total limit.
+    PayloadTooLarge,
+
+    /// The body is not valid `multipart/form-data`, or couldn't be read.
+    Malformed(String),
+
+    /// The text fields couldn't be deserialized.
+    Invalid(String),
+}
+
+impl MultipartError {
+    /// The status which the request should be refused with.
+    pub fn status(&self) -> StatusCode {
+        match *self {
+            MultipartError::UnsupportedMediaType => StatusCode::UnsupportedMediaType,
+            MultipartError::PartTooLarge | MultipartError::PayloadTooLarge => {
+                StatusCode::PayloadTooLarge
+            }
+            MultipartError::Malformed(_) | MultipartError::Invalid(_) => StatusCode::BadRequest,
+        }
+    }
+}
+
+impl Display for MultipartError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MultipartError::UnsupportedMediaType => {
+                out.write_str("request body is not multipart/form-data")
+            }
+            MultipartError::PartTooLarge => out.write_str("multipart part is too large"),
+            MultipartError::PayloadTooLarge => out.write_str("request body is too large"),
+            MultipartError::Malformed(ref message) => {
+                write!(out, "malformed multipart body: {}", message)
+            }
+            MultipartError::Invalid(ref message) => {
+                write!(out, "invalid multipart fields: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for MultipartError {
+    fn description(&self) -> &str {
+        "multipart body could not be read"
+    }
+}
+
+impl From<hyper::Error> for MultipartError {
+    fn from(e: hyper::Error) -> MultipartError {
+        MultipartError::Malformed(e.to_string())
+    }
+}
+
+/// The position of the `Parser` within the body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    /// Before the first delimiter.
+    Preamble,
+
+    /// After a delimiter, which is followed either by `--` to end the body, or a line break.
+    Delimiter,
+
+    /// Reading the headers of a part.
+    Headers,
+
+    /// Reading the body of a part.
+    Body,
+
+    /// After the final delimiter.
+    Done,
+}
+
+/// Reads the parts of a body, which is shared between a `Multipart` and the `PartBody` of the
+/// current part.
+struct Parser {
+    body: Body,
+    eof: bool,
+    buf: Vec<u8>,
+    delimiter: Vec<u8>,
+    stage: Stage,
+    part: usize,
+    part_len: usize,
+    total_len: usize,
+    part_limit: usize,
+    total_limit: usize,
+}
+
+impl Parser {
+    /// Reads the next chunk of the body into the buffer, returning `false` at the end of the body.
+    fn fill(&mut self) -> Poll<bool, MultipartError> {
+        if self.eof {
+            return Err(malformed("unexpected end of body"));
+        }
+
+        match try_ready!(self.body.poll()) {
+            Some(chunk) => {
+                self.total_len += chunk.len();
+                if self.total_len > self.total_limit {
+                    return Err(MultipartError::PayloadTooLarge);
+                }
+
+                self.buf.extend_from_slice(&chunk);
+                Ok(Async::Ready(true))
+            }
+            None => {
+                self.eof = true;
+                Ok(Async::Ready(false))
+            }
+        }
+    }
+
+    /// Reads the body of the current part, or the preamble, up to the next delimiter. Bytes which
+    /// could be the start of a delimiter are kept in the buffer until more of the body is read.
+    fn poll_body(&mut self, limited: bool) -> Poll<Option<Vec<u8>>, MultipartError> {
+        loop {
+            if self.stage != Stage::Body && self.stage != Stage::Preamble {
+                return Ok(Async::Ready(None));
+            }
+
+            let (len, found) = match find(&self.buf, &self.delimiter) {
+                Some(i) => (i, true),
+                None => (self.buf.len().saturating_sub(self.delimiter.len() - 1), false),
+            };
+
+            if len > 0 || found {
+                let bytes = self.buf.drain(..len).collect::<Vec<_>>();
+                if found {
+                    let delimiter_len = self.delimiter.len();
+                    self.buf.drain(..delimiter_len);
+                    self.stage = Stage::Delimiter;
+                }
+
+                if limited {
+                    self.part_len += bytes.len();
+                    if self.part_len > self.part_limit {
+                        return Err(MultipartError::PartTooLarge);
+                    }
+                }
+
+                if !bytes.is_empty() {
+                    return Ok(Async::Ready(Some(bytes)));
+                }
+            } else if !try_ready!(self.fill()) {
+                return Err(malformed("unexpected end of body"));
+            }
+        }
+    }
+
+    /// Skips to the next part, and reads its headers. Returns `None` after the final delimiter.
+    fn poll_part(&mut self) -> Poll<Option<Headers>, MultipartError> {
+        loop {
+            match self.stage {
+                Stage::Preamble | Stage::Body => {
+                    // The rest of the current part is skipped.
+                    try_ready!(self.poll_body(false));
+                }
+                Stage::Delimiter => {
+                    if self.buf.len() < 2 {
+                        if !try_ready!(self.fill()) {
+                            return Err(malformed("missing final boundary"));
+                        }
+                        continue;
+                    }
+
+                    if self.buf.starts_with(b"--") {
+                        self.stage = Stage::Done;
+                        continue;
+                    }
+
+                    // The delimiter may be followed by whitespace before the line break.
+                    match find(&self.buf, b"\r\n") {
+                        Some(i) => {
+                            self.buf.drain(..i + 2);
+                            self.stage = Stage::Headers;
+                        }
+                        None if self.buf.len() > HEADERS_LIMIT => {
+                            return Err(malformed("invalid boundary"));
+                        }
+                        None => {
+                            try_ready!(self.fill());
+                        }
+                    }
+                }
+                Stage::Headers => {
+                    let end = if self.buf.starts_with(b"\r\n") {
+                        Some(0)
+                    } else {
+                        find(&self.buf, b"\r\n\r\n").map(|i| i + 2)
+                    };
+
+                    match end {
+                        Some(i) => {
+                            let headers = parse_headers(&self.buf[..i])?;
+                            self.buf.drain(..i + 2);
+                            self.stage = Stage::Body;
+                            self.part += 1;
+                            self.part_len = 0;
+                            return Ok(Async::Ready(Some(headers)));
+                        }
+                        None if self.buf.len() > HEADERS_LIMIT => {
+                            return Err(malformed("headers are too large"));
+                        }
+                        None => {
+                            try_ready!(self.fill());
+                        }
+                    }
+                }
+                Stage::Done => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+/// Creates a `MultipartError::Malformed` describing the problem.
+fn malformed(message: &str) -> MultipartError {
+    MultipartError::Malformed(message.to_owned())
+}
+
+/// The boundary from a `multipart/form-data` `Content-Type`.
+fn boundary(headers: &Headers) -> Option<String> {
+    match headers.get::<ContentType>() {
+        Some(&ContentType(ref mime))
+            if mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA =>
+        {
+            mime.get_param(mime::BOUNDARY)
+                .map(|boundary| boundary.as_str().to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Parses header lines, each of which ends with a line break.
+fn parse_headers(bytes: &[u8]) -> Result<Headers, MultipartError> {
+    let text = str::from_utf8(bytes).map_err(|e| MultipartError::Malformed(e.to_string()))?;
+
+    let mut headers = Headers::new();
+    for line in text.split("\r\n").filter(|line| !line.is_empty()) {
+        let mut split = line.splitn(2, ':');
+        match (split.next(), split.next()) {
+            (Some(name), Some(value)) => {
+                headers.append_raw(name.trim().to_owned(), value.trim().as_bytes().to_vec())
+            }
+            _ => return Err(MultipartError::Malformed(format!("invalid header `{}`", line))),
+        }
+    }
+
+    Ok(headers)
+}
+
+/// Finds a parameter of a `Content-Disposition` header, such as `name` in
+/// `form-data; name="title"`.
+fn disposition_param(disposition: &str, param: &str) -> Option<String> {
+    disposition
+        .split(';')
+        .skip(1)
+        .filter_map(|p| {
+            let mut split = p.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(name), Some(value)) if name.trim().eq_ignore_ascii_case(param) => {
+                    let value = value.trim();
+                    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                        Some(value[1..value.len() - 1].replace("\\\"", "\""))
+                    } else {
+                        Some(value.to_owned())
+                    }
+                }
+                _ => None,
+            }
+        })
+        .next()
+}
+
+/// The position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use futures::Sink;
+
+    const BODY: &str = "preamble\r\n\
+                        --XYZ\r\n\
+                        Content-Disposition: form-data; name=\"title\"\r\n\
+                        \r\n\
+                        Holiday\r\n\
+                        --XYZ  \r\n\
+                        Content-Disposition: form-data; name=\"photo\"; filename=\"\\\"b\\\"\"\r\n\
+                        Content-Type: image/jpeg\r\n\
+                        \r\n\
+                        \r\n--XY\r\n-XYZ01\r\n\
+                        --XYZ\r\n\
+                        Content-Disposition: form-data; name=\"tags\"\r\n\
+                        \r\n\
+                        sea\r\n\
+                        --XYZ\r\n\
+                        Content-Disposition: form-data; name=\"tags\"\r\n\
+                        \r\n\
+                        sun\r\n\
+                        --XYZ--\r\n\
+                        epilogue";
+
+    /// Creates a `Body` which yields `body` in chunks of `size` bytes.
+    fn chunked(body: &'static str, size: usize) -> Body {
+        let (mut tx, body_rx) = Body::pair();
+        thread::spawn(move || {
+            for chunk in body.as_bytes().chunks(size) {
+                tx = tx.send(Ok(Chunk::from(chunk.to_vec()))).wait().unwrap();
+            }
+        });
+        body_rx
+    }
+
+    fn read_parts(multipart: Multipart) -> Result<Vec<(Option<String>, String)>, MultipartError> {
+        multipart
+            .and_then(|part| {
+                let filename = part.filename().map(str::to_owned);
+                part.read_to_string().map(|body| (filename, body))
+            })
+            .collect()
+            .wait()
+    }
+
+    #[test]
+    fn parts_are_read_across_chunks() {
+        for &size in &[1, 3, 7, 1024] {
+            let parts = read_parts(Multipart::new(chunked(BODY, size), "XYZ")).unwrap();
+            assert_eq!(
+                parts,
+                vec![
+                    (None, "Holiday".to_owned()),
+                    (Some("\"b\"".to_owned()), "\r\n--XY\r\n-XYZ01".to_owned()),
+                    (None, "sea".to_owned()),
+                    (None, "sun".to_owned()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn part_headers_are_available() {
+        let mut multipart = Multipart::new(Body::from(BODY), "XYZ").wait();
+        let title = multipart.next().unwrap().unwrap();
+        assert_eq!(title.name(), Some("title"));
+        assert_eq!(title.content_type(), None);
+
+        // The body of the title is skipped.
+        let photo = multipart.next().unwrap().unwrap();
+        assert_eq!(photo.name(), Some("photo"));
+        assert_eq!(photo.content_type(), Some(mime::IMAGE_JPEG));
+        assert_eq!(photo.headers().len(), 2);
+    }
+
+    #[test]
+    fn limits_are_applied() {
+        let result = read_parts(Multipart::new(chunked(BODY, 5), "XYZ").with_part_limit(13));
+        assert_eq!(result, Err(MultipartError::PartTooLarge));
+
+        let result = read_parts(Multipart::new(chunked(BODY, 5), "XYZ").with_part_limit(14));
+        assert!(result.is_ok());
+
+        let result = read_parts(Multipart::new(chunked(BODY, 5), "XYZ").with_total_limit(64));
+        assert_eq!(result, Err(MultipartError::PayloadTooLarge));
+    }
+
+    #[test]
+    fn truncated_bodies_are_malformed() {
+        let result = read_parts(Multipart::new(Body::from(&BODY[..90]), "XYZ"));
+        assert!(match result {
+            Err(MultipartError::Malformed(_)) => true,
+            _ => false,
+        });
+    }
+
+    #[derive(Deserialize)]
+    struct Fields {
+        title: String,
+        tags: Vec<String>,
+        missing: Option<u8>,
+    }
+
+    #[test]
+    fn text_fields_are_deserialized() {
+        let fields = Multipart::new(chunked(BODY, 4), "XYZ")
+            .read_fields::<Fields>()
+            .wait()
+            .unwrap();
+        assert_eq!(fields.title, "Holiday");
+        assert_eq!(fields.tags, vec!["sea", "sun"]);
+        assert_eq!(fields.missing, None);
+    }
+}
@@ -41,6 +41,17 @@ pub(crate) fn split<'r>(query: Option<&'r str>) -> QueryStringMapping {
     }
 }
 
+/// Provides the decoded keys which are given in a query string without a value, such as `b` in
+/// `a=1&b`. These are skipped by `split`.
+pub(crate) fn keys_without_values(query: Option<&str>) -> Vec<String> {
+    query
+        .into_iter()
+        .flat_map(|query| query.split(is_separator))
+        .filter(|key| !key.is_empty() && !key.contains("="))
+        .filter_map(|key| form_url_decode(key).ok())
+        .collect()
+}
+
 fn is_separator(c: char) -> bool {
     c == '&' || c == ';'
 }
@@ -89,4 +100,11 @@ mod tests {
         let qsm = split(Some("a=b=c&d=e"));
         assert_eq!(to_pairs(&qsm), vec![("a", vec!["b=c"]), ("d", vec!["e"])],);
     }
+
+    #[test]
+    fn keys_without_values_tests() {
+        assert_eq!(keys_without_values(Some("a&b=1;c%20d&&e=")), vec!["a", "c d"]);
+        assert!(keys_without_values(Some("a=1")).is_empty());
+        assert!(keys_without_values(None).is_empty());
+    }
 }
@@ -15,29 +15,49 @@
 extern crate base64;
 extern crate bincode;
 extern crate borrow_bag;
+#[cfg(feature = "http2")]
+extern crate bytes;
 extern crate chrono;
+extern crate cookie;
 #[cfg(windows)]
 extern crate crossbeam;
+#[macro_use]
 extern crate futures;
+#[cfg(feature = "http2")]
+extern crate h2;
+#[cfg(feature = "http2")]
+extern crate http as http_types;
 #[macro_use]
 extern crate hyper;
+#[cfg(unix)]
+extern crate libc;
 extern crate linked_hash_map;
 #[macro_use]
 extern crate log;
 extern crate mime;
 extern crate mio;
+extern crate net2;
 extern crate num_cpus;
 extern crate rand;
 extern crate regex;
+#[cfg(feature = "tls")]
+extern crate rustls;
 #[macro_use]
 extern crate serde;
+extern crate serde_json;
+extern crate time;
 extern crate tokio_core;
+extern crate tokio_io;
+#[cfg(unix)]
+extern crate tokio_uds;
 extern crate url;
 extern crate uuid;
 
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(all(test, feature = "tls"))]
+extern crate webpki;
 
 pub mod extractor;
 pub mod handler;
@@ -48,11 +68,25 @@ pub mod router;
 mod service;
 pub mod state;
 pub mod test;
+#[cfg(feature = "tls")]
+pub mod tls;
+mod error;
 mod os;
+mod server;
 
-pub use os::current::start_with_num_threads;
+pub use error::StartError;
+pub use server::ServerBuilder;
+pub use os::Serve;
+#[cfg(unix)]
+pub use os::unix::listen_fds;
 
+use std::io;
 use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+#[cfg(unix)]
+use std::path::Path;
+use std::time::Duration;
+use futures::Future;
+use net2::TcpBuilder;
 use handler::NewHandler;
 
 /// Starts a Gotham application, with the default number of threads (equal to the number of CPUs).
@@ -69,17 +103,678 @@ where
     start_with_num_threads(addr, threads, new_handler)
 }
 
-fn tcp_listener<A>(addr: A) -> (TcpListener, SocketAddr)
+/// Starts a Gotham application, with the given number of threads.
+///
+/// ## Windows
+///
+/// An additional thread is used on Windows to accept connections.
+pub fn start_with_num_threads<NH, A>(addr: A, threads: usize, new_handler: NH)
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+{
+    if let Err(e) = try_start_with_num_threads(addr, threads, new_handler) {
+        panic!("{}", e);
+    }
+}
+
+/// Starts a Gotham application with the default number of threads, which runs until
+/// `shutdown_signal` resolves.
+///
+/// Once shutdown begins, the listener is closed and idle connections are closed. Requests which
+/// are in flight are given up to `drain_timeout` to complete before the remaining connections are
+/// dropped and this function returns.
+///
+/// ## Windows
+///
+/// An additional thread is used on Windows to accept connections.
+pub fn start_until<NH, A, F>(addr: A, new_handler: NH, shutdown_signal: F, drain_timeout: Duration)
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+    F: Future<Item = (), Error = ()>,
+{
+    let threads = num_cpus::get();
+    start_with_num_threads_until(addr, threads, new_handler, shutdown_signal, drain_timeout)
+}
+
+/// Starts a Gotham application with the given number of threads, which runs until
+/// `shutdown_signal` resolves.
+///
+/// See `start_until` for details of how shutdown is handled.
+pub fn start_with_num_threads_until<NH, A, F>(
+    addr: A,
+    threads: usize,
+    new_handler: NH,
+    shutdown_signal: F,
+    drain_timeout: Duration,
+) where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+    F: Future<Item = (), Error = ()>,
+{
+    if let Err(e) = try_start_with_num_threads_until(
+        addr,
+        threads,
+        new_handler,
+        shutdown_signal,
+        drain_timeout,
+    ) {
+        panic!("{}", e);
+    }
+}
+
+/// Starts a Gotham application which accepts connections on a Unix domain socket at `path`, with
+/// the default number of threads. The socket must not already exist, and is removed when the
+/// application shuts down.
+///
+/// Handlers can determine the address of the client with `state::peer_addr`, as
+/// `state::client_addr` is only available for TCP connections.
+#[cfg(unix)]
+pub fn start_unix<NH, P>(path: P, new_handler: NH)
+where
+    NH: NewHandler + 'static,
+    P: AsRef<Path>,
+{
+    if let Err(e) = ServerBuilder::new().run_unix(path, new_handler) {
+        panic!("{}", e);
+    }
+}
+
+/// Serves a Gotham application on a reactor which is managed by the caller, using the default
+/// settings. The returned future accepts connections on `listener` until it is dropped, and can
+/// be composed with other futures on the same reactor.
+///
+/// ```rust,no_run
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate tokio_core;
+/// #
+/// # use futures::Future;
+/// # use hyper::{Response, StatusCode};
+/// # use tokio_core::net::TcpListener;
+/// # use tokio_core::reactor::Core;
+/// # use gotham::state::State;
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Accepted))
+/// # }
+/// #
+/// # fn main() {
+/// let mut core = Core::new().unwrap();
+/// let handle = core.handle();
+///
+/// let addr = "127.0.0.1:7878".parse().unwrap();
+/// let listener = TcpListener::bind(&addr, &handle).unwrap();
+///
+/// let server = gotham::serve(listener, &handle, || Ok(my_handler));
+/// core.run(server.map_err(|e| eprintln!("listener failed: {}", e)))
+///     .unwrap();
+/// # }
+/// ```
+pub fn serve<NH>(
+    listener: tokio_core::net::TcpListener,
+    handle: &tokio_core::reactor::Handle,
+    new_handler: NH,
+) -> Serve
+where
+    NH: NewHandler + 'static,
+{
+    ServerBuilder::new().serve(listener, handle, new_handler)
+}
+
+/// Starts a Gotham application with the default number of threads, returning an error instead of
+/// panicking if the application cannot be started.
+///
+/// ## Windows
+///
+/// An additional thread is used on Windows to accept connections.
+pub fn try_start<NH, A>(addr: A, new_handler: NH) -> Result<(), StartError>
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+{
+    ServerBuilder::new().run(addr, new_handler)
+}
+
+/// Starts a Gotham application with the given number of threads, returning an error instead of
+/// panicking if the application cannot be started.
+pub fn try_start_with_num_threads<NH, A>(
+    addr: A,
+    threads: usize,
+    new_handler: NH,
+) -> Result<(), StartError>
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+{
+    ServerBuilder::new()
+        .with_threads(threads)
+        .run(addr, new_handler)
+}
+
+/// Starts a Gotham application with the default number of threads, which runs until
+/// `shutdown_signal` resolves. An error is returned instead of panicking if the application cannot
+/// be started.
+///
+/// See `start_until` for details of how shutdown is handled.
+pub fn try_start_until<NH, A, F>(
+    addr: A,
+    new_handler: NH,
+    shutdown_signal: F,
+    drain_timeout: Duration,
+) -> Result<(), StartError>
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+    F: Future<Item = (), Error = ()>,
+{
+    ServerBuilder::new()
+        .with_drain_timeout(drain_timeout)
+        .run_until(addr, new_handler, shutdown_signal)
+}
+
+/// Starts a Gotham application with the given number of threads, which runs until
+/// `shutdown_signal` resolves. An error is returned instead of panicking if the application cannot
+/// be started.
+///
+/// See `start_until` for details of how shutdown is handled.
+pub fn try_start_with_num_threads_until<NH, A, F>(
+    addr: A,
+    threads: usize,
+    new_handler: NH,
+    shutdown_signal: F,
+    drain_timeout: Duration,
+) -> Result<(), StartError>
+where
+    NH: NewHandler + 'static,
+    A: ToSocketAddrs,
+    F: Future<Item = (), Error = ()>,
+{
+    ServerBuilder::new()
+        .with_threads(threads)
+        .with_drain_timeout(drain_timeout)
+        .run_until(addr, new_handler, shutdown_signal)
+}
+
+fn tcp_listener<A>(addr: A) -> Result<(TcpListener, SocketAddr), StartError>
 where
     A: ToSocketAddrs,
 {
     let addr = match addr.to_socket_addrs().map(|ref mut i| i.next()) {
         Ok(Some(a)) => a,
-        Ok(_) => panic!("unable to resolve listener address"),
-        Err(_) => panic!("unable to parse listener address"),
+        Ok(None) => {
+            let e = io::Error::new(
+                io::ErrorKind::NotFound,
+                "address did not resolve to any socket addresses",
+            );
+            return Err(StartError::AddressResolution(e));
+        }
+        Err(e) => return Err(StartError::AddressResolution(e)),
+    };
+
+    let listener = bind(addr, false).map_err(|e| StartError::Bind(addr, e))?;
+
+    Ok((listener, addr))
+}
+
+/// Binds a listener to every address which each of `addrs` resolves to, skipping duplicates.
+fn tcp_listeners<I>(addrs: I) -> Result<Vec<TcpListener>, StartError>
+where
+    I: IntoIterator,
+    I::Item: ToSocketAddrs,
+{
+    let mut resolved: Vec<SocketAddr> = Vec::new();
+
+    for addr in addrs {
+        let iter = addr.to_socket_addrs()
+            .map_err(StartError::AddressResolution)?;
+
+        for addr in iter {
+            if !resolved.contains(&addr) {
+                resolved.push(addr);
+            }
+        }
+    }
+
+    if resolved.is_empty() {
+        let e = io::Error::new(
+            io::ErrorKind::NotFound,
+            "addresses did not resolve to any socket addresses",
+        );
+        return Err(StartError::AddressResolution(e));
+    }
+
+    resolved
+        .iter()
+        .map(|&addr| {
+            let only_v6 = shares_port_with_ipv4(addr, &resolved);
+            bind(addr, only_v6).map_err(|e| StartError::Bind(addr, e))
+        })
+        .collect()
+}
+
+/// Determines whether `addr` is an IPv6 address whose port is also bound on an IPv4 address,
+/// which requires the IPv6 listener to only accept IPv6 connections. Otherwise `[::]` and
+/// `0.0.0.0` would conflict on platforms where IPv6 sockets also accept IPv4 connections by
+/// default.
+fn shares_port_with_ipv4(addr: SocketAddr, resolved: &[SocketAddr]) -> bool {
+    addr.is_ipv6() && addr.port() != 0
+        && resolved
+            .iter()
+            .any(|other| other.is_ipv4() && other.port() == addr.port())
+}
+
+/// Binds a listener to `addr`. When `only_v6` is set, an IPv6 listener only accepts IPv6
+/// connections, and otherwise the platform default applies, as with `TcpListener::bind`.
+fn bind(addr: SocketAddr, only_v6: bool) -> io::Result<TcpListener> {
+    let builder = match addr {
+        SocketAddr::V4(_) => TcpBuilder::new_v4()?,
+        SocketAddr::V6(_) => {
+            let builder = TcpBuilder::new_v6()?;
+            if only_v6 {
+                builder.only_v6(true)?;
+            }
+            builder
+        }
     };
 
-    let listener = TcpListener::bind(addr).expect("unable to open TCP listener");
+    // Matches `TcpListener::bind`, which allows the address to be reused straight after a
+    // previous server on it has stopped.
+    #[cfg(unix)]
+    builder.reuse_address(true)?;
+
+    builder.bind(addr)?;
+    builder.listen(128)
+}
+
+/// Ensures that the `NewHandler` is able to create a `Handler` before any connections are
+/// accepted, so that a misconfigured application fails at startup rather than on every request.
+fn check_new_handler<NH>(new_handler: &NH) -> Result<(), StartError>
+where
+    NH: NewHandler,
+{
+    new_handler
+        .new_handler()
+        .map(|_| ())
+        .map_err(StartError::NewHandler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    use futures::sync::oneshot;
+    use hyper::{Response, StatusCode};
+
+    use state::State;
+
+    fn handler(state: State) -> (State, Response) {
+        (state, Response::new().with_status(StatusCode::Accepted))
+    }
+
+    #[test]
+    fn try_start_reports_address_in_use() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        match try_start_with_num_threads(addr, 1, || Ok(handler)) {
+            Err(StartError::Bind(a, _)) => assert_eq!(a, addr),
+            r => panic!("expected bind error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn try_start_reports_unresolvable_address() {
+        match try_start_with_num_threads("not an address", 1, || Ok(handler)) {
+            Err(StartError::AddressResolution(_)) => (),
+            r => panic!("expected address resolution error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn try_start_reports_new_handler_failure() {
+        let new_handler = || -> io::Result<fn(State) -> (State, Response)> {
+            Err(io::Error::new(io::ErrorKind::Other, "no database connection"))
+        };
+
+        match try_start_with_num_threads("127.0.0.1:0", 1, new_handler) {
+            Err(StartError::NewHandler(e)) => assert_eq!(e.to_string(), "no database connection"),
+            r => panic!("expected new handler error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn server_builder_serves_until_shutdown() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let (signal, shutdown) = oneshot::channel::<()>();
+        let server = thread::spawn(move || {
+            ServerBuilder::new()
+                .with_threads(2)
+                .with_keep_alive(false)
+                .with_idle_timeout(Duration::from_secs(5))
+                .with_max_connections(8)
+                .with_drain_timeout(Duration::from_secs(1))
+                .run_until(addr, || Ok(handler), shutdown.map_err(|_| ()))
+        });
+
+        let mut stream = loop {
+            match TcpStream::connect(addr) {
+                Ok(stream) => break stream,
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+
+        signal.send(()).unwrap();
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn server_builder_serves_pre_bound_listeners() {
+        let listeners = vec![
+            TcpListener::bind("127.0.0.1:0").unwrap(),
+            TcpListener::bind("127.0.0.1:0").unwrap(),
+        ];
+        let addrs = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect::<Vec<_>>();
+
+        let (signal, shutdown) = oneshot::channel::<()>();
+        let server = thread::spawn(move || {
+            ServerBuilder::new()
+                .with_threads(2)
+                .with_keep_alive(false)
+                .run_listeners_until(listeners, || Ok(handler), shutdown.map_err(|_| ()))
+        });
+
+        for addr in addrs {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+        }
+
+        signal.send(()).unwrap();
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn tcp_listeners_binds_each_address_once() {
+        let free = || {
+            TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+        };
+        let (a, b) = (free(), free());
+
+        let listeners = tcp_listeners(vec![a, b, a]).unwrap();
+        let bound = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(bound, vec![a, b]);
+    }
+
+    #[test]
+    fn bind_binds_ipv4_and_ipv6_on_the_same_port() {
+        // Hosts without IPv6, such as some CI containers, can't run this test.
+        if TcpListener::bind("[::1]:0").is_err() {
+            return;
+        }
+
+        let v4 = bind("0.0.0.0:0".parse().unwrap(), false).unwrap();
+        let port = v4.local_addr().unwrap().port();
+
+        let addr: SocketAddr = format!("[::]:{}", port).parse().unwrap();
+        let v6 = bind(addr, true).unwrap();
+        assert_eq!(v6.local_addr().unwrap(), addr);
+    }
+
+    #[test]
+    fn only_ipv6_addresses_sharing_a_port_with_ipv4_are_restricted() {
+        let addrs = ["0.0.0.0:80", "[::]:80", "[::]:443", "[::1]:0", "127.0.0.1:0"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect::<Vec<SocketAddr>>();
+
+        let restricted = addrs
+            .iter()
+            .map(|&addr| shares_port_with_ipv4(addr, &addrs))
+            .collect::<Vec<_>>();
+
+        assert_eq!(restricted, vec![false, true, false, false, false]);
+    }
+
+    #[test]
+    fn tcp_listeners_reports_unresolvable_address() {
+        match tcp_listeners(vec!["127.0.0.1:0", "not an address"]) {
+            Err(StartError::AddressResolution(_)) => (),
+            r => panic!("expected address resolution error, got {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn run_listeners_reports_no_listeners() {
+        match ServerBuilder::new().run_listeners(vec![], || Ok(handler)) {
+            Err(StartError::NoListeners) => (),
+            r => panic!("expected no listeners error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn try_start_reports_invalid_thread_count() {
+        match try_start_with_num_threads("127.0.0.1:0", 0, || Ok(handler)) {
+            Err(StartError::InvalidThreadCount) => (),
+            r => panic!("expected invalid thread count error, got {:?}", r),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn server_builder_serves_unix_socket() {
+        use std::fs;
+        use std::os::unix::net::UnixStream;
+        use std::process;
+
+        use state::{client_addr, peer_addr, PeerAddr};
+
+        fn unix_handler(state: State) -> (State, Response) {
+            let status = match (peer_addr(&state), client_addr(&state)) {
+                (Some(&PeerAddr::Unix(_)), None) => StatusCode::Accepted,
+                _ => StatusCode::InternalServerError,
+            };
+
+            (state, Response::new().with_status(status))
+        }
+
+        let path = env::temp_dir().join(format!("gotham-test-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let (signal, shutdown) = oneshot::channel::<()>();
+        let server = {
+            let path = path.clone();
+            thread::spawn(move || {
+                ServerBuilder::new()
+                    .with_threads(2)
+                    .with_keep_alive(false)
+                    .run_unix_until(path, || Ok(unix_handler), shutdown.map_err(|_| ()))
+            })
+        };
+
+        let mut stream = loop {
+            match UnixStream::connect(&path) {
+                Ok(stream) => break stream,
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+
+        signal.send(()).unwrap();
+        server.join().unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn serve_runs_on_existing_reactor() {
+        use futures::future::Either;
+        use tokio_core::reactor::Core;
+
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let listener = tokio_core::net::TcpListener::bind(&addr, &handle).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (done, response) = oneshot::channel();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            done.send(response).unwrap();
+        });
+
+        let server = serve(listener, &handle, || Ok(handler));
+        let response = match core.run(server.select2(response)) {
+            Ok(Either::B((response, _))) => response,
+            _ => panic!("server stopped before responding"),
+        };
+
+        assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+    }
+
+    #[test]
+    fn server_builder_reads_proxy_protocol_header() {
+        use futures::future::Either;
+        use tokio_core::reactor::Core;
+
+        use state::client_addr;
+
+        fn proxied_handler(state: State) -> (State, Response) {
+            let status = match client_addr(&state) {
+                Some(addr) if addr == "192.168.0.1:56324".parse().unwrap() => StatusCode::Accepted,
+                _ => StatusCode::InternalServerError,
+            };
+
+            (state, Response::new().with_status(status))
+        }
+
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let listener = tokio_core::net::TcpListener::bind(&addr, &handle).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (done, response) = oneshot::channel();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(
+                    b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n\
+                      GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            done.send(response).unwrap();
+        });
+
+        let server = ServerBuilder::new()
+            .with_proxy_protocol(true)
+            .serve(listener, &handle, || Ok(proxied_handler));
+        let response = match core.run(server.select2(response)) {
+            Ok(Either::B((response, _))) => response,
+            _ => panic!("server stopped before responding"),
+        };
+
+        assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+    }
+
+    #[test]
+    fn server_builder_limits_connections_awaiting_proxy_protocol_header() {
+        use std::time::Instant;
+
+        use futures::future::Either;
+        use tokio_core::reactor::Core;
+
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let listener = tokio_core::net::TcpListener::bind(&addr, &handle).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (done, response) = oneshot::channel();
+        thread::spawn(move || {
+            // Holds the only connection until the handshake timeout closes it.
+            let mut silent = TcpStream::connect(addr).unwrap();
+            let started = Instant::now();
+
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(
+                    b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n\
+                      GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let elapsed = started.elapsed();
+
+            assert_eq!(silent.read(&mut [0; 1]).unwrap(), 0);
+            done.send((response, elapsed)).unwrap();
+        });
+
+        let server = ServerBuilder::new()
+            .with_proxy_protocol(true)
+            .with_max_connections(1)
+            .with_handshake_timeout(Duration::from_millis(200))
+            .serve(listener, &handle, || Ok(handler));
+        let (response, elapsed) = match core.run(server.select2(response)) {
+            Ok(Either::B((response, _))) => response,
+            _ => panic!("server stopped before responding"),
+        };
 
-    (listener, addr)
+        assert!(response.starts_with("HTTP/1.1 202 Accepted"));
+        assert!(elapsed >= Duration::from_millis(150));
+    }
 }
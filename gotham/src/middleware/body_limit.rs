@@ -0,0 +1,326 @@
+//! Defines `BodyLimitMiddleware`, which refuses request bodies larger than a configured size.
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures::{future, Async, Future, Poll, Sink, Stream};
+use futures::sink::SendAll;
+use futures::sync::mpsc::{SendError, Sender};
+use hyper::{self, Body, Chunk, Headers, Response, StatusCode};
+use hyper::header::ContentLength;
+
+use handler::{HandlerError, HandlerFuture};
+use http::response::create_response;
+use middleware::{Middleware, NewMiddleware};
+use state::{request_id, FromState, State};
+
+/// Refuses requests whose body is larger than `limit` bytes with `413 Payload Too Large`.
+///
+/// A request with a larger `Content-Length` is refused before the rest of the pipeline and the
+/// handler are invoked. The `Body` in `State` is also replaced by one which stops yielding chunks
+/// once `limit` bytes have been read, so chunked bodies are limited without any change to the
+/// handler. When that happens, reading the `Body` fails, and the response from the handler is
+/// replaced by `413 Payload Too Large`. The chunks are passed on while the future returned by the
+/// handler is polled, so the `Body` must be read before that future completes.
+///
+/// The same limit can be applied to every request served by an application with
+/// `ServerBuilder::with_max_body_size`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use futures::{future, Future, Stream};
+/// # use hyper::{Body, Response, StatusCode};
+/// # use gotham::handler::{HandlerFuture, IntoHandlerError};
+/// # use gotham::middleware::body_limit::BodyLimitMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn echo(mut state: State) -> Box<HandlerFuture> {
+///     let f = Body::take_from(&mut state).concat2().then(move |result| match result {
+///         Ok(body) => {
+///             let res = Response::new().with_body(body.to_vec());
+///             future::ok((state, res))
+///         }
+///         Err(e) => future::err((state, e.into_handler_error())),
+///     });
+///
+///     Box::new(f)
+/// }
+///
+/// # fn main() {
+/// let (chain, pipelines) = single_pipeline(
+///     new_pipeline().add(BodyLimitMiddleware::new(16)).build()
+/// );
+///
+/// let router = build_router(chain, pipelines, |route| {
+///     route.post("/echo").to(echo);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server.client()
+///     .post("http://example.com/echo", "a short body", mime::TEXT_PLAIN)
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.status(), StatusCode::Ok);
+///
+/// let response = test_server.client()
+///     .post("http://example.com/echo", "a body which is too large", mime::TEXT_PLAIN)
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.status(), StatusCode::PayloadTooLarge);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimitMiddleware {
+    limit: u64,
+}
+
+impl BodyLimitMiddleware {
+    /// Creates a `BodyLimitMiddleware` which accepts bodies of up to `limit` bytes.
+    pub fn new(limit: usize) -> BodyLimitMiddleware {
+        BodyLimitMiddleware {
+            limit: limit as u64,
+        }
+    }
+}
+
+impl NewMiddleware for BodyLimitMiddleware {
+    type Instance = BodyLimitMiddleware;
+
+    fn new_middleware(&self) -> io::Result<BodyLimitMiddleware> {
+        Ok(*self)
+    }
+}
+
+impl Middleware for BodyLimitMiddleware {
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+    {
+        limit_request_body(state, self.limit, chain)
+    }
+}
+
+/// Refuses the request if its `Content-Length` is larger than `limit`, and otherwise limits the
+/// `Body` in `State` before invoking `f`.
+pub(crate) fn limit_request_body<F>(mut state: State, limit: u64, f: F) -> Box<HandlerFuture>
+where
+    F: FnOnce(State) -> Box<HandlerFuture>,
+{
+    let content_length = Headers::try_borrow_from(&state)
+        .and_then(|headers| headers.get::<ContentLength>())
+        .map(|&ContentLength(len)| len);
+
+    if content_length.map_or(false, |len| len > limit) {
+        trace!("[{}] refusing request with a large Content-Length", request_id(&state));
+        let res = payload_too_large(&state);
+        return Box::new(future::ok((state, res)));
+    }
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let forward = state.try_take::<Body>().map(|body| {
+        let (tx, limited) = Body::pair();
+        state.put(limited);
+
+        tx.send_all(LimitedBody {
+            body,
+            limit,
+            read: 0,
+            exceeded: exceeded.clone(),
+            done: false,
+        })
+    });
+
+    let handler = ForwardBody {
+        forward,
+        handler: f(state),
+    };
+
+    let f = handler.then(move |result| {
+        if !exceeded.load(Ordering::SeqCst) {
+            return result;
+        }
+
+        let state = match result {
+            Ok((state, _)) | Err((state, _)) => state,
+        };
+
+        trace!("[{}] request body exceeded the limit", request_id(&state));
+        let res = payload_too_large(&state);
+        Ok((state, res))
+    });
+
+    Box::new(f)
+}
+
+fn payload_too_large(state: &State) -> Response {
+    create_response(state, StatusCode::PayloadTooLarge, None)
+}
+
+/// Completes with the handler's future, while forwarding the chunks of the request body to the
+/// `Body` in `State` as the handler reads them. Both are polled by the task serving the request,
+/// so no reactor is needed, and forwarding stops once the handler's future has completed.
+struct ForwardBody {
+    forward: Option<SendAll<Sender<Result<Chunk, hyper::Error>>, LimitedBody>>,
+    handler: Box<HandlerFuture>,
+}
+
+impl Future for ForwardBody {
+    type Item = (State, Response);
+    type Error = (State, HandlerError);
+
+    fn poll(&mut self) -> Poll<(State, Response), (State, HandlerError)> {
+        let forwarded = match self.forward {
+            Some(ref mut forward) => match forward.poll() {
+                Ok(Async::NotReady) => false,
+                // The handler has dropped the `Body`, or the whole body has been forwarded.
+                _ => true,
+            },
+            None => false,
+        };
+
+        if forwarded {
+            self.forward = None;
+        }
+
+        self.handler.poll()
+    }
+}
+
+/// Yields the chunks of a `Body`, and then an error once more than `limit` bytes have been read.
+struct LimitedBody {
+    body: Body,
+    limit: u64,
+    read: u64,
+    exceeded: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl Stream for LimitedBody {
+    type Item = Result<Chunk, hyper::Error>;
+    type Error = SendError<Result<Chunk, hyper::Error>>;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+
+        match self.body.poll() {
+            Ok(Async::Ready(Some(chunk))) => {
+                self.read += chunk.len() as u64;
+                if self.read > self.limit {
+                    self.exceeded.store(true, Ordering::SeqCst);
+                    self.done = true;
+                    return Ok(Async::Ready(Some(Err(hyper::Error::TooLarge))));
+                }
+
+                Ok(Async::Ready(Some(Ok(chunk))))
+            }
+            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(e) => {
+                self.done = true;
+                Ok(Async::Ready(Some(Err(e))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use hyper::Method;
+    use tokio_core::reactor::Core;
+
+    use state::set_request_id;
+
+    fn state(body: Body, content_length: Option<u64>) -> State {
+        let mut headers = Headers::new();
+        if let Some(len) = content_length {
+            headers.set(ContentLength(len));
+        }
+
+        let mut state = State::new();
+        state.put(Method::Post);
+        state.put(headers);
+        state.put(body);
+        set_request_id(&mut state);
+        state
+    }
+
+    fn read_body(mut state: State) -> Box<HandlerFuture> {
+        let f = Body::take_from(&mut state).concat2().then(move |result| {
+            let status = match result {
+                Ok(ref body) if body.len() == 10 => StatusCode::Ok,
+                Ok(_) => StatusCode::InternalServerError,
+                Err(_) => StatusCode::BadRequest,
+            };
+
+            let res = create_response(&state, status, None);
+            Ok((state, res))
+        });
+
+        Box::new(f)
+    }
+
+    fn chunked_body() -> Body {
+        let (mut tx, body) = Body::pair();
+        thread::spawn(move || {
+            for _ in 0..5 {
+                tx = tx.send(Ok(Chunk::from(vec![0; 2]))).wait().unwrap();
+            }
+        });
+        body
+    }
+
+    fn status(limit: u64, body: Body, content_length: Option<u64>) -> StatusCode {
+        let mut core = Core::new().unwrap();
+        let state = state(body, content_length);
+
+        match core.run(limit_request_body(state, limit, read_body)) {
+            Ok((_state, res)) => res.status(),
+            Err((_state, e)) => panic!("handler failed: {}", e),
+        }
+    }
+
+    #[test]
+    fn content_length_is_checked_before_dispatch() {
+        fn unreachable(_state: State) -> Box<HandlerFuture> {
+            panic!("the handler should not be invoked");
+        }
+
+        let mut core = Core::new().unwrap();
+        let state = state(Body::from(vec![0; 10]), Some(10));
+        let f = limit_request_body(state, 9, unreachable);
+        let (_state, res) = core.run(f).map_err(|_| ()).unwrap();
+        assert_eq!(res.status(), StatusCode::PayloadTooLarge);
+
+        assert_eq!(status(10, Body::from(vec![0; 10]), Some(10)), StatusCode::Ok);
+    }
+
+    #[test]
+    fn chunked_bodies_are_counted() {
+        assert_eq!(status(10, chunked_body(), None), StatusCode::Ok);
+        assert_eq!(status(9, chunked_body(), None), StatusCode::PayloadTooLarge);
+    }
+
+    #[test]
+    fn chunked_bodies_are_limited_without_a_reactor() {
+        // No `Handle` is in `State`, and the future is driven by the current thread alone.
+        let f = limit_request_body(state(chunked_body(), None), 9, read_body);
+        let (_state, res) = f.wait().map_err(|_| ()).unwrap();
+        assert_eq!(res.status(), StatusCode::PayloadTooLarge);
+    }
+}
@@ -7,6 +7,7 @@ use std::panic::RefUnwindSafe;
 use handler::HandlerFuture;
 use state::State;
 
+pub mod body_limit;
 pub mod chain;
 pub mod session;
 
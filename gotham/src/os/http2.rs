@@ -0,0 +1,567 @@
+//! Defines the HTTP/2 connection handling, which is only available when the `http2` feature is
+//! enabled.
+//!
+//! HTTP/2 requests are converted into the same `hyper::Request` values produced by the HTTP/1
+//! implementation, so that `State` is populated identically for handlers. The URI is reduced to
+//! its path and query, and the `:authority` pseudo-header is provided as the `Host` header.
+
+use std::cmp;
+use std::io;
+use std::str::FromStr;
+
+use bytes::Bytes;
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use futures::sync::mpsc;
+use h2;
+use h2::server::SendResponse;
+use h2::{RecvStream, SendStream};
+use http_types as http;
+use hyper::{self, Body, Chunk};
+use hyper::server::Service;
+use tokio_core::reactor::Handle;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use os::rewind::Rewind;
+use os::shutdown::Drain;
+
+/// The connection preface which begins every HTTP/2 connection.
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The protocol identifier for HTTP/2 over TLS, as negotiated via ALPN.
+#[cfg(feature = "tls")]
+pub(crate) const ALPN_H2: &[u8] = b"h2";
+
+/// Reads from a cleartext connection until it can be determined whether the client is speaking
+/// HTTP/2 with prior knowledge. Completes with the connection, rewound to its first byte, and
+/// `true` if the HTTP/2 connection preface was received.
+pub(crate) fn detect_preface<I>(io: I) -> DetectPreface<I>
+where
+    I: AsyncRead + AsyncWrite,
+{
+    DetectPreface {
+        io: Some(io),
+        buf: Vec::with_capacity(PREFACE.len()),
+    }
+}
+
+/// The future returned by `detect_preface`.
+pub(crate) struct DetectPreface<I> {
+    io: Option<I>,
+    buf: Vec<u8>,
+}
+
+impl<I> Future for DetectPreface<I>
+where
+    I: AsyncRead + AsyncWrite,
+{
+    type Item = (Rewind<I>, bool);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(Rewind<I>, bool), io::Error> {
+        loop {
+            let is_h2 = if !PREFACE.starts_with(&self.buf) {
+                Some(false)
+            } else if self.buf.len() == PREFACE.len() {
+                Some(true)
+            } else {
+                let mut chunk = [0u8; 24];
+                let wanted = PREFACE.len() - self.buf.len();
+                let io = self.io
+                    .as_mut()
+                    .expect("DetectPreface polled after completion");
+
+                match io.read(&mut chunk[..wanted]) {
+                    // The connection was closed early, which the HTTP/1 implementation will handle.
+                    Ok(0) => Some(false),
+                    Ok(n) => {
+                        self.buf.extend_from_slice(&chunk[..n]);
+                        None
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        return Ok(Async::NotReady)
+                    }
+                    Err(e) => return Err(e),
+                }
+            };
+
+            if let Some(is_h2) = is_h2 {
+                let io = self.io.take().unwrap();
+                let prefix = ::std::mem::replace(&mut self.buf, Vec::new());
+                return Ok(Async::Ready((Rewind::new(prefix, io), is_h2)));
+            }
+        }
+    }
+}
+
+fn h2_error(e: h2::Error) -> hyper::Error {
+    hyper::Error::Io(io::Error::new(io::ErrorKind::Other, e))
+}
+
+enum State<I> {
+    Handshaking(h2::server::Handshake<I, Bytes>),
+    Serving(h2::server::Connection<I, Bytes>),
+}
+
+/// An HTTP/2 connection, which dispatches each stream to `service` on its own task.
+pub(crate) struct Connection<I, S> {
+    state: State<I>,
+    service: S,
+    handle: Handle,
+    draining: bool,
+}
+
+impl<I, S> Connection<I, S>
+where
+    I: AsyncRead + AsyncWrite + 'static,
+    S: Service<Request = hyper::Request, Response = hyper::Response, Error = hyper::Error>,
+    S::Future: 'static,
+{
+    pub(crate) fn new(
+        io: I,
+        service: S,
+        max_header_size: Option<usize>,
+        handle: &Handle,
+    ) -> Connection<I, S> {
+        let mut builder = h2::server::Builder::new();
+
+        if let Some(max) = max_header_size {
+            builder.max_header_list_size(cmp::min(max, u32::max_value() as usize) as u32);
+        }
+
+        Connection {
+            state: State::Handshaking(builder.handshake(io)),
+            service,
+            handle: handle.clone(),
+            draining: false,
+        }
+    }
+
+    fn dispatch(&self, request: http::Request<RecvStream>, respond: SendResponse<Bytes>) {
+        let (mut parts, recv) = request.into_parts();
+
+        if !parts.headers.contains_key(http::header::HOST) {
+            if let Some(authority) = parts.uri.authority_part() {
+                if let Ok(host) = http::header::HeaderValue::from_str(authority.as_str()) {
+                    parts.headers.insert(http::header::HOST, host);
+                }
+            }
+        }
+
+        if let Some(uri) = parts
+            .uri
+            .path_and_query()
+            .and_then(|p| http::Uri::from_str(p.as_str()).ok())
+        {
+            parts.uri = uri;
+        }
+
+        let body = if recv.is_end_stream() {
+            Body::empty()
+        } else {
+            let (tx, body) = Body::pair();
+            self.handle.spawn(ForwardBody {
+                recv,
+                tx,
+                pending: None,
+                done: false,
+            });
+            body
+        };
+
+        let head = parts.method == http::Method::HEAD;
+        let request = hyper::Request::from(http::Request::from_parts(parts, body));
+
+        let response = self.service
+            .call(request)
+            .then(move |result| send_response(result, respond, head));
+
+        self.handle.spawn(response.then(|result| {
+            if let Err(e) = result {
+                debug!("[DEBUG][Unable to send HTTP/2 response: {}]", e);
+            }
+
+            Ok(())
+        }));
+    }
+}
+
+impl<I, S> Future for Connection<I, S>
+where
+    I: AsyncRead + AsyncWrite + 'static,
+    S: Service<Request = hyper::Request, Response = hyper::Response, Error = hyper::Error>,
+    S::Future: 'static,
+{
+    type Item = ();
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<(), hyper::Error> {
+        loop {
+            let conn = match self.state {
+                State::Handshaking(ref mut handshake) => {
+                    match handshake.poll().map_err(h2_error)? {
+                        Async::Ready(conn) => conn,
+                        Async::NotReady => return Ok(Async::NotReady),
+                    }
+                }
+                State::Serving(ref mut conn) => match conn.poll().map_err(h2_error)? {
+                    Async::Ready(Some((request, respond))) => {
+                        self.dispatch(request, respond);
+                        continue;
+                    }
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => return Ok(Async::NotReady),
+                },
+            };
+
+            self.state = State::Serving(conn);
+
+            if self.draining {
+                self.drain();
+            }
+        }
+    }
+}
+
+impl<I, S> Drain for Connection<I, S>
+where
+    I: AsyncRead + AsyncWrite + 'static,
+    S: Service<Request = hyper::Request, Response = hyper::Response, Error = hyper::Error>,
+    S::Future: 'static,
+{
+    fn drain(&mut self) {
+        self.draining = true;
+
+        if let State::Serving(ref mut conn) = self.state {
+            conn.graceful_shutdown();
+        }
+    }
+}
+
+/// Removes the headers which are specific to an HTTP/1 connection, and are forbidden in HTTP/2.
+fn remove_connection_headers(headers: &mut http::HeaderMap) {
+    headers.remove(http::header::CONNECTION);
+    headers.remove(http::header::TRANSFER_ENCODING);
+    headers.remove(http::header::UPGRADE);
+    headers.remove("keep-alive");
+    headers.remove("proxy-connection");
+}
+
+fn send_response(
+    result: Result<hyper::Response, hyper::Error>,
+    mut respond: SendResponse<Bytes>,
+    head: bool,
+) -> Box<Future<Item = (), Error = h2::Error>> {
+    let response = match result {
+        Ok(response) => http::Response::<Body>::from(response),
+        Err(e) => {
+            debug!("[DEBUG][HTTP/2 request failed: {}]", e);
+            respond.send_reset(h2::Reason::INTERNAL_ERROR);
+            return Box::new(::futures::future::ok(()));
+        }
+    };
+
+    let (mut parts, body) = response.into_parts();
+    remove_connection_headers(&mut parts.headers);
+
+    let end_of_stream = head || body.is_empty();
+    let send = match respond.send_response(http::Response::from_parts(parts, ()), end_of_stream) {
+        Ok(send) => send,
+        Err(e) => return Box::new(::futures::future::err(e)),
+    };
+
+    if end_of_stream {
+        Box::new(::futures::future::ok(()))
+    } else {
+        Box::new(SendBody {
+            body,
+            send,
+            buf: None,
+        })
+    }
+}
+
+/// Forwards the body of an HTTP/2 request to the `Body` given to the handler.
+struct ForwardBody {
+    recv: RecvStream,
+    tx: mpsc::Sender<Result<Chunk, hyper::Error>>,
+    pending: Option<Result<Chunk, hyper::Error>>,
+    done: bool,
+}
+
+impl Future for ForwardBody {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            if let Some(item) = self.pending.take() {
+                match self.tx.start_send(item) {
+                    Ok(AsyncSink::Ready) => (),
+                    Ok(AsyncSink::NotReady(item)) => {
+                        self.pending = Some(item);
+                        return Ok(Async::NotReady);
+                    }
+                    // The handler has dropped the body, so the remainder is discarded.
+                    Err(_) => return Ok(Async::Ready(())),
+                }
+            }
+
+            if self.done {
+                return Ok(Async::Ready(()));
+            }
+
+            match self.recv.poll() {
+                Ok(Async::Ready(Some(data))) => {
+                    let _ = self.recv.release_capacity().release_capacity(data.len());
+                    self.pending = Some(Ok(Chunk::from(data)));
+                }
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(())),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    self.pending = Some(Err(h2_error(e)));
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+/// Sends the body of a response, as the flow control window allows.
+struct SendBody {
+    body: Body,
+    send: SendStream<Bytes>,
+    buf: Option<Bytes>,
+}
+
+impl Future for SendBody {
+    type Item = ();
+    type Error = h2::Error;
+
+    fn poll(&mut self) -> Poll<(), h2::Error> {
+        loop {
+            if let Some(mut buf) = self.buf.take() {
+                self.send.reserve_capacity(buf.len());
+
+                match self.send.poll_capacity()? {
+                    Async::Ready(Some(capacity)) => {
+                        let n = cmp::min(capacity, buf.len());
+                        let data = buf.split_to(n);
+                        self.send.send_data(data, false)?;
+
+                        if !buf.is_empty() {
+                            self.buf = Some(buf);
+                        }
+
+                        continue;
+                    }
+                    // The stream has been reset by the client.
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => {
+                        self.buf = Some(buf);
+                        return Ok(Async::NotReady);
+                    }
+                }
+            }
+
+            match self.body.poll() {
+                Ok(Async::Ready(Some(chunk))) => {
+                    if !chunk.is_empty() {
+                        self.buf = Some(Bytes::from(chunk));
+                    }
+                }
+                Ok(Async::Ready(None)) => {
+                    self.send.send_data(Bytes::new(), true)?;
+                    return Ok(Async::Ready(()));
+                }
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    debug!("[DEBUG][Response body failed: {}]", e);
+                    self.send.send_reset(h2::Reason::INTERNAL_ERROR);
+                    return Ok(Async::Ready(()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Read};
+    use std::net;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use hyper::{HttpVersion, Method, StatusCode, Uri};
+    use hyper::header::Headers;
+    use mime;
+    use tokio_core::net::{TcpListener, TcpStream};
+    use tokio_core::reactor::Core;
+
+    use http::response::create_response;
+    use server::ServerBuilder;
+    use state::{FromState, State};
+
+    fn detect(input: &[u8]) -> (Vec<u8>, bool) {
+        let (mut io, is_h2) = detect_preface(Cursor::new(input.to_vec()))
+            .wait()
+            .unwrap();
+
+        let mut replayed = Vec::new();
+        io.read_to_end(&mut replayed).unwrap();
+        (replayed, is_h2)
+    }
+
+    #[test]
+    fn detects_prior_knowledge_preface() {
+        let mut input = PREFACE.to_vec();
+        input.extend_from_slice(b"\x00\x00\x00\x04\x00\x00\x00\x00\x00");
+
+        let (replayed, is_h2) = detect(&input);
+        assert!(is_h2);
+        assert_eq!(replayed, input);
+    }
+
+    #[test]
+    fn http1_request_is_not_http2() {
+        let input = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+        let (replayed, is_h2) = detect(input);
+        assert!(!is_h2);
+        assert_eq!(replayed, &input[..]);
+    }
+
+    #[test]
+    fn connection_closed_during_preface_is_not_http2() {
+        let input = &PREFACE[..10];
+
+        let (replayed, is_h2) = detect(input);
+        assert!(!is_h2);
+        assert_eq!(replayed, input);
+    }
+
+    /// The parts of the request which `ConnectedGothamService::call` stores in `State`.
+    type Received = Arc<Mutex<Option<(Method, Uri, HttpVersion, Headers)>>>;
+
+    /// Starts a server on a new thread, which records the request it receives.
+    fn start(server: ServerBuilder, received: Received) -> net::SocketAddr {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut core = Core::new().unwrap();
+            let handle = core.handle();
+            let listener = TcpListener::from_listener(listener, &addr, &handle).unwrap();
+
+            let serve = server.serve(listener, &handle, move || {
+                let received = received.clone();
+                Ok(move |state: State| {
+                    *received.lock().unwrap() = Some((
+                        Method::borrow_from(&state).clone(),
+                        Uri::borrow_from(&state).clone(),
+                        *HttpVersion::borrow_from(&state),
+                        Headers::borrow_from(&state).clone(),
+                    ));
+
+                    let body = (b"received".to_vec(), mime::TEXT_PLAIN);
+                    let res = create_response(&state, StatusCode::Ok, Some(body));
+                    (state, res)
+                })
+            });
+            core.run(serve).unwrap();
+        });
+
+        addr
+    }
+
+    /// Sends a `POST` request over HTTP/2, returning the status and body of the response.
+    fn post<I>(core: &mut Core, io: I, authority: &str) -> (http::StatusCode, Bytes)
+    where
+        I: AsyncRead + AsyncWrite + 'static,
+    {
+        let handle = core.handle();
+        let request = http::Request::post(format!("https://{}/items?page=2", authority))
+            .header("x-tag", "a")
+            .header("x-tag", "b")
+            .body(())
+            .unwrap();
+
+        let response = h2::client::handshake(io)
+            .and_then(move |(client, conn)| {
+                handle.spawn(conn.map_err(|_| ()));
+                client.ready()
+            })
+            .and_then(|mut client| {
+                let (response, _) = client.send_request(request, true).unwrap();
+                response
+            })
+            .and_then(|response| {
+                let (parts, body) = response.into_parts();
+                body.concat2().map(move |body| (parts.status, body))
+            });
+
+        core.run(response).unwrap()
+    }
+
+    fn assert_received(received: &Received, authority: &str) {
+        let (method, uri, version, headers) = received.lock().unwrap().take().unwrap();
+
+        assert_eq!(method, Method::Post);
+        assert_eq!(uri, "/items?page=2".parse::<Uri>().unwrap());
+        assert_eq!(uri.path(), "/items");
+        assert_eq!(uri.query(), Some("page=2"));
+        assert_eq!(version, HttpVersion::H2);
+
+        let host = headers.get_raw("Host").unwrap();
+        assert_eq!(host.one(), Some(authority.as_bytes()));
+
+        let tags = headers
+            .get_raw("X-Tag")
+            .unwrap()
+            .iter()
+            .map(|line| line.to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn prior_knowledge_request_populates_state() {
+        let received = Received::default();
+        let addr = start(ServerBuilder::new().with_http2(true), received.clone());
+        let authority = format!("localhost:{}", addr.port());
+
+        let mut core = Core::new().unwrap();
+        let io = core.run(TcpStream::connect(&addr, &core.handle())).unwrap();
+        let (status, body) = post(&mut core, io, &authority);
+
+        assert_eq!(status, http::StatusCode::OK);
+        assert_eq!(&body[..], b"received");
+        assert_received(&received, &authority);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn alpn_h2_request_populates_state() {
+        use tls;
+
+        let received = Received::default();
+        let server = ServerBuilder::new()
+            .with_tls(tls::test_config())
+            .with_http2(true);
+        let addr = start(server, received.clone());
+        let authority = format!("localhost:{}", addr.port());
+
+        let mut core = Core::new().unwrap();
+        let connect = TcpStream::connect(&addr, &core.handle())
+            .and_then(|io| tls::connect(io, &[ALPN_H2, b"http/1.1"]));
+        let io = core.run(connect).unwrap();
+        assert_eq!(io.alpn_protocol(), Some(ALPN_H2));
+
+        let (status, body) = post(&mut core, io, &authority);
+
+        assert_eq!(status, http::StatusCode::OK);
+        assert_eq!(&body[..], b"received");
+        assert_received(&received, &authority);
+    }
+}
@@ -0,0 +1,288 @@
+//! Defines the wrappers used to close connections which have been idle for longer than the
+//! configured idle timeout.
+//!
+//! A connection is considered idle when it has no request in flight, and no data has been read
+//! from or written to the socket.
+
+use std::cell::Cell;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll};
+use hyper::server::Service;
+use tokio_core::reactor::{Handle, Timeout};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use os::shutdown::Drain;
+
+/// Records the activity on a single connection.
+#[derive(Clone)]
+pub(crate) struct Activity {
+    last: Rc<Cell<Instant>>,
+    in_flight: Rc<Cell<usize>>,
+}
+
+impl Activity {
+    pub(crate) fn new() -> Activity {
+        Activity {
+            last: Rc::new(Cell::new(Instant::now())),
+            in_flight: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn touch(&self) {
+        self.last.set(Instant::now());
+    }
+
+    /// Determines how long the connection has been idle, or `None` if a request is in flight.
+    fn idle_for(&self) -> Option<Duration> {
+        if self.in_flight.get() > 0 {
+            None
+        } else {
+            Some(self.last.get().elapsed())
+        }
+    }
+}
+
+/// Wraps the socket of a connection, recording activity whenever data is read or written.
+pub(crate) struct ActivityIo<T> {
+    io: T,
+    activity: Activity,
+}
+
+impl<T> ActivityIo<T> {
+    pub(crate) fn new(io: T, activity: Activity) -> ActivityIo<T> {
+        ActivityIo { io, activity }
+    }
+}
+
+impl<T> Read for ActivityIo<T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.io.read(buf)?;
+        self.activity.touch();
+        Ok(n)
+    }
+}
+
+impl<T> Write for ActivityIo<T>
+where
+    T: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.io.write(buf)?;
+        self.activity.touch();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.io.flush()
+    }
+}
+
+impl<T> AsyncRead for ActivityIo<T>
+where
+    T: AsyncRead,
+{
+}
+
+impl<T> AsyncWrite for ActivityIo<T>
+where
+    T: AsyncWrite,
+{
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.io.shutdown()
+    }
+}
+
+/// Wraps the `Service` of a connection, recording which requests are in flight so that a slow
+/// handler isn't mistaken for an idle connection.
+pub(crate) struct ActivityService<S> {
+    service: S,
+    activity: Activity,
+}
+
+impl<S> ActivityService<S> {
+    pub(crate) fn new(service: S, activity: Activity) -> ActivityService<S> {
+        ActivityService { service, activity }
+    }
+}
+
+impl<S> Service for ActivityService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = InFlight<S::Future>;
+
+    fn call(&self, req: Self::Request) -> Self::Future {
+        let in_flight = &self.activity.in_flight;
+        in_flight.set(in_flight.get() + 1);
+
+        InFlight {
+            f: self.service.call(req),
+            activity: self.activity.clone(),
+        }
+    }
+}
+
+/// A response future which is counted as in flight until it is dropped.
+pub(crate) struct InFlight<F> {
+    f: F,
+    activity: Activity,
+}
+
+impl<F> Future for InFlight<F>
+where
+    F: Future,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        self.f.poll()
+    }
+}
+
+impl<F> Drop for InFlight<F> {
+    fn drop(&mut self) {
+        let in_flight = &self.activity.in_flight;
+        in_flight.set(in_flight.get() - 1);
+        self.activity.touch();
+    }
+}
+
+/// Wraps a connection, completing it once it has been idle for longer than `timeout`.
+pub(crate) struct IdleTimeout<C> {
+    conn: C,
+    activity: Activity,
+    timeout: Duration,
+    timer: Timeout,
+}
+
+impl<C> IdleTimeout<C>
+where
+    C: Drain<Error = ::hyper::Error>,
+{
+    pub(crate) fn new(
+        conn: C,
+        activity: Activity,
+        timeout: Duration,
+        handle: &Handle,
+    ) -> io::Result<IdleTimeout<C>> {
+        let timer = Timeout::new(timeout, handle)?;
+
+        Ok(IdleTimeout {
+            conn,
+            activity,
+            timeout,
+            timer,
+        })
+    }
+}
+
+impl<C> Future for IdleTimeout<C>
+where
+    C: Drain<Error = ::hyper::Error>,
+{
+    type Item = ();
+    type Error = ::hyper::Error;
+
+    fn poll(&mut self) -> Poll<(), ::hyper::Error> {
+        if let Async::Ready(()) = self.conn.poll()? {
+            return Ok(Async::Ready(()));
+        }
+
+        // The timer is reset for as long as the connection has seen activity within the timeout, so
+        // this loop will only complete the connection once it has been idle for the whole period.
+        while let Async::Ready(()) = self.timer.poll()? {
+            match self.activity.idle_for() {
+                Some(idle) if idle >= self.timeout => {
+                    debug!("[DEBUG][Closing connection after {:?} idle]", idle);
+                    return Ok(Async::Ready(()));
+                }
+                Some(idle) => self.timer.reset(Instant::now() + (self.timeout - idle)),
+                None => self.timer.reset(Instant::now() + self.timeout),
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+impl<C> Drain for IdleTimeout<C>
+where
+    C: Drain<Error = ::hyper::Error>,
+{
+    fn drain(&mut self) {
+        self.conn.drain()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future;
+    use hyper::server::service_fn;
+    use tokio_core::reactor::Core;
+
+    /// A connection which never completes on its own.
+    struct OpenConnection;
+
+    impl Future for OpenConnection {
+        type Item = ();
+        type Error = ::hyper::Error;
+
+        fn poll(&mut self) -> Poll<(), ::hyper::Error> {
+            Ok(Async::NotReady)
+        }
+    }
+
+    impl Drain for OpenConnection {
+        fn drain(&mut self) {}
+    }
+
+    #[test]
+    fn idle_connection_is_closed() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let timeout = Duration::from_millis(20);
+        let conn = IdleTimeout::new(OpenConnection, Activity::new(), timeout, &handle).unwrap();
+
+        let start = Instant::now();
+        core.run(conn).unwrap();
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn connection_with_request_in_flight_is_not_closed() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let activity = Activity::new();
+        let service = ActivityService::new(
+            service_fn(|_: ()| future::empty::<(), ::hyper::Error>()),
+            activity.clone(),
+        );
+        let request = service.call(());
+
+        let timeout = Duration::from_millis(10);
+        let conn = IdleTimeout::new(OpenConnection, activity.clone(), timeout, &handle).unwrap();
+        let later = Timeout::new(Duration::from_millis(50), &handle).unwrap();
+
+        match core.run(conn.select2(later)) {
+            Ok(future::Either::B(_)) => (),
+            _ => panic!("connection closed while a request was in flight"),
+        }
+
+        drop(request);
+        assert!(activity.idle_for().is_some());
+    }
+}
@@ -1,3 +1,36 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use futures::{Async, Future, Poll, Stream};
+use hyper::server::Http;
+use tokio_core;
+use tokio_core::reactor::Handle;
+use tokio_core::reactor::Timeout;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use error::StartError;
+use handler::NewHandler;
+use server::ServerBuilder;
+use service::{ConnectedGothamService, GothamService};
+use state::PeerAddr;
+#[cfg(feature = "tls")]
+use state::Scheme;
+#[cfg(feature = "tls")]
+use tls::{self, TlsConfig};
+
+use self::idle::{Activity, ActivityIo, ActivityService, IdleTimeout};
+use self::shutdown::{Connections, Drain};
+
+#[cfg(feature = "http2")]
+mod http2;
+mod idle;
+mod proxy;
+mod rewind;
+mod shutdown;
+
 #[cfg(not(windows))]
 pub mod unix;
 #[cfg(not(windows))]
@@ -7,3 +40,423 @@ pub use self::unix as current;
 pub mod windows;
 #[cfg(windows)]
 pub use self::windows as current;
+
+/// Waits for each of the `threads` reactor threads to report whether it started successfully,
+/// returning the first error reported.
+fn await_started(
+    started: Receiver<Result<(), StartError>>,
+    threads: usize,
+) -> Result<(), StartError> {
+    // A thread which panics before reporting drops its sender, which ends the iteration early.
+    started.iter().take(threads).collect()
+}
+
+/// Waits for the reactor threads to finish after the server has been shut down.
+fn join_workers(workers: Vec<JoinHandle<()>>) {
+    for worker in workers {
+        if worker.join().is_err() {
+            error!(target: "gotham::start", " Gotham reactor thread panicked during shutdown");
+        }
+    }
+}
+
+/// Serves the application on a reactor which is managed by the caller. See `ServerBuilder::serve`.
+pub(crate) fn serve<NH>(
+    server: ServerBuilder,
+    listener: tokio_core::net::TcpListener,
+    handle: &Handle,
+    new_handler: NH,
+) -> Serve
+where
+    NH: NewHandler + 'static,
+{
+    let connections = Connections::new();
+    let protocol = server.protocol();
+    let gotham_service = GothamService::new(Arc::new(new_handler), handle.clone())
+        .with_max_body_size(server.max_body_size);
+    let handle = handle.clone();
+
+    let incoming = listener
+        .incoming()
+        .map(|(socket, addr)| (socket, PeerAddr::Tcp(addr)));
+    let tracked = connections.clone();
+
+    let accept = connections
+        .limit(incoming, server.max_connections)
+        .for_each(move |(socket, addr)| {
+            let service = gotham_service.connect(addr);
+            serve_connection(&server, &protocol, socket, service, &handle, &tracked);
+            Ok(())
+        });
+
+    Serve {
+        accept: Box::new(accept),
+        connections,
+    }
+}
+
+/// A future which accepts and serves connections on a reactor managed by the caller, returned by
+/// `gotham::serve` and `ServerBuilder::serve`.
+///
+/// The future only completes if the listener fails. When it is dropped, the listener is closed and
+/// the connections it accepted are closed once their in-flight requests have completed.
+pub struct Serve {
+    accept: Box<Future<Item = (), Error = io::Error>>,
+    connections: Connections,
+}
+
+impl Future for Serve {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(), io::Error> {
+        self.accept.poll()
+    }
+}
+
+impl Drop for Serve {
+    fn drop(&mut self) {
+        let _ = self.connections.drain();
+    }
+}
+
+/// Serves a single connection on the reactor, applying the connection settings from the
+/// `ServerBuilder`.
+fn serve_connection<NH, I>(
+    server: &ServerBuilder,
+    protocol: &Http,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    if server.proxy_protocol {
+        serve_proxied_connection(server, protocol, io, service, handle, connections);
+    } else {
+        serve_accepted(server, protocol, io, service, handle, connections);
+    }
+}
+
+/// Reads the PROXY protocol header from the connection, and then serves it on behalf of the
+/// client which the header identifies.
+fn serve_proxied_connection<NH, I>(
+    server: &ServerBuilder,
+    protocol: &Http,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    let handshake_timeout = server.handshake_timeout;
+    let server = server.clone();
+    let protocol = protocol.clone();
+    let conn_connections = connections.clone();
+    let conn_handle = handle.clone();
+
+    let header = proxy::read_header(io).then(move |result| {
+        match result {
+            Ok((io, source)) => {
+                let service = match source {
+                    Some(addr) => service.with_client_addr(PeerAddr::Tcp(addr)),
+                    None => service,
+                };
+
+                serve_accepted(&server, &protocol, io, service, &conn_handle, &conn_connections);
+            }
+            Err(e) => debug!("[DEBUG][Invalid PROXY protocol header: {}]", e),
+        }
+
+        Ok(())
+    });
+
+    spawn_preparation(header, handshake_timeout, handle, connections);
+}
+
+/// Serves a connection which is ready to begin with either a TLS handshake or HTTP.
+fn serve_accepted<NH, I>(
+    server: &ServerBuilder,
+    protocol: &Http,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    #[cfg(feature = "tls")]
+    {
+        if let Some(ref tls) = server.tls {
+            serve_tls_connection(server, tls, protocol, io, service, handle, connections);
+            return;
+        }
+    }
+
+    #[cfg(feature = "http2")]
+    {
+        if server.http2 {
+            serve_cleartext_connection(server, protocol, io, service, handle, connections);
+            return;
+        }
+    }
+
+    serve_http(
+        protocol,
+        server.idle_timeout,
+        io,
+        service,
+        handle,
+        connections,
+    );
+}
+
+/// Completes a TLS handshake on the connection, and then serves it with the protocol negotiated
+/// via ALPN.
+#[cfg(feature = "tls")]
+fn serve_tls_connection<NH, I>(
+    server: &ServerBuilder,
+    tls: &TlsConfig,
+    protocol: &Http,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    let idle_timeout = server.idle_timeout;
+    #[cfg(feature = "http2")]
+    let max_header_size = server.max_header_size;
+    let protocol = protocol.clone();
+    let conn_connections = connections.clone();
+    let conn_handle = handle.clone();
+
+    let config = tls.server_config(server.http2_enabled());
+    let handshake = tls::accept(io, &config).then(move |result| {
+        let io = match result {
+            Ok(io) => io,
+            Err(e) => {
+                debug!("[DEBUG][TLS handshake failed: {}]", e);
+                return Ok(());
+            }
+        };
+
+        let service = service.with_scheme(Scheme::Https);
+
+        #[cfg(feature = "http2")]
+        {
+            if io.alpn_protocol() == Some(http2::ALPN_H2) {
+                serve_h2(
+                    max_header_size,
+                    idle_timeout,
+                    io,
+                    service,
+                    &conn_handle,
+                    &conn_connections,
+                );
+                return Ok(());
+            }
+        }
+
+        serve_http(
+            &protocol,
+            idle_timeout,
+            io,
+            service,
+            &conn_handle,
+            &conn_connections,
+        );
+        Ok(())
+    });
+
+    spawn_preparation(handshake, server.handshake_timeout, handle, connections);
+}
+
+/// Serves a cleartext connection with HTTP/2 if the client sends the HTTP/2 connection preface
+/// (prior knowledge), and with HTTP/1 otherwise.
+#[cfg(feature = "http2")]
+fn serve_cleartext_connection<NH, I>(
+    server: &ServerBuilder,
+    protocol: &Http,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    let idle_timeout = server.idle_timeout;
+    let max_header_size = server.max_header_size;
+    let protocol = protocol.clone();
+    let conn_connections = connections.clone();
+    let conn_handle = handle.clone();
+
+    let detect = http2::detect_preface(io).then(move |result| {
+        match result {
+            Ok((io, true)) => serve_h2(
+                max_header_size,
+                idle_timeout,
+                io,
+                service,
+                &conn_handle,
+                &conn_connections,
+            ),
+            Ok((io, false)) => serve_http(
+                &protocol,
+                idle_timeout,
+                io,
+                service,
+                &conn_handle,
+                &conn_connections,
+            ),
+            Err(e) => debug!("[DEBUG][Unable to read from connection: {}]", e),
+        }
+
+        Ok(())
+    });
+
+    spawn_preparation(detect, server.handshake_timeout, handle, connections);
+}
+
+/// Spawns a future which prepares a connection to be served, such as a TLS handshake. The
+/// connection is tracked from this point, so it counts towards the connection limit, and it is
+/// abandoned if it takes longer than the handshake timeout or when shutdown begins.
+fn spawn_preparation<F>(
+    prepare: F,
+    handshake_timeout: Duration,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    F: Future<Item = (), Error = ()> + 'static,
+{
+    match Timeout::new(handshake_timeout, handle) {
+        Ok(deadline) => {
+            let preparation = Preparation {
+                prepare,
+                deadline,
+                abandoned: false,
+            };
+            handle.spawn(connections.track(preparation));
+        }
+        Err(e) => error!("[ERROR][Unable to create connection handshake timeout: {}]", e),
+    }
+}
+
+/// A connection which is being prepared to be served. Once prepared, the connection is tracked
+/// again by the future which serves it, before this one completes, so it is counted throughout.
+struct Preparation<F> {
+    prepare: F,
+    deadline: Timeout,
+    abandoned: bool,
+}
+
+impl<F> Future for Preparation<F>
+where
+    F: Future<Item = (), Error = ()>,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if self.abandoned {
+            return Ok(Async::Ready(()));
+        }
+
+        if let Async::Ready(()) = self.prepare.poll()? {
+            return Ok(Async::Ready(()));
+        }
+
+        match self.deadline.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            _ => {
+                debug!("[DEBUG][Connection was not prepared within the handshake timeout]");
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+}
+
+impl<F> Drain for Preparation<F>
+where
+    F: Future<Item = (), Error = ()>,
+{
+    // No request can be in flight yet, so the connection is closed immediately.
+    fn drain(&mut self) {
+        self.abandoned = true;
+    }
+}
+
+/// Serves HTTP/2 on a connection which has been negotiated to use it.
+#[cfg(feature = "http2")]
+fn serve_h2<NH, I>(
+    max_header_size: Option<usize>,
+    idle_timeout: Option<Duration>,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    match idle_timeout {
+        Some(timeout) => {
+            let activity = Activity::new();
+            let conn = http2::Connection::new(
+                ActivityIo::new(io, activity.clone()),
+                ActivityService::new(service, activity.clone()),
+                max_header_size,
+                handle,
+            );
+
+            match IdleTimeout::new(conn, activity, timeout, handle) {
+                Ok(conn) => handle.spawn(connections.track(conn).then(|_| Ok(()))),
+                Err(e) => error!("[ERROR][Unable to create connection idle timeout: {}]", e),
+            }
+        }
+        None => {
+            let conn = http2::Connection::new(io, service, max_header_size, handle);
+            handle.spawn(connections.track(conn).then(|_| Ok(())));
+        }
+    }
+}
+
+/// Serves HTTP on a connection which is ready to exchange requests and responses.
+fn serve_http<NH, I>(
+    protocol: &Http,
+    idle_timeout: Option<Duration>,
+    io: I,
+    service: ConnectedGothamService<NH>,
+    handle: &Handle,
+    connections: &Connections,
+) where
+    NH: NewHandler + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    match idle_timeout {
+        Some(timeout) => {
+            let activity = Activity::new();
+            let conn = protocol.serve_connection(
+                ActivityIo::new(io, activity.clone()),
+                ActivityService::new(service, activity.clone()),
+            );
+
+            match IdleTimeout::new(conn, activity, timeout, handle) {
+                Ok(conn) => handle.spawn(connections.track(conn).then(|_| Ok(()))),
+                Err(e) => error!("[ERROR][Unable to create connection idle timeout: {}]", e),
+            }
+        }
+        None => {
+            let conn = protocol.serve_connection(io, service);
+            handle.spawn(connections.track(conn).then(|_| Ok(())));
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Defines parsing of the PROXY protocol header, which a load balancer sends at the start of each
+//! connection to identify the client it is forwarding. Both the human readable version 1 and the
+//! binary version 2 of the protocol are supported.
+//!
+//! See https://www.haproxy.org/download/1.8/doc/proxy-protocol.txt for the specification.
+
+use std::cmp;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str;
+
+use futures::{Async, Future, Poll};
+use tokio_io::AsyncRead;
+
+use os::rewind::Rewind;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// The maximum length of a version 1 header, including the terminating CRLF.
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\x00\r\nQUIT\n";
+
+/// The length of the fixed part of a version 2 header, which is followed by the addresses.
+const V2_HEADER_LEN: usize = 16;
+
+/// Reads the PROXY protocol header from the start of a connection. Completes with the connection,
+/// positioned after the header, and the source address of the client if the header included one.
+///
+/// A connection which doesn't begin with a valid header is rejected with an error.
+pub(crate) fn read_header<I>(io: I) -> ReadHeader<I>
+where
+    I: AsyncRead,
+{
+    ReadHeader {
+        io: Some(io),
+        buf: Vec::new(),
+    }
+}
+
+/// The future returned by `read_header`.
+pub(crate) struct ReadHeader<I> {
+    io: Option<I>,
+    buf: Vec<u8>,
+}
+
+impl<I> Future for ReadHeader<I>
+where
+    I: AsyncRead,
+{
+    type Item = (Rewind<I>, Option<SocketAddr>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(Rewind<I>, Option<SocketAddr>), io::Error> {
+        loop {
+            if let Parsed::Complete { len, source } = parse(&self.buf)? {
+                let io = self.io.take().expect("ReadHeader polled after completion");
+
+                // Anything read after the header belongs to the proxied connection.
+                let rest = self.buf.split_off(len);
+                return Ok(Async::Ready((Rewind::new(rest, io), source)));
+            }
+
+            let mut chunk = [0u8; 256];
+            let io = self.io
+                .as_mut()
+                .expect("ReadHeader polled after completion");
+
+            match io.read(&mut chunk) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed before PROXY protocol header was received",
+                    ))
+                }
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(Async::NotReady)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Parsed {
+    /// More data is required to parse the header.
+    Incomplete,
+
+    /// The header occupies the first `len` bytes. The source address is absent when the proxy
+    /// sent the header on its own behalf (such as for a health check), or doesn't know the client.
+    Complete {
+        len: usize,
+        source: Option<SocketAddr>,
+    },
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse(buf: &[u8]) -> io::Result<Parsed> {
+    match buf.first() {
+        None => Ok(Parsed::Incomplete),
+        Some(&b'P') => parse_v1(buf),
+        Some(&b'\r') => parse_v2(buf),
+        Some(_) => Err(invalid("missing PROXY protocol header")),
+    }
+}
+
+fn parse_v1(buf: &[u8]) -> io::Result<Parsed> {
+    let n = cmp::min(buf.len(), V1_PREFIX.len());
+    if buf[..n] != V1_PREFIX[..n] {
+        return Err(invalid("missing PROXY protocol header"));
+    }
+
+    let end = match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(end) if end + 2 <= V1_MAX_LEN => end,
+        None if buf.len() < V1_MAX_LEN => return Ok(Parsed::Incomplete),
+        _ => return Err(invalid("PROXY protocol header is too long")),
+    };
+
+    let line = str::from_utf8(&buf[V1_PREFIX.len()..end])
+        .map_err(|_| invalid("PROXY protocol header is not valid ASCII"))?;
+    let fields = line.split(' ').collect::<Vec<_>>();
+
+    let source = match fields[0] {
+        "UNKNOWN" => None,
+        "TCP4" | "TCP6" if fields.len() == 5 => {
+            let ip = fields[1]
+                .parse::<IpAddr>()
+                .map_err(|_| invalid("invalid source address in PROXY protocol header"))?;
+            let port = fields[3]
+                .parse::<u16>()
+                .map_err(|_| invalid("invalid source port in PROXY protocol header"))?;
+
+            if ip.is_ipv4() != (fields[0] == "TCP4") {
+                return Err(invalid("PROXY protocol address does not match its family"));
+            }
+
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(invalid("unsupported PROXY protocol header")),
+    };
+
+    Ok(Parsed::Complete {
+        len: end + 2,
+        source,
+    })
+}
+
+fn parse_v2(buf: &[u8]) -> io::Result<Parsed> {
+    let n = cmp::min(buf.len(), V2_SIGNATURE.len());
+    if buf[..n] != V2_SIGNATURE[..n] {
+        return Err(invalid("missing PROXY protocol header"));
+    }
+
+    if buf.len() < V2_HEADER_LEN {
+        return Ok(Parsed::Incomplete);
+    }
+
+    if buf[12] >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    let len = ((buf[14] as usize) << 8) | buf[15] as usize;
+    if buf.len() < V2_HEADER_LEN + len {
+        return Ok(Parsed::Incomplete);
+    }
+
+    let addrs = &buf[V2_HEADER_LEN..V2_HEADER_LEN + len];
+    let port = |b: &[u8]| ((b[0] as u16) << 8) | b[1] as u16;
+
+    let source = match buf[12] & 0x0f {
+        // LOCAL: the connection was opened by the proxy itself.
+        0x0 => None,
+        // PROXY: the address family is in the high nibble, and any type of transport is accepted.
+        0x1 => match buf[13] >> 4 {
+            0x1 if addrs.len() >= 12 => {
+                let ip = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+                Some(SocketAddr::new(IpAddr::V4(ip), port(&addrs[8..10])))
+            }
+            0x2 if addrs.len() >= 36 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&addrs[..16]);
+                let ip = Ipv6Addr::from(octets);
+                Some(SocketAddr::new(IpAddr::V6(ip), port(&addrs[32..34])))
+            }
+            0x1 | 0x2 => return Err(invalid("PROXY protocol addresses are truncated")),
+            // AF_UNSPEC and AF_UNIX don't identify a remote client.
+            _ => None,
+        },
+        _ => return Err(invalid("unsupported PROXY protocol command")),
+    };
+
+    Ok(Parsed::Complete {
+        len: V2_HEADER_LEN + len,
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Cursor, Read};
+
+    fn v2(command: u8, family: u8, addrs: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.push((addrs.len() >> 8) as u8);
+        header.push(addrs.len() as u8);
+        header.extend_from_slice(addrs);
+        header
+    }
+
+    #[test]
+    fn parses_v1_tcp4() {
+        let buf = b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\nGET /";
+
+        assert_eq!(
+            parse(buf).unwrap(),
+            Parsed::Complete {
+                len: 43,
+                source: Some("192.168.0.1:56324".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_v1_tcp6() {
+        let buf = b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 80\r\n";
+
+        assert_eq!(
+            parse(buf).unwrap(),
+            Parsed::Complete {
+                len: buf.len(),
+                source: Some("[2001:db8::1]:4000".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_v1_unknown() {
+        let buf = b"PROXY UNKNOWN\r\n";
+
+        assert_eq!(
+            parse(buf).unwrap(),
+            Parsed::Complete {
+                len: buf.len(),
+                source: None,
+            }
+        );
+    }
+
+    #[test]
+    fn incomplete_v1_needs_more_data() {
+        assert_eq!(parse(b"PRO").unwrap(), Parsed::Incomplete);
+        assert_eq!(parse(b"PROXY TCP4 192.168").unwrap(), Parsed::Incomplete);
+    }
+
+    #[test]
+    fn rejects_invalid_v1() {
+        assert!(parse(b"PROXY TCP4 not-an-ip 10.0.0.1 1 2\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 2001:db8::1 10.0.0.1 1 2\r\n").is_err());
+        assert!(parse(&[b'P'; V1_MAX_LEN][..]).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(parse(b"GET / HTTP/1.1\r\n").is_err());
+    }
+
+    #[test]
+    fn parses_v2_ipv4() {
+        let addrs = [192, 168, 0, 1, 10, 0, 0, 1, 0xdc, 0x04, 0x01, 0xbb];
+        let buf = v2(0x1, 0x11, &addrs);
+
+        assert_eq!(
+            parse(&buf).unwrap(),
+            Parsed::Complete {
+                len: 28,
+                source: Some("192.168.0.1:56324".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_v2_ipv6_with_tlvs() {
+        let mut addrs = vec![0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        addrs.extend_from_slice(&[0; 16]);
+        addrs.extend_from_slice(&[0x0f, 0xa0, 0x00, 0x50]);
+        // A TLV, which is skipped.
+        addrs.extend_from_slice(&[0x04, 0x00, 0x01, 0x00]);
+        let buf = v2(0x1, 0x21, &addrs);
+
+        assert_eq!(
+            parse(&buf).unwrap(),
+            Parsed::Complete {
+                len: buf.len(),
+                source: Some("[2001:db8::1]:4000".parse().unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_v2_local() {
+        let buf = v2(0x0, 0x00, &[]);
+
+        assert_eq!(
+            parse(&buf).unwrap(),
+            Parsed::Complete {
+                len: 16,
+                source: None,
+            }
+        );
+    }
+
+    #[test]
+    fn incomplete_v2_needs_more_data() {
+        let buf = v2(0x1, 0x11, &[192, 168, 0, 1, 10, 0, 0, 1, 0xdc, 0x04, 0x01, 0xbb]);
+
+        assert_eq!(parse(&buf[..10]).unwrap(), Parsed::Incomplete);
+        assert_eq!(parse(&buf[..20]).unwrap(), Parsed::Incomplete);
+    }
+
+    #[test]
+    fn read_header_rewinds_to_request() {
+        let input = b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\nGET / HTTP/1.1\r\n\r\n";
+
+        let (mut io, source) = read_header(Cursor::new(input.to_vec())).wait().unwrap();
+        assert_eq!(source, Some("192.168.0.1:56324".parse().unwrap()));
+
+        let mut rest = Vec::new();
+        io.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n\r\n");
+    }
+}
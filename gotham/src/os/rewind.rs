@@ -0,0 +1,65 @@
+//! Defines `Rewind`, which allows the start of a connection to be inspected before it is served.
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use futures::Poll;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// A connection which replays bytes which have already been read from the socket, such as while
+/// detecting the protocol in use, before reading any further.
+pub(crate) struct Rewind<I> {
+    prefix: Vec<u8>,
+    pos: usize,
+    io: I,
+}
+
+impl<I> Rewind<I> {
+    pub(crate) fn new(prefix: Vec<u8>, io: I) -> Rewind<I> {
+        Rewind { prefix, pos: 0, io }
+    }
+}
+
+impl<I> Read for Rewind<I>
+where
+    I: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.prefix.len() {
+            let n = cmp::min(buf.len(), self.prefix.len() - self.pos);
+            buf[..n].copy_from_slice(&self.prefix[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
+
+        self.io.read(buf)
+    }
+}
+
+impl<I> Write for Rewind<I>
+where
+    I: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.io.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.io.flush()
+    }
+}
+
+impl<I> AsyncRead for Rewind<I>
+where
+    I: AsyncRead,
+{
+}
+
+impl<I> AsyncWrite for Rewind<I>
+where
+    I: AsyncWrite,
+{
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.io.shutdown()
+    }
+}
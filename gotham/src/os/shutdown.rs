@@ -0,0 +1,378 @@
+//! Defines the connection tracking used to gracefully shut down a Gotham server, allowing
+//! in-flight requests to complete before the reactor is stopped.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use futures::{task, Async, Future, Poll, Stream};
+use hyper::{self, Request, Response};
+use hyper::server::{Connection, Service};
+use tokio_core::reactor::{Core, Timeout};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Drains the connections being served by the reactor, waiting at most `timeout` for in-flight
+/// requests to complete. Connections which remain open after `timeout` are dropped with the
+/// reactor.
+pub(crate) fn drain(core: &mut Core, connections: &Connections, timeout: Duration) {
+    let active = connections.active();
+
+    if active == 0 {
+        return;
+    }
+
+    info!(
+        target: "gotham::start",
+        " Gotham draining {} connection(s) for up to {:?}",
+        active,
+        timeout,
+    );
+
+    let deadline = Timeout::new(timeout, &core.handle())
+        .expect("unable to create timeout for draining connections")
+        .map_err(|_| ());
+
+    // Neither future can fail, so the outcome is determined by the remaining connection count.
+    let _ = core.run(connections.drain().select(deadline));
+
+    let remaining = connections.active();
+    if remaining > 0 {
+        warn!(
+            target: "gotham::start",
+            " Gotham dropping {} connection(s) still open after {:?}",
+            remaining,
+            timeout,
+        );
+    }
+}
+
+/// A connection which can be asked to stop accepting further requests once the in-flight request
+/// (if any) has been completed.
+pub(crate) trait Drain: Future<Item = ()> {
+    /// Requests that the connection closes after the current request. An idle connection is closed
+    /// immediately.
+    fn drain(&mut self);
+}
+
+impl<I, S> Drain for Connection<I, S>
+where
+    S: Service<Request = Request, Response = Response, Error = hyper::Error> + 'static,
+    I: AsyncRead + AsyncWrite + 'static,
+{
+    fn drain(&mut self) {
+        self.disable_keep_alive()
+    }
+}
+
+struct Inner {
+    active: usize,
+    next_id: usize,
+    draining: bool,
+    connections: HashMap<usize, task::Task>,
+    blocker: Option<task::Task>,
+    acceptor: Option<task::Task>,
+}
+
+/// Tracks the connections being served by a single reactor. Connections are registered via
+/// `track`, and `drain` returns a future which completes when every tracked connection has been
+/// closed.
+#[derive(Clone)]
+pub(crate) struct Connections {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Connections {
+    pub(crate) fn new() -> Connections {
+        Connections {
+            inner: Rc::new(RefCell::new(Inner {
+                active: 0,
+                next_id: 0,
+                draining: false,
+                connections: HashMap::new(),
+                blocker: None,
+                acceptor: None,
+            })),
+        }
+    }
+
+    /// Wraps the connection so that it is counted until it completes, and will be drained when
+    /// shutdown begins.
+    pub(crate) fn track<C>(&self, conn: C) -> TrackedConnection<C>
+    where
+        C: Drain,
+    {
+        let id = {
+            let mut inner = self.inner.borrow_mut();
+            inner.active += 1;
+            inner.next_id = inner.next_id.wrapping_add(1);
+            inner.next_id
+        };
+
+        TrackedConnection {
+            id,
+            conn,
+            drained: false,
+            connections: Rc::downgrade(&self.inner),
+        }
+    }
+
+    /// Begins draining every tracked connection, and returns a future which completes once all
+    /// tracked connections have been closed.
+    pub(crate) fn drain(&self) -> WaitUntilDrained {
+        let connections = {
+            let mut inner = self.inner.borrow_mut();
+            inner.draining = true;
+            inner.connections.drain().collect::<Vec<_>>()
+        };
+
+        for (_, task) in connections {
+            task.notify();
+        }
+
+        WaitUntilDrained {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Limits the stream of incoming connections, such that no more than `max` tracked connections
+    /// are open at any time. While at the limit, new connections are left in the listener's backlog
+    /// rather than being accepted.
+    pub(crate) fn limit<S>(&self, incoming: S, max: Option<usize>) -> LimitConnections<S>
+    where
+        S: Stream,
+    {
+        LimitConnections {
+            incoming,
+            max,
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// The number of connections which are currently open.
+    pub(crate) fn active(&self) -> usize {
+        self.inner.borrow().active
+    }
+}
+
+/// A connection which is being tracked by `Connections`.
+pub(crate) struct TrackedConnection<C>
+where
+    C: Drain,
+{
+    id: usize,
+    conn: C,
+    drained: bool,
+    connections: Weak<RefCell<Inner>>,
+}
+
+impl<C> Future for TrackedConnection<C>
+where
+    C: Drain,
+{
+    type Item = ();
+    type Error = C::Error;
+
+    fn poll(&mut self) -> Poll<(), C::Error> {
+        if !self.drained {
+            let draining = match self.connections.upgrade() {
+                Some(inner) => {
+                    let mut inner = inner.borrow_mut();
+
+                    if !inner.draining {
+                        inner.connections.entry(self.id).or_insert_with(task::current);
+                    }
+
+                    inner.draining
+                }
+                // The server which accepted the connection has been dropped.
+                None => true,
+            };
+
+            if draining {
+                self.drained = true;
+                self.conn.drain();
+            }
+        }
+
+        self.conn.poll()
+    }
+}
+
+impl<C> Drop for TrackedConnection<C>
+where
+    C: Drain,
+{
+    fn drop(&mut self) {
+        if let Some(inner) = self.connections.upgrade() {
+            let mut inner = inner.borrow_mut();
+            inner.active -= 1;
+            inner.connections.remove(&self.id);
+
+            if let Some(task) = inner.acceptor.take() {
+                task.notify();
+            }
+
+            if inner.active == 0 {
+                if let Some(task) = inner.blocker.take() {
+                    task.notify();
+                }
+            }
+        }
+    }
+}
+
+/// A stream of incoming connections which is paused while the maximum number of connections are
+/// open.
+pub(crate) struct LimitConnections<S> {
+    incoming: S,
+    max: Option<usize>,
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl<S> Stream for LimitConnections<S>
+where
+    S: Stream,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if let Some(max) = self.max {
+            let mut inner = self.inner.borrow_mut();
+
+            if inner.active >= max {
+                inner.acceptor = Some(task::current());
+                return Ok(Async::NotReady);
+            }
+        }
+
+        self.incoming.poll()
+    }
+}
+
+/// Completes when every connection tracked by `Connections` has been closed.
+pub(crate) struct WaitUntilDrained {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Future for WaitUntilDrained {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut inner = self.inner.borrow_mut();
+
+        if inner.active == 0 {
+            Ok(Async::Ready(()))
+        } else {
+            inner.blocker = Some(task::current());
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use futures::{future, stream, Async};
+    use futures::sync::oneshot;
+    use tokio_core::reactor::{Core, Timeout};
+
+    /// Serves a single request, and then holds the connection open until it is drained.
+    struct FakeConnection {
+        request: oneshot::Receiver<()>,
+        keep_alive: bool,
+    }
+
+    impl FakeConnection {
+        fn new(request: oneshot::Receiver<()>) -> FakeConnection {
+            FakeConnection {
+                request,
+                keep_alive: true,
+            }
+        }
+    }
+
+    impl Future for FakeConnection {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<(), ()> {
+            if let Async::NotReady = self.request.poll().map_err(|_| ())? {
+                return Ok(Async::NotReady);
+            }
+
+            if self.keep_alive {
+                Ok(Async::NotReady)
+            } else {
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+
+    impl Drain for FakeConnection {
+        fn drain(&mut self) {
+            self.keep_alive = false;
+        }
+    }
+
+    #[test]
+    fn drain_waits_for_in_flight_connections() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        let connections = Connections::new();
+
+        let (tx, rx) = oneshot::channel();
+        handle.spawn(connections.track(FakeConnection::new(rx)));
+        core.turn(Some(Duration::from_millis(1)));
+        assert_eq!(connections.active(), 1);
+
+        let complete = Timeout::new(Duration::from_millis(10), &handle)
+            .unwrap()
+            .map(move |_| tx.send(()).unwrap())
+            .map_err(|_| ());
+        handle.spawn(complete);
+
+        core.run(connections.drain()).unwrap();
+        assert_eq!(connections.active(), 0);
+    }
+
+    #[test]
+    fn limit_pauses_incoming_connections() {
+        let mut core = Core::new().unwrap();
+        let connections = Connections::new();
+        let mut incoming = connections.limit(stream::repeat::<(), ()>(()), Some(1));
+
+        let (_tx, rx) = oneshot::channel();
+        let conn = connections.track(FakeConnection::new(rx));
+
+        let mut poll = || core.run(future::lazy(|| Ok::<_, ()>(incoming.poll()))).unwrap();
+
+        assert!(poll().unwrap().is_not_ready());
+        drop(conn);
+        assert_eq!(poll().unwrap(), Async::Ready(Some(())));
+    }
+
+    #[test]
+    fn drain_closes_idle_connections() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        let connections = Connections::new();
+
+        let (tx, rx) = oneshot::channel();
+        tx.send(()).unwrap();
+
+        // The request has completed, but the connection is held open by keep-alive until it is
+        // drained.
+        handle.spawn(connections.track(FakeConnection::new(rx)));
+        core.turn(Some(Duration::from_millis(1)));
+        assert_eq!(connections.active(), 1);
+
+        core.run(connections.drain()).unwrap();
+        assert_eq!(connections.active(), 0);
+    }
+}
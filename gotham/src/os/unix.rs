@@ -1,74 +1,520 @@
+use std::env;
+use std::fs;
 use std::io;
-use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::mem;
+use std::net::TcpListener;
+use std::ops::Range;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net;
+use std::path::Path;
+use std::process;
 use std::thread;
 use std::sync::Arc;
+use std::sync::mpsc;
 
 use hyper::server::Http;
 use tokio_core;
 use tokio_core::reactor::{Core, Handle};
-use futures::{Future, Stream};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_uds;
+use libc;
+use futures::{stream, Future, Stream};
+use futures::sync::oneshot;
 
 use handler::NewHandler;
+use server::ServerBuilder;
 use service::GothamService;
+use error::StartError;
+use os::shutdown::{self, Connections};
+use state::{PeerAddr, Scheme};
 
-/// Starts a Gotham application, with the given number of threads.
-pub fn start_with_num_threads<NH, A>(addr: A, threads: usize, new_handler: NH)
+/// Starts a Gotham application with the settings from `server`, which accepts connections on each
+/// of `listeners` and runs until `shutdown_signal` resolves.
+pub(crate) fn start<NH, F>(
+    server: ServerBuilder,
+    listeners: Vec<TcpListener>,
+    new_handler: NH,
+    shutdown_signal: F,
+) -> Result<(), StartError>
 where
     NH: NewHandler + 'static,
-    A: ToSocketAddrs,
+    F: Future<Item = (), Error = ()>,
 {
-    let (listener, addr) = ::tcp_listener(addr);
+    run(server, listeners, new_handler, shutdown_signal)
+}
+
+/// Starts a Gotham application which accepts connections on a Unix domain socket at `path`, and
+/// runs until `shutdown_signal` resolves. The socket is removed when the application shuts down.
+pub(crate) fn start_unix<NH, P, F>(
+    server: ServerBuilder,
+    path: P,
+    new_handler: NH,
+    shutdown_signal: F,
+) -> Result<(), StartError>
+where
+    NH: NewHandler + 'static,
+    P: AsRef<Path>,
+    F: Future<Item = (), Error = ()>,
+{
+    let path = path.as_ref().to_owned();
+    let listener =
+        net::UnixListener::bind(&path).map_err(|e| StartError::BindUnix(path.clone(), e))?;
+
+    let result = run(server, vec![listener], new_handler, shutdown_signal);
+
+    // The socket file outlives the listener, and would otherwise cause the next start to fail.
+    if let Err(e) = fs::remove_file(&path) {
+        warn!(
+            target: "gotham::start",
+            " Gotham unable to remove socket {}: {}",
+            path.display(),
+            e
+        );
+    }
+
+    result
+}
+
+/// Takes the listening sockets passed to the application by systemd socket activation (the
+/// `LISTEN_FDS` protocol), in the order they were configured. An empty list is returned when the
+/// application wasn't socket activated.
+///
+/// The sockets can only be taken once, as the environment variables describing them are removed
+/// to prevent them being inherited by child processes.
+///
+/// ```rust,no_run
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::State;
+/// # use gotham::ServerBuilder;
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Accepted))
+/// # }
+/// #
+/// # fn main() {
+/// let listeners = gotham::listen_fds().expect("invalid LISTEN_FDS");
+///
+/// ServerBuilder::new()
+///     .run_listeners(listeners, || Ok(my_handler))
+///     .expect("unable to start server");
+/// # }
+/// ```
+pub fn listen_fds() -> io::Result<Vec<TcpListener>> {
+    let pid = env::var("LISTEN_PID");
+    let fds = env::var("LISTEN_FDS");
+
+    let (pid, fds) = match (pid, fds) {
+        (Ok(pid), Ok(fds)) => (pid, fds),
+        _ => return Ok(Vec::new()),
+    };
+
+    // The sockets were intended for a different process, which has since executed this one, and
+    // the variables are left for it to describe them.
+    if pid.parse::<u32>().ok() != Some(process::id()) {
+        return Ok(Vec::new());
+    }
+
+    let fds = listen_fd_range(&fds)?;
+
+    // Every descriptor is checked before any is wrapped, so that a failure part way through
+    // doesn't close the descriptors which were already wrapped, and the variables are only
+    // removed once the descriptors are known to be usable.
+    for fd in fds.clone() {
+        check_tcp_listener(fd)?;
+
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    Ok(fds.map(|fd| unsafe { TcpListener::from_raw_fd(fd) })
+        .collect())
+}
+
+/// Ensures `fd` is a TCP socket rather than, for example, a Unix domain socket or a UDP socket,
+/// without taking ownership of it.
+fn check_tcp_listener(fd: RawFd) -> io::Result<()> {
+    let mut sock_type: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            &mut sock_type as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockname(
+            fd,
+            &mut addr as *mut libc::sockaddr_storage as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let family = libc::c_int::from(addr.ss_family);
+    if sock_type != libc::SOCK_STREAM || (family != libc::AF_INET && family != libc::AF_INET6) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("file descriptor {} is not a TCP socket", fd),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses the value of `LISTEN_FDS` into the range of file descriptors passed by systemd.
+fn listen_fd_range(fds: &str) -> io::Result<Range<RawFd>> {
+    // The first file descriptor passed by systemd, following stdin, stdout and stderr.
+    const LISTEN_FDS_START: RawFd = 3;
 
-    let protocol = Arc::new(Http::new());
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let fds = fds.parse::<RawFd>()
+        .map_err(|_| invalid("LISTEN_FDS is not a number"))?;
+
+    if fds < 0 {
+        return Err(invalid("LISTEN_FDS is negative"));
+    }
+
+    let end = LISTEN_FDS_START
+        .checked_add(fds)
+        .ok_or_else(|| invalid("LISTEN_FDS is too large"))?;
+
+    Ok(LISTEN_FDS_START..end)
+}
+
+/// A listening socket which can be shared between reactor threads.
+trait Listener: Sized + Send + 'static {
+    type Io: AsyncRead + AsyncWrite + 'static;
+
+    fn try_clone(&self) -> io::Result<Self>;
+
+    /// Registers the listener with a reactor, returning the stream of accepted connections.
+    fn incoming(self, handle: &Handle) -> io::Result<Incoming<Self::Io>>;
+
+    /// Describes where the listener accepts connections, for logging.
+    fn location(&self, scheme: Scheme) -> String;
+
+    /// Converts an error from preparing the listener into the error reported from startup.
+    fn start_error(&self, e: io::Error) -> StartError;
+}
+
+type Incoming<I> = Box<Stream<Item = (I, PeerAddr), Error = io::Error>>;
+
+impl Listener for TcpListener {
+    type Io = tokio_core::net::TcpStream;
+
+    fn try_clone(&self) -> io::Result<TcpListener> {
+        TcpListener::try_clone(self)
+    }
+
+    fn incoming(self, handle: &Handle) -> io::Result<Incoming<Self::Io>> {
+        let addr = self.local_addr()?;
+        let listener = tokio_core::net::TcpListener::from_listener(self, &addr, handle)?;
+
+        Ok(Box::new(listener
+            .incoming()
+            .map(|(socket, addr)| (socket, PeerAddr::Tcp(addr)))))
+    }
+
+    fn location(&self, scheme: Scheme) -> String {
+        match self.local_addr() {
+            Ok(addr) => format!("{}://{}", scheme, addr),
+            Err(_) => format!("{}://(unknown)", scheme),
+        }
+    }
+
+    fn start_error(&self, e: io::Error) -> StartError {
+        match self.local_addr() {
+            Ok(addr) => StartError::Bind(addr, e),
+            Err(_) => StartError::Reactor(e),
+        }
+    }
+}
+
+impl Listener for net::UnixListener {
+    type Io = tokio_uds::UnixStream;
+
+    fn try_clone(&self) -> io::Result<net::UnixListener> {
+        net::UnixListener::try_clone(self)
+    }
+
+    fn incoming(self, handle: &Handle) -> io::Result<Incoming<Self::Io>> {
+        let listener = tokio_uds::UnixListener::from_listener(self, handle)?;
+
+        Ok(Box::new(listener.incoming().map(|(socket, addr)| {
+            let path = addr.as_pathname().map(Path::to_owned);
+            (socket, PeerAddr::Unix(path))
+        })))
+    }
+
+    fn location(&self, _scheme: Scheme) -> String {
+        let path = self.local_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(Path::to_owned));
+
+        match path {
+            Some(path) => format!("unix:{}", path.display()),
+            None => "unix:(unnamed)".to_owned(),
+        }
+    }
+
+    fn start_error(&self, e: io::Error) -> StartError {
+        let path = self.local_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(Path::to_owned));
+
+        match path {
+            Some(path) => StartError::BindUnix(path, e),
+            None => StartError::Reactor(e),
+        }
+    }
+}
+
+/// Runs the application on `listeners`, with a reactor for each thread.
+fn run<L, NH, F>(
+    server: ServerBuilder,
+    listeners: Vec<L>,
+    new_handler: NH,
+    shutdown_signal: F,
+) -> Result<(), StartError>
+where
+    L: Listener,
+    NH: NewHandler + 'static,
+    F: Future<Item = (), Error = ()>,
+{
+    if listeners.is_empty() {
+        return Err(StartError::NoListeners);
+    }
+
+    if server.threads == 0 {
+        return Err(StartError::InvalidThreadCount);
+    }
+
+    ::check_new_handler(&new_handler)?;
+
+    let threads = server.threads;
+    let locations = listeners
+        .iter()
+        .map(|listener| listener.location(server.scheme()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let server = Arc::new(server);
     let new_handler = Arc::new(new_handler);
 
+    // Each thread accepts connections from its own copy of every listener.
+    let copies = (0..threads - 1)
+        .map(|_| {
+            listeners
+                .iter()
+                .map(|listener| listener.try_clone().map_err(|e| listener.start_error(e)))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut signals = Vec::with_capacity(threads);
+    let mut workers = Vec::with_capacity(threads);
+    let (ready, started) = mpsc::channel();
+
+    for listeners in copies {
+        let server = server.clone();
+        let new_handler = new_handler.clone();
+        let ready = ready.clone();
+
+        let (signal, shutdown) = oneshot::channel();
+        signals.push(signal);
+
+        workers.push(thread::spawn(move || {
+            let (core, incoming) = match bind_core(listeners) {
+                Ok(bound) => bound,
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+
+            let _ = ready.send(Ok(()));
+
+            run_core(
+                core,
+                incoming,
+                &server,
+                new_handler,
+                shutdown.then(|_| Ok(())),
+            )
+        }));
+    }
+
+    drop(ready);
+
+    let (core, incoming) =
+        match super::await_started(started, threads - 1).and_then(|()| bind_core(listeners)) {
+            Ok(bound) => bound,
+            Err(e) => {
+                // Dropping the signals causes the reactors which did start to shut down.
+                drop(signals);
+                super::join_workers(workers);
+                return Err(e);
+            }
+        };
+
     info!(
         target: "gotham::start",
-        " Gotham listening on http://{} with {} threads",
-        addr,
+        " Gotham listening on {} with {} threads",
+        locations,
         threads,
     );
 
-    for _ in 0..threads - 1 {
-        let listener = listener.try_clone().expect("unable to clone TCP listener");
-        let protocol = protocol.clone();
-        let new_handler = new_handler.clone();
-        thread::spawn(move || start_core(listener, &addr, &protocol, new_handler));
-    }
+    // The shutdown signal is observed on this thread, and then forwarded to every other reactor.
+    let shutdown_signal = shutdown_signal.then(move |_| {
+        info!(target: "gotham::start", " Gotham shutting down");
 
-    start_core(listener, &addr, &protocol, new_handler);
+        for signal in signals {
+            // An error means the reactor has already stopped, so there is nothing to signal.
+            let _ = signal.send(());
+        }
+
+        Ok(())
+    });
+
+    run_core(core, incoming, &server, new_handler, shutdown_signal);
+
+    super::join_workers(workers);
+    Ok(())
 }
 
-fn start_core<NH>(listener: TcpListener, addr: &SocketAddr, protocol: &Http, new_handler: Arc<NH>)
+/// Creates a reactor for a single thread, and registers the listeners with it.
+fn bind_core<L>(listeners: Vec<L>) -> Result<(Core, Incoming<L::Io>), StartError>
 where
+    L: Listener,
+{
+    let core = Core::new().map_err(StartError::Reactor)?;
+    let mut incoming: Incoming<L::Io> = Box::new(stream::empty());
+
+    for listener in listeners {
+        let accepted = listener
+            .incoming(&core.handle())
+            .map_err(StartError::Reactor)?;
+
+        incoming = Box::new(incoming.select(accepted));
+    }
+
+    Ok((core, incoming))
+}
+
+fn run_core<I, NH, F>(
+    mut core: Core,
+    incoming: Incoming<I>,
+    server: &ServerBuilder,
+    new_handler: Arc<NH>,
+    shutdown_signal: F,
+) where
+    I: AsyncRead + AsyncWrite + 'static,
     NH: NewHandler + 'static,
+    F: Future<Item = (), Error = ()>,
 {
-    let mut core = Core::new().expect("unable to spawn tokio reactor");
     let handle = core.handle();
-    core.run(serve(listener, addr, protocol, new_handler, &handle))
-        .expect("unable to run reactor over listener");
+    let protocol = server.protocol();
+    let connections = Connections::new();
+
+    {
+        // When the shutdown signal resolves, the `serve` future (and the listener it owns) is
+        // dropped, which stops any further connections from being accepted.
+        let serve = serve(
+            incoming,
+            server,
+            &protocol,
+            new_handler,
+            &handle,
+            &connections,
+        );
+        let shutdown_signal = shutdown_signal.then(|_| Ok(()));
+
+        core.run(shutdown_signal.select(serve).map(|_| ()).map_err(|(e, _)| e))
+            .expect("unable to run reactor over listener");
+    }
+
+    shutdown::drain(&mut core, &connections, server.drain_timeout);
 }
 
-fn serve<'a, NH>(
-    listener: TcpListener,
-    addr: &SocketAddr,
+fn serve<'a, I, NH>(
+    incoming: Incoming<I>,
+    server: &'a ServerBuilder,
     protocol: &'a Http,
     new_handler: Arc<NH>,
     handle: &'a Handle,
+    connections: &Connections,
 ) -> Box<Future<Item = (), Error = io::Error> + 'a>
 where
+    I: AsyncRead + AsyncWrite + 'static,
     NH: NewHandler + 'static,
 {
-    let gotham_service = GothamService::new(new_handler, handle.clone());
-
-    let listener = tokio_core::net::TcpListener::from_listener(listener, addr, handle)
-        .expect("unable to convert TCP listener to tokio listener");
+    let gotham_service =
+        GothamService::new(new_handler, handle.clone()).with_max_body_size(server.max_body_size);
+    let connections = connections.clone();
+    let incoming = connections.limit(incoming, server.max_connections);
 
-    Box::new(listener.incoming().for_each(move |(socket, addr)| {
+    Box::new(incoming.for_each(move |(socket, addr)| {
         let service = gotham_service.connect(addr);
-        let f = protocol.serve_connection(socket, service).then(|_| Ok(()));
-
-        handle.spawn(f);
+        super::serve_connection(server, protocol, socket, service, handle, &connections);
         Ok(())
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_fd_range_starts_after_stdio() {
+        assert_eq!(listen_fd_range("0").unwrap(), 3..3);
+        assert_eq!(listen_fd_range("2").unwrap(), 3..5);
+    }
+
+    #[test]
+    fn listen_fd_range_rejects_invalid_counts() {
+        for fds in &["two", "-1", &RawFd::max_value().to_string()] {
+            let err = listen_fd_range(fds).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn check_tcp_listener_rejects_other_sockets() {
+        use std::net::UdpSocket;
+        use std::os::unix::io::AsRawFd;
+
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(check_tcp_listener(tcp.as_raw_fd()).is_ok());
+
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (unix, _) = net::UnixStream::pair().unwrap();
+        for fd in &[udp.as_raw_fd(), unix.as_raw_fd()] {
+            let err = check_tcp_listener(*fd).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        // The descriptors are still open, so they were not taken by the check.
+        assert!(tcp.local_addr().is_ok());
+        assert!(udp.local_addr().is_ok());
+    }
+}
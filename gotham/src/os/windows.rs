@@ -1,16 +1,21 @@
 use std::io;
-use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::net::{SocketAddr, TcpListener};
 use std::thread;
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender};
 
 use hyper::server::Http;
 use tokio_core;
 use tokio_core::net::TcpStream;
 use tokio_core::reactor::{Core, Handle};
-use futures::{future, task, Async, Future, Poll, Stream};
+use futures::{future, stream, task, Async, Future, Poll, Stream};
+use futures::sync::oneshot;
 
 use handler::NewHandler;
+use server::ServerBuilder;
 use service::GothamService;
+use error::StartError;
+use os::shutdown::{self, Connections};
 
 use crossbeam::sync::SegQueue;
 
@@ -40,64 +45,184 @@ impl Stream for SocketQueue {
     }
 }
 
-/// Starts a Gotham application, with the given number of threads.
+/// Starts a Gotham application with the settings from `server`, which accepts connections on each
+/// of `listeners` and runs until `shutdown_signal` resolves.
 ///
 /// ## Windows
 ///
 /// An additional thread is used on Windows to accept connections.
-pub fn start_with_num_threads<NH, A>(addr: A, threads: usize, new_handler: NH)
+pub(crate) fn start<NH, F>(
+    server: ServerBuilder,
+    listeners: Vec<TcpListener>,
+    new_handler: NH,
+    shutdown_signal: F,
+) -> Result<(), StartError>
 where
     NH: NewHandler + 'static,
-    A: ToSocketAddrs,
+    F: Future<Item = (), Error = ()>,
 {
-    let (listener, addr) = ::tcp_listener(addr);
+    if listeners.is_empty() {
+        return Err(StartError::NoListeners);
+    }
+
+    if server.threads == 0 {
+        return Err(StartError::InvalidThreadCount);
+    }
+
+    ::check_new_handler(&new_handler)?;
 
-    let protocol = Arc::new(Http::new());
+    let threads = server.threads;
+    let locations = listeners
+        .iter()
+        .map(|listener| match listener.local_addr() {
+            Ok(addr) => format!("{}://{}", server.scheme(), addr),
+            Err(_) => format!("{}://(unknown)", server.scheme()),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let server = Arc::new(server);
     let new_handler = Arc::new(new_handler);
 
     let queue = SocketQueue::new();
 
+    let mut signals = Vec::with_capacity(threads + 1);
+    let mut workers = Vec::with_capacity(threads);
+    let (ready, started) = mpsc::channel();
+
     {
         let queue = queue.clone();
-        thread::spawn(move || start_listen_core(listener, addr, queue));
+        let ready = ready.clone();
+        let (signal, shutdown) = oneshot::channel();
+        signals.push(signal);
+
+        workers.push(thread::spawn(move || {
+            start_listen_core(listeners, queue, shutdown.then(|_| Ok(())), ready)
+        }));
     }
 
+    for _ in 0..threads - 1 {
+        let server = server.clone();
+        let queue = queue.clone();
+        let new_handler = new_handler.clone();
+        let ready = ready.clone();
+
+        let (signal, shutdown) = oneshot::channel();
+        signals.push(signal);
+
+        workers.push(thread::spawn(move || {
+            let core = match Core::new() {
+                Ok(core) => core,
+                Err(e) => {
+                    let _ = ready.send(Err(StartError::Reactor(e)));
+                    return;
+                }
+            };
+
+            let _ = ready.send(Ok(()));
+
+            run_serve_core(
+                core,
+                queue,
+                &server,
+                new_handler,
+                shutdown.then(|_| Ok(())),
+            )
+        }));
+    }
+
+    drop(ready);
+
+    let core = match super::await_started(started, threads)
+        .and_then(|()| Core::new().map_err(StartError::Reactor))
+    {
+        Ok(core) => core,
+        Err(e) => {
+            // Dropping the signals causes the reactors which did start to shut down.
+            drop(signals);
+            super::join_workers(workers);
+            return Err(e);
+        }
+    };
+
     info!(
         target: "gotham::start",
-        " Gotham listening on http://{} with {} threads",
-        addr,
+        " Gotham listening on {} with {} threads",
+        locations,
         threads,
     );
 
-    for _ in 0..threads - 1 {
-        let protocol = protocol.clone();
-        let queue = queue.clone();
-        let new_handler = new_handler.clone();
-        thread::spawn(move || start_serve_core(queue, &protocol, new_handler));
-    }
+    // The shutdown signal is observed on this thread, and then forwarded to every other reactor.
+    let shutdown_signal = shutdown_signal.then(move |_| {
+        info!(target: "gotham::start", " Gotham shutting down");
+
+        for signal in signals {
+            // An error means the reactor has already stopped, so there is nothing to signal.
+            let _ = signal.send(());
+        }
+
+        Ok(())
+    });
+
+    run_serve_core(core, queue, &server, new_handler, shutdown_signal);
 
-    start_serve_core(queue, &protocol, new_handler);
+    super::join_workers(workers);
+    Ok(())
 }
 
-fn start_listen_core(listener: TcpListener, addr: SocketAddr, queue: SocketQueue) {
-    let mut core = Core::new().expect("unable to spawn tokio reactor");
-    let handle = core.handle();
-    core.run(listen(listener, addr, queue, &handle))
-        .expect("unable to run reactor over listener");
+fn start_listen_core<F>(
+    listeners: Vec<TcpListener>,
+    queue: SocketQueue,
+    shutdown_signal: F,
+    ready: Sender<Result<(), StartError>>,
+) where
+    F: Future<Item = (), Error = ()>,
+{
+    let bound = Core::new().map_err(StartError::Reactor).and_then(|core| {
+        listeners
+            .into_iter()
+            .map(|listener| {
+                let addr = listener.local_addr()?;
+                tokio_core::net::TcpListener::from_listener(listener, &addr, &core.handle())
+            })
+            .collect::<io::Result<Vec<_>>>()
+            .map(|listeners| (core, listeners))
+            .map_err(StartError::Reactor)
+    });
+
+    let (mut core, listeners) = match bound {
+        Ok(bound) => bound,
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+
+    let _ = ready.send(Ok(()));
+
+    let shutdown_signal = shutdown_signal.then(|_| Ok(()));
+    core.run(
+        shutdown_signal
+            .select(listen(listeners, queue))
+            .map(|_| ())
+            .map_err(|(e, _)| e),
+    ).expect("unable to run reactor over listener");
 }
 
 fn listen(
-    listener: TcpListener,
-    addr: SocketAddr,
+    listeners: Vec<tokio_core::net::TcpListener>,
     queue: SocketQueue,
-    handle: &Handle,
 ) -> Box<Future<Item = (), Error = io::Error>> {
-    let listener = tokio_core::net::TcpListener::from_listener(listener, &addr, handle)
-        .expect("unable to convert TCP listener to tokio listener");
-
     let mut n: usize = 0;
 
-    Box::new(listener.incoming().for_each(move |conn| {
+    let mut incoming: Box<Stream<Item = (TcpStream, SocketAddr), Error = io::Error>> =
+        Box::new(stream::empty());
+
+    for listener in listeners {
+        incoming = Box::new(incoming.select(listener.incoming()));
+    }
+
+    Box::new(incoming.for_each(move |conn| {
         queue.queue.push(conn);
         let tasks = queue
             .notify
@@ -110,27 +235,44 @@ fn listen(
     }))
 }
 
-fn start_serve_core<NH>(queue: SocketQueue, protocol: &Http, new_handler: Arc<NH>)
-where
+fn run_serve_core<NH, F>(
+    mut core: Core,
+    queue: SocketQueue,
+    server: &ServerBuilder,
+    new_handler: Arc<NH>,
+    shutdown_signal: F,
+) where
     NH: NewHandler + 'static,
+    F: Future<Item = (), Error = ()>,
 {
-    let mut core = Core::new().expect("unable to spawn tokio reactor");
     let handle = core.handle();
-    core.run(serve(queue, protocol, new_handler, &handle))
-        .expect("unable to run reactor for work stealing");
+    let protocol = server.protocol();
+    let connections = Connections::new();
+
+    {
+        let serve = serve(queue, server, &protocol, new_handler, &handle, &connections);
+        core.run(shutdown_signal.select(serve).map(|_| ()).map_err(|_| ()))
+            .expect("unable to run reactor for work stealing");
+    }
+
+    shutdown::drain(&mut core, &connections, server.drain_timeout);
 }
 
 fn serve<'a, NH>(
     queue: SocketQueue,
+    server: &'a ServerBuilder,
     protocol: &'a Http,
     new_handler: Arc<NH>,
     handle: &'a Handle,
+    connections: &Connections,
 ) -> Box<Future<Item = (), Error = ()> + 'a>
 where
     NH: NewHandler + 'static,
 {
-    let gotham_service = GothamService::new(new_handler, handle.clone());
+    let gotham_service =
+        GothamService::new(new_handler, handle.clone()).with_max_body_size(server.max_body_size);
     let tasks_m = queue.notify.clone();
+    let connections = connections.clone();
 
     Box::new(
         future::lazy(move || {
@@ -140,13 +282,13 @@ where
             tasks.push(task::current());
             future::ok(())
         }).and_then(move |_| {
-            queue.for_each(move |(socket, addr)| {
-                let service = gotham_service.connect(addr);
-                let f = protocol.serve_connection(socket, service).then(|_| Ok(()));
-
-                handle.spawn(f);
-                Ok(())
-            })
+            connections
+                .limit(queue, server.max_connections)
+                .for_each(move |(socket, addr)| {
+                    let service = gotham_service.connect(addr.into());
+                    super::serve_connection(server, protocol, socket, service, handle, &connections);
+                    Ok(())
+                })
         }),
     )
 }
@@ -5,10 +5,12 @@ use hyper::Method;
 
 use pipeline::chain::PipelineHandleChain;
 use pipeline::set::PipelineSet;
-use router::route::matcher::{IntoRouteMatcher, MethodOnlyRouteMatcher, RouteMatcher};
+use router::route::matcher::{AnyRouteMatcher, HostRouteMatcher, IntoRouteMatcher,
+                             MethodOnlyRouteMatcher, RouteMatcher};
 use extractor::{NoopPathExtractor, NoopQueryStringExtractor};
 use router::builder::{AssociatedRouteBuilder, DelegateRouteBuilder, RouterBuilder, ScopeBuilder,
                       SingleRouteBuilder};
+use router::error::RouteErrorReason;
 use router::tree::node::{NodeBuilder, SegmentType};
 use router::tree::regex::ConstrainedSegmentRegex;
 
@@ -465,6 +467,7 @@ where
         IRM: IntoRouteMatcher<Output = M>,
         M: RouteMatcher + Send + Sync + 'static,
     {
+        let host = self.host_matcher();
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
         let matcher = matcher.into_route_matcher();
@@ -474,6 +477,87 @@ where
             node_builder,
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
+            host,
+            fallback: false,
+            header_extraction: None,
+            body_extraction: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Begins defining the fallback route for the current scope, which the `Router` dispatches to
+    /// when a request for a path beneath the scope matches no route, instead of responding with
+    /// `404 Not Found`. The fallback accepts every request method, and is dispatched through the
+    /// pipelines and extractors of the route, in the same way as any other route.
+    ///
+    /// When scopes which are nested both have a fallback, the fallback of the innermost scope
+    /// containing the request path is used. A `Router` which is delegated to uses its own
+    /// fallback, if it has one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn index_html(state: State) -> (State, Response) {
+    ///     // Serve the single page application, which does its own routing.
+    /// #   (state, Response::new().with_status(StatusCode::Ok))
+    /// }
+    ///
+    /// fn api_not_found(state: State) -> (State, Response) {
+    ///     // Respond with a JSON error.
+    /// #   (state, Response::new().with_status(StatusCode::NotFound).with_body("{}"))
+    /// }
+    /// #
+    /// # fn list_users(state: State) -> (State, Response) {
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.scope("/api", |route| {
+    ///         route.get("/users").to(list_users);
+    ///         route.fallback().to(api_not_found);
+    ///     });
+    ///
+    ///     route.fallback().to(index_html);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let get = |uri| test_server.client().get(uri).perform().unwrap();
+    /// #
+    /// #   assert_eq!(get("https://example.com/api/users").status(), StatusCode::Accepted);
+    /// #
+    /// #   let response = get("https://example.com/api/posts/1");
+    /// #   assert_eq!(response.status(), StatusCode::NotFound);
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "{}");
+    /// #
+    /// #   assert_eq!(get("https://example.com/users/1").status(), StatusCode::Ok);
+    /// #   assert_eq!(get("https://example.com/").status(), StatusCode::Ok);
+    /// # }
+    /// ```
+    fn fallback<'b>(&'b mut self) -> ExplicitSingleRouteBuilder<'b, AnyRouteMatcher, C, P> {
+        let host = self.host_matcher();
+        let (node_builder, pipeline_chain, pipelines) = self.component_refs();
+
+        SingleRouteBuilder {
+            matcher: AnyRouteMatcher::new(),
+            node_builder,
+            pipeline_chain: *pipeline_chain,
+            pipelines: pipelines.clone(),
+            host,
+            fallback: true,
+            header_extraction: None,
+            body_extraction: None,
             phantom: PhantomData,
         }
     }
@@ -521,6 +605,7 @@ where
     where
         F: FnOnce(&mut ScopeBuilder<C, P>),
     {
+        let host = self.host_matcher();
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
 
@@ -528,6 +613,84 @@ where
             node_builder,
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
+            host,
+        };
+
+        f(&mut scope_builder)
+    }
+
+    /// Begins a new scope at the current location, whose routes only match requests for hosts
+    /// which match `pattern`. See `HostRouteMatcher` for the patterns which are supported. The
+    /// labels captured from the host are given to the `PathExtractor` of each route in the scope.
+    ///
+    /// Hosts can be combined with scopes and delegation in either order. When host scopes are
+    /// nested, only the innermost pattern applies.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn api_handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// # }
+    /// #
+    /// # fn site_handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new().with_status(StatusCode::Ok))
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.host("api.example.com", |route| {
+    ///         route.get("/status").to(api_handler);
+    ///     });
+    ///
+    ///     route.host("*.example.com", |route| {
+    ///         route.get("/status").to(site_handler);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://api.example.com/status")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://www.example.com/status")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Ok);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.org/status")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::NotFound);
+    /// # }
+    /// ```
+    fn host<F>(&mut self, pattern: &str, f: F)
+    where
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+    {
+        let host = Some(HostRouteMatcher::new(pattern));
+        let (node_builder, pipeline_chain, pipelines) = self.component_refs();
+
+        let mut scope_builder = ScopeBuilder {
+            node_builder,
+            pipeline_chain: *pipeline_chain,
+            pipelines: pipelines.clone(),
+            host,
         };
 
         f(&mut scope_builder)
@@ -639,12 +802,14 @@ where
         F: FnOnce(&mut ScopeBuilder<NC, P>),
         NC: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
     {
+        let host = self.host_matcher();
         let (node_builder, _pipeline_chain, pipelines) = self.component_refs();
 
         let mut scope_builder = ScopeBuilder {
             node_builder,
             pipeline_chain,
             pipelines: pipelines.clone(),
+            host,
         };
 
         f(&mut scope_builder)
@@ -691,6 +856,7 @@ where
     /// # }
     /// ```
     fn delegate<'b>(&'b mut self, path: &str) -> DelegateRouteBuilder<'b, C, P> {
+        let host = self.host_matcher();
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
 
@@ -698,6 +864,7 @@ where
             node_builder,
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
+            host,
         }
     }
 
@@ -773,6 +940,7 @@ where
     /// # }
     /// ```
     fn delegate_without_pipelines<'b>(&'b mut self, path: &str) -> DelegateRouteBuilder<'b, (), P> {
+        let host = self.host_matcher();
         let (node_builder, _pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
 
@@ -780,6 +948,7 @@ where
             node_builder,
             pipeline_chain: (),
             pipelines: pipelines.clone(),
+            host,
         }
     }
 
@@ -853,6 +1022,7 @@ where
     where
         F: FnOnce(&mut DefaultAssociatedRouteBuilder<'b, C, P>),
     {
+        let host = self.host_matcher();
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
 
@@ -860,6 +1030,9 @@ where
             node_builder,
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
+            host,
+            header_extraction: None,
+            body_extraction: None,
             phantom: PhantomData,
         };
 
@@ -869,6 +1042,11 @@ where
     /// Return the components that comprise this builder. For internal use only.
     #[doc(hidden)]
     fn component_refs(&mut self) -> (&mut NodeBuilder, &mut C, &PipelineSet<P>);
+
+    /// Return the host pattern which routes defined by this builder are restricted to. For
+    /// internal use only.
+    #[doc(hidden)]
+    fn host_matcher(&self) -> Option<HostRouteMatcher>;
 }
 
 fn descend<'n>(node_builder: &'n mut NodeBuilder, path: &str) -> &'n mut NodeBuilder {
@@ -895,14 +1073,27 @@ where
         Some(segment) => {
             trace!("[descending into {}]", segment);
 
+            let raw = segment;
             let (segment, segment_type) = match segment.chars().next() {
                 Some(':') => {
                     let segment = &segment[1..];
                     match segment.find(":") {
                         Some(n) => {
                             let (segment, pattern) = segment.split_at(n);
-                            let regex = ConstrainedSegmentRegex::new(&pattern[1..]);
-                            (segment, SegmentType::Constrained { regex })
+                            match ConstrainedSegmentRegex::try_new(&pattern[1..]) {
+                                Ok(regex) => (segment, SegmentType::Constrained { regex }),
+                                Err(e) => {
+                                    let reason = RouteErrorReason::InvalidRegex {
+                                        regex: pattern[1..].to_owned(),
+                                        message: e.to_string(),
+                                    };
+                                    node.add_error(Some(raw), reason);
+
+                                    // Building fails, so the rest of the path is only descended
+                                    // into to find any other errors.
+                                    (raw, SegmentType::Static)
+                                }
+                            }
                         }
                         None => (segment, SegmentType::Dynamic),
                     }
@@ -914,7 +1105,7 @@ where
 
             if !node.has_child(segment, segment_type.clone()) {
                 let node_builder = NodeBuilder::new(segment, segment_type.clone());
-                node.add_child(node_builder);
+                node.add_child_recording_errors(node_builder);
             }
 
             let child = node.borrow_mut_child(segment, segment_type).unwrap();
@@ -939,6 +1130,10 @@ where
             &self.pipelines,
         )
     }
+
+    fn host_matcher(&self) -> Option<HostRouteMatcher> {
+        None
+    }
 }
 
 impl<'a, C, P> DrawRoutes<C, P> for ScopeBuilder<'a, C, P>
@@ -953,6 +1148,10 @@ where
             &self.pipelines,
         )
     }
+
+    fn host_matcher(&self) -> Option<HostRouteMatcher> {
+        self.host.clone()
+    }
 }
 
 #[cfg(test)]
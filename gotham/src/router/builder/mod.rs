@@ -9,21 +9,28 @@ use std::panic::RefUnwindSafe;
 
 use hyper::{Method, StatusCode};
 
+use http::cookie::Key;
 use pipeline::chain::PipelineHandleChain;
 use pipeline::set::{finalize_pipeline_set, new_pipeline_set, PipelineSet};
-use router::Router;
-use router::tree::TreeBuilder;
+use router::{CorsPolicy, PathPolicy, Router, RouterSettings};
+use router::error::{RouteError, RouteErrorReason};
+use router::url_for::NamedRoutes;
+use router::tree::{Tree, TreeBuilder};
 use router::response::extender::ResponseExtender;
 use router::response::finalizer::ResponseFinalizerBuilder;
-use router::route::{Delegation, Extractors, RouteImpl};
-use router::route::matcher::{AnyRouteMatcher, MethodOnlyRouteMatcher, RouteMatcher};
+use router::route::{Delegation, Extractors, Route, RouteImpl};
+use router::route::matcher::{AnyRouteMatcher, HostRouteMatcher, MethodOnlyRouteMatcher,
+                             RouteMatcher};
 use router::route::dispatch::DispatcherImpl;
-use extractor::{NoopPathExtractor, NoopQueryStringExtractor, PathExtractor, QueryStringExtractor};
+use extractor::{BodyExtraction, HeaderExtraction, HeaderExtractor, NoopPathExtractor,
+                NoopQueryStringExtractor, PathExtractor, QueryStringExtractor, RequestBodyExtractor,
+                DEFAULT_BODY_LIMIT};
 use router::tree::node::NodeBuilder;
 
 pub use self::single::DefineSingleRoute;
 pub use self::draw::DrawRoutes;
 pub use self::modify::{ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor};
+pub use router::error::RouterBuildError;
 
 /// The default type returned when building a single associated route. See
 /// `router::builder::DefineSingleRoute` for an overview of the ways that a route can be specified.
@@ -77,7 +84,60 @@ pub type AssociatedSingleRouteBuilder<'a, C, P, PE, QSE> =
 /// #   assert_eq!(response.status(), StatusCode::Accepted);
 /// # }
 /// ```
+///
+/// # Panics
+///
+/// If any of the routes are invalid, such as a constrained segment with an invalid regex. Use
+/// `try_build_router` to receive the problems as a `RouterBuildError` instead.
 pub fn build_router<C, P, F>(pipeline_chain: C, pipelines: PipelineSet<P>, f: F) -> Router
+where
+    C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    P: Send + Sync + 'static,
+    F: FnOnce(&mut RouterBuilder<C, P>),
+{
+    try_build_router(pipeline_chain, pipelines, f).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Builds a `Router` in the same way as `build_router`, but returns a `RouterBuildError` listing
+/// every invalid route rather than panicking. This allows routes which are defined at runtime,
+/// such as from a configuration file, to be reported without crashing the application.
+///
+/// The problems reported are:
+///
+/// * Constrained segments (`:name:regex`) with a regex which can't be compiled.
+/// * Paths which delegate to another `Router`, but also have other routes at or beneath them.
+/// * Route names which are given to more than one route.
+/// * Scopes which are given more than one fallback route.
+/// * Conflicting routes, when `RouteValidation::Fail` has been chosen.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::State;
+/// # use gotham::router::builder::*;
+/// # use gotham::pipeline::set::{finalize_pipeline_set, new_pipeline_set};
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Accepted))
+/// # }
+/// #
+/// # fn main() {
+/// let pipelines = finalize_pipeline_set(new_pipeline_set());
+/// let result = try_build_router((), pipelines, |route| {
+///     route.get("/users/:id:[0-9+").to(my_handler);
+/// });
+///
+/// let error = result.err().unwrap();
+/// assert_eq!(error.errors()[0].path(), "/users/:id:[0-9+");
+/// # }
+/// ```
+pub fn try_build_router<C, P, F>(
+    pipeline_chain: C,
+    pipelines: PipelineSet<P>,
+    f: F,
+) -> Result<Router, RouterBuildError>
 where
     C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
     P: Send + Sync + 'static,
@@ -85,20 +145,82 @@ where
 {
     let mut tree_builder = TreeBuilder::new();
 
-    let response_finalizer = {
+    let (response_finalizer, route_validation, settings) = {
         let mut builder = RouterBuilder {
             node_builder: tree_builder.borrow_root_mut(),
             pipeline_chain,
             pipelines,
             response_finalizer_builder: ResponseFinalizerBuilder::internal_new(),
+            route_validation: RouteValidation::Warn,
+            settings: RouterSettings::default(),
         };
 
         f(&mut builder);
 
-        builder.response_finalizer_builder.finalize()
+        (
+            builder.response_finalizer_builder.finalize(),
+            builder.route_validation,
+            builder.settings,
+        )
     };
 
-    Router::internal_new(tree_builder.finalize(), response_finalizer)
+    let mut errors = tree_builder.errors();
+    let named_routes = tree_builder.named_routes().unwrap_or_else(|e| {
+        errors.extend(e);
+        NamedRoutes::default()
+    });
+
+    let tree = tree_builder.finalize();
+    errors.extend(validate_routes(&tree, route_validation));
+
+    if !errors.is_empty() {
+        return Err(RouterBuildError::new(errors));
+    }
+
+    Ok(Router::internal_new(
+        tree,
+        response_finalizer,
+        named_routes,
+        settings,
+    ))
+}
+
+/// Reports the conflicting routes in `tree` as chosen by `route_validation`, returning them as
+/// errors when the build should fail.
+fn validate_routes(tree: &Tree, route_validation: RouteValidation) -> Vec<RouteError> {
+    let conflicts = tree.find_conflicts();
+
+    match route_validation {
+        RouteValidation::Warn => {
+            for conflict in &conflicts {
+                warn!("route conflict: {}", conflict);
+            }
+            vec![]
+        }
+        RouteValidation::Fail => conflicts
+            .into_iter()
+            .map(|conflict| {
+                RouteError::new(
+                    conflict.path().to_owned(),
+                    RouteErrorReason::Conflict(conflict),
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Determines how conflicting routes are handled when a `Router` is built. A conflict is a route
+/// which can never be reached, because requests for it are always dispatched elsewhere. See
+/// `RouteConflict` for the conflicts which are detected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteValidation {
+    /// Each conflict is logged as a warning, and the `Router` is built anyway. This is the
+    /// default.
+    Warn,
+
+    /// Each conflict is reported as an error by `try_build_router`, so `build_router` panics
+    /// with a message listing the conflicts.
+    Fail,
 }
 
 /// Builds a `Router` with **no** middleware using the provided closure. Routes are defined using
@@ -144,6 +266,19 @@ where
     build_router(pipeline_chain, pipelines, f)
 }
 
+/// Builds a `Router` with **no** middleware in the same way as `build_simple_router`, but returns
+/// a `RouterBuildError` listing every invalid route rather than panicking. See `try_build_router`
+/// for the problems which are reported.
+pub fn try_build_simple_router<F>(f: F) -> Result<Router, RouterBuildError>
+where
+    F: FnOnce(&mut RouterBuilder<(), ()>),
+{
+    let pipelines = finalize_pipeline_set(new_pipeline_set());
+    let pipeline_chain = ();
+
+    try_build_router(pipeline_chain, pipelines, f)
+}
+
 /// The top-level builder which is created by `build_router` and passed to the provided closure.
 /// See the `build_router` function and the `DrawRoutes` trait for usage.
 pub struct RouterBuilder<'a, C, P>
@@ -155,6 +290,8 @@ where
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
     response_finalizer_builder: ResponseFinalizerBuilder,
+    route_validation: RouteValidation,
+    settings: RouterSettings,
 }
 
 impl<'a, C, P> RouterBuilder<'a, C, P>
@@ -229,6 +366,111 @@ where
         self.response_finalizer_builder
             .add(status_code, Box::new(extender))
     }
+
+    /// Chooses how conflicting routes are handled once the routes have been defined. By default,
+    /// they are logged as warnings.
+    ///
+    /// ```rust,should_panic
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::Response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::builder::*;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// build_simple_router(|route| {
+    ///     route.set_route_validation(RouteValidation::Fail);
+    ///
+    ///     route.get("/users/:id").to(my_handler);
+    ///     // Panics, as `/users/:id` is a dynamic segment which receives every request.
+    ///     route.get("/users/:name").to(my_handler);
+    /// });
+    /// # }
+    /// ```
+    pub fn set_route_validation(&mut self, route_validation: RouteValidation) {
+        self.route_validation = route_validation;
+    }
+
+    /// Chooses whether the `Router` answers `OPTIONS` requests itself. When enabled, which is the
+    /// default, an `OPTIONS` request for a path with routes but no `OPTIONS` route receives a
+    /// `200 OK` response, with an `Allow` header listing the methods of the routes at the path.
+    /// Routes defined with `options()` are always dispatched to as usual.
+    ///
+    /// When disabled, such requests receive a `405 Method Not Allowed` response.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Method, Response, StatusCode};
+    /// # use hyper::header::Allow;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new())
+    /// # }
+    /// #
+    /// fn router(automatic_options: bool) -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.set_automatic_options(automatic_options);
+    ///         route.get_or_head("/users").to(handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router(true)).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .build_request(Method::Options, "https://example.com/users")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Ok);
+    /// #   assert_eq!(
+    /// #       response.headers().get::<Allow>(),
+    /// #       Some(&Allow(vec![Method::Get, Method::Head, Method::Options]))
+    /// #   );
+    /// #
+    /// #   let test_server = TestServer::new(router(false)).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .build_request(Method::Options, "https://example.com/users")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+    /// # }
+    /// ```
+    pub fn set_automatic_options(&mut self, automatic_options: bool) {
+        self.settings.automatic_options = automatic_options;
+    }
+
+    /// Sets the `CorsPolicy` used to answer CORS preflight requests. Preflight requests are
+    /// `OPTIONS` requests, so they are only answered while `set_automatic_options` is enabled,
+    /// and for paths which have no `OPTIONS` route. See `CorsPolicy` for an example.
+    pub fn set_cors_policy(&mut self, cors_policy: CorsPolicy) {
+        self.settings.cors_policy = Some(cors_policy);
+    }
+
+    /// Sets the `PathPolicy`, which determines how requests for paths with trailing or repeated
+    /// slashes are treated, and whether literal segments are compared regardless of case. A
+    /// `Router` which is delegated to uses the policy of the `Router` delegating to it instead of
+    /// its own. See `PathPolicy` for an example.
+    pub fn set_path_policy(&mut self, path_policy: PathPolicy) {
+        self.settings.path_policy = path_policy;
+    }
+
+    /// Sets the `Key` used for signed and private cookies in the `CookieJar`. Without a key,
+    /// `CookieJar::signed` and `CookieJar::private` panic. A `Router` which is delegated to uses
+    /// the `CookieJar`, and so the key, of the `Router` delegating to it. See `CookieJar` for an
+    /// example.
+    pub fn set_cookie_key(&mut self, key: Key) {
+        self.settings.cookie_key = Some(key);
+    }
 }
 
 /// A scoped builder, which is created by `DrawRoutes::scope` and passed to the provided closure.
@@ -241,6 +483,7 @@ where
     node_builder: &'a mut NodeBuilder,
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
+    host: Option<HostRouteMatcher>,
 }
 
 /// A delegated builder, which is created by `DrawRoutes::delegate` and returned. The `DrawRoutes`
@@ -253,10 +496,9 @@ where
     node_builder: &'a mut NodeBuilder,
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
+    host: Option<HostRouteMatcher>,
 }
 
-type DelegatedRoute = RouteImpl<AnyRouteMatcher, NoopPathExtractor, NoopQueryStringExtractor>;
-
 impl<'a, C, P> DelegateRouteBuilder<'a, C, P>
 where
     C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
@@ -264,15 +506,32 @@ where
 {
     /// Directs the delegated route to the given `Router`.
     pub fn to_router(self, router: Router) {
-        let dispatcher = DispatcherImpl::new(router, self.pipeline_chain, self.pipelines);
-        let route: DelegatedRoute = DelegatedRoute::new(
-            AnyRouteMatcher::new(),
-            Box::new(dispatcher),
-            Extractors::new(),
-            Delegation::External,
-        );
+        self.node_builder.set_delegated_router(router.clone());
+
+        let dispatcher = Box::new(DispatcherImpl::new(
+            router,
+            self.pipeline_chain,
+            self.pipelines,
+        ));
+        let extractors: Extractors<NoopPathExtractor, NoopQueryStringExtractor> =
+            Extractors::new();
+
+        let route: Box<Route + Send + Sync> = match self.host {
+            Some(host) => Box::new(RouteImpl::new(
+                host,
+                dispatcher,
+                extractors,
+                Delegation::External,
+            )),
+            None => Box::new(RouteImpl::new(
+                AnyRouteMatcher::new(),
+                dispatcher,
+                extractors,
+                Delegation::External,
+            )),
+        };
 
-        self.node_builder.add_route(Box::new(route));
+        self.node_builder.add_route_recording_errors(route);
     }
 }
 
@@ -290,6 +549,10 @@ where
     matcher: M,
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
+    host: Option<HostRouteMatcher>,
+    fallback: bool,
+    header_extraction: Option<HeaderExtraction>,
+    body_extraction: Option<BodyExtraction>,
     phantom: PhantomData<(PE, QSE)>,
 }
 
@@ -314,6 +577,10 @@ where
             matcher: self.matcher,
             pipeline_chain: self.pipeline_chain,
             pipelines: self.pipelines,
+            host: self.host,
+            fallback: self.fallback,
+            header_extraction: self.header_extraction,
+            body_extraction: self.body_extraction,
             phantom: PhantomData,
         }
     }
@@ -331,6 +598,9 @@ where
     node_builder: &'a mut NodeBuilder,
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
+    host: Option<HostRouteMatcher>,
+    header_extraction: Option<HeaderExtraction>,
+    body_extraction: Option<BodyExtraction>,
     phantom: PhantomData<(PE, QSE)>,
 }
 
@@ -398,6 +668,9 @@ where
             node_builder: self.node_builder,
             pipeline_chain: self.pipeline_chain,
             pipelines: self.pipelines.clone(),
+            host: self.host.clone(),
+            header_extraction: self.header_extraction,
+            body_extraction: self.body_extraction,
             phantom: PhantomData,
         }
     }
@@ -462,10 +735,209 @@ where
             node_builder: self.node_builder,
             pipeline_chain: self.pipeline_chain,
             pipelines: self.pipelines.clone(),
+            host: self.host.clone(),
+            header_extraction: self.header_extraction,
+            body_extraction: self.body_extraction,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Binds a `HeaderExtractor` to the associated routes, so that values from the request headers
+    /// are stored in `State` before requests are dispatched to them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate gotham_derive;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use hyper::header::UserAgent;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn handler(state: State) -> (State, Response) {
+    ///     // Implementation elided.
+    /// #   assert_eq!(state.borrow::<MyHeaders>().user_agent.as_str(), "test");
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    ///
+    /// #[derive(StateData, HeaderExtractor, StaticResponseExtender)]
+    /// struct MyHeaders {
+    /// #   #[allow(dead_code)]
+    ///     user_agent: String,
+    /// }
+    ///
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.associate("/resource", |assoc| {
+    ///         let mut assoc = assoc.with_header_extractor::<MyHeaders>();
+    ///         assoc.get().to(handler);
+    ///         assoc.delete().to(handler);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/resource")
+    /// #       .with_header(UserAgent::new("test"))
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// # }
+    /// ```
+    pub fn with_header_extractor<'b, T>(&'b mut self) -> AssociatedRouteBuilder<'b, C, P, PE, QSE>
+    where
+        T: HeaderExtractor,
+    {
+        AssociatedRouteBuilder {
+            node_builder: self.node_builder,
+            pipeline_chain: self.pipeline_chain,
+            pipelines: self.pipelines.clone(),
+            host: self.host.clone(),
+            header_extraction: Some(HeaderExtraction::new::<T>()),
+            body_extraction: self.body_extraction,
             phantom: PhantomData,
         }
     }
 
+    /// Binds a `RequestBodyExtractor` to the associated routes, so that the request body is
+    /// deserialized into `State` before requests are dispatched to them. Bodies larger than
+    /// `DEFAULT_BODY_LIMIT` are refused.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate gotham_derive;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// # extern crate serde;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn handler(state: State) -> (State, Response) {
+    ///     // Implementation elided.
+    /// #   assert_eq!(state.borrow::<MyBody>().val.as_str(), "test_val");
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    ///
+    /// #[derive(StateData, Deserialize, StaticResponseExtender)]
+    /// struct MyBody {
+    /// #   #[allow(dead_code)]
+    ///     val: String,
+    /// }
+    ///
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.associate("/resource", |assoc| {
+    ///         let mut assoc = assoc.with_body_extractor::<MyBody>();
+    ///         assoc.post().to(handler);
+    ///         assoc.put().to(handler);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post(
+    /// #           "https://example.com/resource",
+    /// #           "val=test_val",
+    /// #           mime::APPLICATION_WWW_FORM_URLENCODED,
+    /// #       )
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// # }
+    /// ```
+    pub fn with_body_extractor<'b, T>(&'b mut self) -> AssociatedRouteBuilder<'b, C, P, PE, QSE>
+    where
+        T: RequestBodyExtractor,
+    {
+        self.with_body_extractor_limit::<T>(DEFAULT_BODY_LIMIT)
+    }
+
+    /// Binds a `RequestBodyExtractor` to the associated routes in the same way as
+    /// `with_body_extractor`, but refuses bodies larger than `limit` bytes with `413 Payload Too
+    /// Large`.
+    pub fn with_body_extractor_limit<'b, T>(
+        &'b mut self,
+        limit: usize,
+    ) -> AssociatedRouteBuilder<'b, C, P, PE, QSE>
+    where
+        T: RequestBodyExtractor,
+    {
+        AssociatedRouteBuilder {
+            node_builder: self.node_builder,
+            pipeline_chain: self.pipeline_chain,
+            pipelines: self.pipelines.clone(),
+            host: self.host.clone(),
+            header_extraction: self.header_extraction,
+            body_extraction: Some(BodyExtraction::new::<T>(limit)),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Names the associated path, so that it can be generated with `gotham::router::url_for`.
+    /// Names must be unique within a `Router`, including any `Router` it delegates to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::router::{url_for, Router};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn handler(state: State) -> (State, Response) {
+    ///     assert_eq!(url_for(&state, "resource", &[]).unwrap(), "/resource");
+    ///     // Implementation elided.
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    ///
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.associate("/resource", |assoc| {
+    ///         assoc.name("resource");
+    ///         assoc.get().to(handler);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/resource")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// # }
+    /// ```
+    pub fn name(&mut self, name: &str) {
+        self.node_builder.add_name(name);
+    }
+
     /// Associates a route which matches requests with any of the specified methods, to the current
     /// path.
     ///
@@ -526,6 +998,9 @@ where
             ref mut node_builder,
             ref pipeline_chain,
             ref pipelines,
+            ref host,
+            header_extraction,
+            body_extraction,
             phantom,
         } = *self;
 
@@ -537,6 +1012,10 @@ where
             node_builder: *node_builder,
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
+            host: host.clone(),
+            fallback: false,
+            header_extraction,
+            body_extraction,
         }
     }
 
@@ -884,9 +1363,10 @@ where
 mod tests {
     use super::*;
 
+    use std::net::SocketAddr;
     use std::sync::Arc;
 
-    use hyper::{Method, Request, Response, StatusCode};
+    use hyper::{Headers, Method, Request, Response, StatusCode};
     use hyper::server::Service;
     use futures::{Future, Stream};
     use tokio_core::reactor::Core;
@@ -896,6 +1376,7 @@ mod tests {
     use state::{State, StateData};
     use service::GothamService;
     use router::response::extender::StaticResponseExtender;
+    use extractor::fixtures::{create_user, ApiHeaders, NewUser};
 
     #[derive(Deserialize)]
     struct SalutationParams {
@@ -1063,7 +1544,7 @@ mod tests {
         let new_service = GothamService::new(Arc::new(router), core.handle());
 
         let mut call = move |req| {
-            let service = new_service.connect("127.0.0.1:10000".parse().unwrap());
+            let service = new_service.connect("127.0.0.1:10000".parse::<SocketAddr>().unwrap().into());
             core.run(service.call(req)).unwrap()
         };
 
@@ -1132,4 +1613,728 @@ mod tests {
         let response_bytes = response.body().concat2().wait().unwrap().to_vec();
         assert_eq!(&response_bytes[..], b"It's a resource.");
     }
+
+    #[test]
+    fn named_routes_generate_urls() {
+        use router::url_for;
+        use test::TestServer;
+
+        fn links(state: State) -> (State, Response) {
+            let body = vec![
+                url_for(&state, "index", &[]),
+                url_for(&state, "user_show", &[("id", "a b")]),
+                url_for(&state, "goodbye", &[("name", "world")]),
+                url_for(&state, "files", &[("*", "x/y")]),
+                url_for(&state, "resource", &[]),
+                url_for(&state, "admin_user", &[("tenant", "acme"), ("id", "7")]),
+            ].into_iter()
+                .map(|url| url.unwrap())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            (state, Response::new().with_status(StatusCode::Ok).with_body(body))
+        }
+
+        let delegated_router = build_simple_router(|route| {
+            route.get("/users/:id").name("admin_user").to(links);
+        });
+
+        let router = build_simple_router(|route| {
+            route.get("/").name("index").to(links);
+            route.scope("/api", |route| {
+                route.get("/users/:id").name("user_show").to(links);
+                route.get("/goodbye/:name:[a-z]+").name("goodbye").to(links);
+            });
+            route.get("/files/*").name("files").to(links);
+            route.associate("/resource", |assoc| {
+                assoc.name("resource");
+                assoc.get().to(links);
+            });
+            route
+                .delegate("/admin/:tenant")
+                .to_router(delegated_router);
+        });
+
+        let expected = "/ /api/users/a%20b /api/goodbye/world /files/x/y /resource \
+                        /admin/acme/users/7";
+
+        let test_server = TestServer::new(router).unwrap();
+        for uri in &["http://localhost/", "http://localhost/admin/t/users/1"] {
+            let response = test_server.client().get(*uri).perform().unwrap();
+            assert_eq!(response.status(), StatusCode::Ok);
+            assert_eq!(response.read_utf8_body().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "route name `index` is defined more than once")]
+    fn duplicate_route_names_panic() {
+        build_simple_router(|route| {
+            route.get("/").name("index").to(api::submit);
+            route.get("/home").name("index").to(api::submit);
+        });
+    }
+
+    #[test]
+    fn router_describes_routes() {
+        use router::route::matcher::AcceptHeaderRouteMatcher;
+
+        let delegated_router = build_simple_router(|route| {
+            route.get("/").to(welcome::delegated);
+            route.post("/b").to(welcome::delegated);
+        });
+
+        let router = build_simple_router(|route| {
+            route.get("/").name("index").to(welcome::index);
+            route
+                .get("/hello/:name:[a-z]+/*")
+                .with_path_extractor::<SalutationParams>()
+                .to(welcome::globbed);
+            route
+                .get(r"/literal/\:param")
+                .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![]))
+                .to(welcome::literal);
+            route.scope("/api", |route| {
+                route.associate("/add", |assoc| {
+                    let mut assoc = assoc.with_query_string_extractor::<AddParams>();
+                    assoc.get_or_head().to(welcome::add);
+                });
+            });
+            route.delegate("/delegated").to_router(delegated_router);
+        });
+
+        let routes = router.routes();
+        let described = routes
+            .iter()
+            .map(|r| (r.path(), r.methods().map(|m| m.to_vec()), r.is_delegated()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            described,
+            vec![
+                ("/", Some(vec![Method::Get]), false),
+                ("/api/add", Some(vec![Method::Get, Method::Head]), false),
+                ("/delegated", None, true),
+                ("/delegated", Some(vec![Method::Get]), false),
+                ("/delegated/b", Some(vec![Method::Post]), false),
+                ("/hello/:name:[a-z]+/*", Some(vec![Method::Get]), false),
+                (r"/literal/\:param", Some(vec![Method::Get]), false),
+            ]
+        );
+
+        assert_eq!(routes[0].names(), &["index".to_owned()]);
+        assert!(routes[1].query_string_extractor().ends_with("AddParams"));
+        assert!(routes[5].path_extractor().ends_with("SalutationParams"));
+
+        let table = router.to_string();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), routes.len() + 1);
+        assert_eq!(lines[0], "NAME   METHODS   PATH                   EXTRACTORS");
+        assert_eq!(lines[1], "index  GET       /");
+        assert_eq!(lines[3], "       *         /delegated             (delegated)");
+    }
+
+    #[test]
+    fn route_conflicts_are_detected() {
+        use router::RouteConflict;
+        use router::route::matcher::AcceptHeaderRouteMatcher;
+
+        let delegated_router = build_simple_router(|route| {
+            route.get("/").to(welcome::delegated);
+        });
+
+        let router = build_simple_router(|route| {
+            route.get("/a").to(welcome::index);
+            route.get_or_head("/a").to(welcome::index);
+
+            route
+                .get("/b")
+                .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![]))
+                .to(welcome::index);
+            route.get("/b").to(welcome::index);
+
+            route.get("/users/:id").to(welcome::index);
+            route.get("/users/:name").to(welcome::index);
+
+            route.delegate("/t/:tenant").to_router(delegated_router);
+            route.get("/t/*").to(welcome::index);
+
+            route.get("/files/*").to(welcome::index);
+            route.get("/files/*/:name").to(welcome::index);
+        });
+
+        assert_eq!(
+            router.data.tree.find_conflicts(),
+            vec![
+                RouteConflict::Duplicate {
+                    path: "/a".to_owned(),
+                    methods: vec![Method::Get],
+                },
+                RouteConflict::Glob {
+                    glob: "/files/*".to_owned(),
+                    shadowed_by: "/files/*/:name".to_owned(),
+                },
+                RouteConflict::Unreachable {
+                    path: "/t/*".to_owned(),
+                    shadowed_by: "/t/:tenant".to_owned(),
+                },
+                RouteConflict::Unreachable {
+                    path: "/users/:name".to_owned(),
+                    shadowed_by: "/users/:id".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct_routes_do_not_conflict() {
+        let router = build_simple_router(|route| {
+            route.set_route_validation(RouteValidation::Fail);
+
+            route.get("/users").to(welcome::index);
+            route.post("/users").to(welcome::index);
+            route.get("/users/new").to(welcome::index);
+            route.get("/users/:id:[0-9]+").to(welcome::index);
+            route.get("/users/:name").to(welcome::index);
+            route.get("/users/:name/posts").to(welcome::index);
+            route.get("/users/*").to(welcome::index);
+        });
+
+        assert!(router.data.tree.find_conflicts().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "unable to build router:\n  /: GET / is registered more than once")]
+    fn route_validation_fail_panics() {
+        build_simple_router(|route| {
+            route.set_route_validation(RouteValidation::Fail);
+            route.get("/").to(welcome::index);
+            route.get("/").to(welcome::index);
+        });
+    }
+
+    #[test]
+    fn try_build_router_reports_every_error() {
+        let delegated_router = build_simple_router(|route| {
+            route.get("/").to(welcome::index);
+        });
+
+        let error = try_build_simple_router(|route| {
+            route.get("/users/:id:[0-9+").to(welcome::index);
+            route.get("/users/:id:[0-9+/posts").to(welcome::index);
+            route.get("/posts/:slug:(").to(welcome::index);
+
+            route.delegate("/api").to_router(delegated_router.clone());
+            route.get("/api/status").to(welcome::index);
+            route.get("/api/health").to(welcome::index);
+
+            route.get("/admin").to(welcome::index);
+            route.delegate("/admin").to_router(delegated_router.clone());
+
+            route.get("/").name("home").to(welcome::index);
+            route.get("/index").name("home").to(welcome::index);
+        }).err()
+            .unwrap();
+
+        let errors = error
+            .errors()
+            .iter()
+            .map(|e| (e.path(), e.reason()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(errors.len(), 5);
+        match errors[0] {
+            ("/users/:id:[0-9+", &RouteErrorReason::InvalidRegex { ref regex, .. }) => {
+                assert_eq!(regex, "[0-9+")
+            }
+            ref other => panic!("unexpected error: {:?}", other),
+        }
+        match errors[1] {
+            ("/posts/:slug:(", &RouteErrorReason::InvalidRegex { ref regex, .. }) => {
+                assert_eq!(regex, "(")
+            }
+            ref other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(errors[2], ("/api", &RouteErrorReason::DelegatedWithChildren));
+        assert_eq!(errors[3], ("/admin", &RouteErrorReason::DelegatedWithRoutes));
+        assert_eq!(
+            errors[4],
+            (
+                "/index",
+                &RouteErrorReason::DuplicateName("home".to_owned())
+            )
+        );
+    }
+
+    #[test]
+    fn options_requests_are_answered_automatically() {
+        use hyper::header::{AccessControlAllowMethods, AccessControlRequestMethod, Allow,
+                            Origin};
+        use test::TestServer;
+
+        let router = build_simple_router(|route| {
+            route.set_cors_policy(CorsPolicy::new());
+
+            route.get("/users").to(welcome::index);
+            route.post("/users").to(welcome::index);
+            route.options("/explicit").to(api::submit);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+
+        let response = test_server
+            .client()
+            .build_request(Method::Options, "http://localhost/users")
+            .with_header(AccessControlRequestMethod(Method::Post))
+            .with_header(Origin::new("https", "example.com", None))
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        let allow = vec![Method::Get, Method::Post, Method::Options];
+        assert_eq!(response.headers().get::<Allow>(), Some(&Allow(allow.clone())));
+        assert_eq!(
+            response.headers().get::<AccessControlAllowMethods>(),
+            Some(&AccessControlAllowMethods(allow))
+        );
+
+        let response = test_server
+            .client()
+            .build_request(Method::Options, "http://localhost/explicit")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::Accepted);
+
+        let response = test_server
+            .client()
+            .build_request(Method::Options, "http://localhost/missing")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NotFound);
+    }
+
+    #[test]
+    fn host_scopes_dispatch_on_host() {
+        use test::TestServer;
+
+        #[derive(Deserialize)]
+        struct TenantParams {
+            tenant: String,
+            name: String,
+        }
+
+        impl StateData for TenantParams {}
+
+        impl StaticResponseExtender for TenantParams {
+            fn extend(_: &mut State, _: &mut Response) {}
+        }
+
+        fn tenant_user(mut state: State) -> (State, Response) {
+            let params = state.take::<TenantParams>();
+            let response = Response::new()
+                .with_status(StatusCode::Ok)
+                .with_body(format!("{}/{}", params.tenant, params.name));
+            (state, response)
+        }
+
+        let delegated_router = build_simple_router(|route| {
+            route.get("/").to(api::submit);
+        });
+
+        let router = build_simple_router(|route| {
+            route.scope("/users", |route| {
+                route.host(":tenant.example.com", |route| {
+                    route
+                        .get("/:name")
+                        .with_path_extractor::<TenantParams>()
+                        .to(tenant_user);
+                });
+            });
+
+            route.host("admin.example.org", |route| {
+                route.delegate("/admin").to_router(delegated_router);
+            });
+
+            route.get("/users/:name").to(welcome::index);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let get = |uri: &str| test_server.client().get(uri).perform().unwrap();
+
+        let response = get("http://acme.example.com/users/bob");
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "acme/bob");
+
+        let response = get("http://example.com/users/bob");
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.read_utf8_body().unwrap(), "");
+
+        assert_eq!(
+            get("http://admin.example.org/admin").status(),
+            StatusCode::Accepted
+        );
+        assert_eq!(
+            get("http://www.example.org/admin").status(),
+            StatusCode::NotFound
+        );
+    }
+
+    #[test]
+    fn path_policy_is_inherited_by_delegated_routers() {
+        use hyper::header::Location;
+        use router::{PathNormalization, RedirectStatus};
+        use test::TestServer;
+
+        let delegated_router = build_simple_router(|route| {
+            route.set_path_policy(PathPolicy::new());
+            route.get("/Submit").to(api::submit);
+        });
+
+        let router = build_simple_router(|route| {
+            route.set_path_policy(
+                PathPolicy::new()
+                    .with_normalization(PathNormalization::Strict)
+                    .with_case_insensitive_literals(true),
+            );
+            route.get("/users").to(welcome::index);
+            route.delegate("/api").to_router(delegated_router);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let get = |uri: &str| test_server.client().get(uri).perform().unwrap().status();
+
+        assert_eq!(get("http://localhost/users"), StatusCode::Ok);
+        assert_eq!(get("http://localhost/USERS"), StatusCode::Ok);
+        assert_eq!(get("http://localhost/users/"), StatusCode::NotFound);
+        assert_eq!(get("http://localhost/api/submit"), StatusCode::Accepted);
+        assert_eq!(get("http://localhost/api//submit"), StatusCode::NotFound);
+
+        let router = build_simple_router(|route| {
+            route.set_path_policy(PathPolicy::new().with_normalization(
+                PathNormalization::Redirect(RedirectStatus::PermanentRedirect),
+            ));
+            route.get("/").to(welcome::index);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server.client().get("http://localhost//").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::PermanentRedirect);
+        assert_eq!(response.headers().get::<Location>(), Some(&Location::new("/")));
+    }
+
+    #[test]
+    fn cookie_jar_is_shared_with_delegated_routers() {
+        use hyper::header::{Cookie, SetCookie};
+        use http::cookie::{Cookie as JarCookie, CookieJar};
+        use state::FromState;
+        use test::TestServer;
+
+        fn rename(mut state: State) -> (State, Response) {
+            {
+                let jar = CookieJar::borrow_mut_from(&mut state);
+                let name = jar.get("name").map(|cookie| cookie.value().to_owned());
+                jar.add(JarCookie::new("user", name.unwrap_or_default()));
+                jar.remove(JarCookie::named("name"));
+            }
+
+            (state, Response::new())
+        }
+
+        let delegated_router = build_simple_router(|route| {
+            route.get("/rename").to(rename);
+        });
+
+        let router = build_simple_router(|route| {
+            route.delegate("/api").to_router(delegated_router);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let mut cookies = Cookie::new();
+        cookies.append("name", "alice");
+
+        let response = test_server
+            .client()
+            .get("http://localhost/api/rename")
+            .with_header(cookies)
+            .perform()
+            .unwrap();
+
+        let mut set_cookie = response.headers().get::<SetCookie>().unwrap().0.clone();
+        set_cookie.sort();
+        assert_eq!(set_cookie.len(), 2);
+        assert!(set_cookie[0].starts_with("name=; Max-Age=0;"));
+        assert_eq!(set_cookie[1], "user=alice");
+    }
+
+    #[test]
+    fn fallback_routes_answer_unmatched_requests() {
+        use test::TestServer;
+
+        let router = build_simple_router(|route| {
+            route.scope("/api", |route| {
+                route.post("/submit").to(api::submit);
+                route.fallback().to(welcome::literal);
+            });
+            route.fallback().to(welcome::index);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let get = |uri: &str| test_server.client().get(uri).perform().unwrap().status();
+
+        assert_eq!(get("http://localhost/api/missing"), StatusCode::Created);
+        assert_eq!(get("http://localhost/api"), StatusCode::Created);
+        assert_eq!(get("http://localhost/missing/page"), StatusCode::Ok);
+
+        // Requests which matched a path, but not its routes, are not given to the fallback.
+        assert_eq!(get("http://localhost/api/submit"), StatusCode::MethodNotAllowed);
+
+        let errors = try_build_simple_router(|route| {
+            route.fallback().to(welcome::index);
+            route.fallback().to(welcome::literal);
+        }).err()
+            .unwrap();
+        assert_eq!(
+            errors.errors(),
+            &[
+                RouteError::new("/".to_owned(), RouteErrorReason::DuplicateFallback),
+            ]
+        );
+    }
+
+    #[test]
+    fn fallback_routes_extract_the_request_path() {
+        use test::TestServer;
+
+        #[derive(Deserialize)]
+        struct TenantParams {
+            id: String,
+        }
+
+        impl StateData for TenantParams {}
+
+        impl StaticResponseExtender for TenantParams {
+            fn extend(_: &mut State, _: &mut Response) {}
+        }
+
+        fn tenant_not_found(mut state: State) -> (State, Response) {
+            let params = state.take::<TenantParams>();
+            let response = Response::new()
+                .with_status(StatusCode::NotFound)
+                .with_body(format!("tenant {}", params.id));
+            (state, response)
+        }
+
+        let router = build_simple_router(|route| {
+            route.scope("/tenants/:id", |route| {
+                route.get("/users").to(welcome::index);
+                route
+                    .fallback()
+                    .with_path_extractor::<TenantParams>()
+                    .to(tenant_not_found);
+            });
+        });
+
+        let described = router
+            .routes()
+            .iter()
+            .map(|r| (r.path().to_owned(), r.is_fallback()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            described,
+            vec![
+                ("/tenants/:id".to_owned(), true),
+                ("/tenants/:id/users".to_owned(), false),
+            ]
+        );
+
+        let test_server = TestServer::new(router).unwrap();
+        let get = |uri: &str| test_server.client().get(uri).perform().unwrap();
+
+        assert_eq!(get("http://localhost/tenants/acme/users").status(), StatusCode::Ok);
+
+        let response = get("http://localhost/tenants/acme/missing/page");
+        assert_eq!(response.status(), StatusCode::NotFound);
+        assert_eq!(response.read_utf8_body().unwrap(), "tenant acme");
+
+        let response = get("http://localhost/tenants/acme");
+        assert_eq!(response.status(), StatusCode::NotFound);
+        assert_eq!(response.read_utf8_body().unwrap(), "tenant acme");
+    }
+
+    #[test]
+    fn body_extractors_deserialize_request_bodies() {
+        use mime;
+        use test::TestServer;
+
+        let router = build_simple_router(|route| {
+            route
+                .post("/users")
+                .with_body_extractor::<NewUser>()
+                .to(create_user);
+
+            route.associate("/small", |assoc| {
+                let mut assoc = assoc.with_body_extractor_limit::<NewUser>(16);
+                assoc.post().to(create_user);
+            });
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let post = |uri: &str, body: &str, content_type: &str| {
+            let response = test_server
+                .client()
+                .post(uri, body.to_owned(), content_type.parse().unwrap())
+                .perform()
+                .unwrap();
+            let status = response.status();
+            (status, response.read_utf8_body().unwrap())
+        };
+
+        let json = mime::APPLICATION_JSON.as_ref();
+        let form = mime::APPLICATION_WWW_FORM_URLENCODED.as_ref();
+
+        assert_eq!(
+            post("http://localhost/users", r#"{"name":"Alice","age":30}"#, json),
+            (StatusCode::Created, "Alice is 30".to_owned())
+        );
+        assert_eq!(
+            post("http://localhost/users", r#"{"name":"Eve","age":9}"#, "application/user+json"),
+            (StatusCode::Created, "Eve is 9".to_owned())
+        );
+        assert_eq!(
+            post("http://localhost/users", "name=Bob+Smith&age=4", form),
+            (StatusCode::Created, "Bob Smith is 4".to_owned())
+        );
+
+        assert_eq!(
+            post("http://localhost/users", "name=Bob&age=many", form).0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            post("http://localhost/users", r#"{"name":"Alice"}"#, json).0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            post("http://localhost/users", "name=Bob&age=4", "text/plain").0,
+            StatusCode::UnsupportedMediaType
+        );
+
+        assert_eq!(
+            post("http://localhost/small", "name=Al&age=3", form),
+            (StatusCode::Created, "Al is 3".to_owned())
+        );
+        assert_eq!(
+            post("http://localhost/small", "name=Alice&age=30", form).0,
+            StatusCode::PayloadTooLarge
+        );
+    }
+
+    #[test]
+    fn body_extractors_read_the_body_after_pipelines() {
+        use std::io;
+
+        use hyper::header::ContentLength;
+        use mime;
+
+        use handler::HandlerFuture;
+        use middleware::{Middleware, NewMiddleware};
+        use middleware::body_limit::BodyLimitMiddleware;
+        use pipeline::single::single_pipeline;
+        use test::TestServer;
+
+        // Makes the body look like a chunked body to the `BodyLimitMiddleware`, which then limits
+        // it while it is read rather than refusing it up front.
+        #[derive(Clone, Copy)]
+        struct RemoveContentLength;
+
+        impl NewMiddleware for RemoveContentLength {
+            type Instance = RemoveContentLength;
+
+            fn new_middleware(&self) -> io::Result<RemoveContentLength> {
+                Ok(*self)
+            }
+        }
+
+        impl Middleware for RemoveContentLength {
+            fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+            where
+                Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+            {
+                state.borrow_mut::<Headers>().remove::<ContentLength>();
+                chain(state)
+            }
+        }
+
+        let (chain, pipelines) = single_pipeline(
+            new_pipeline()
+                .add(RemoveContentLength)
+                .add(BodyLimitMiddleware::new(24))
+                .build(),
+        );
+
+        let router = build_router(chain, pipelines, |route| {
+            route
+                .post("/users")
+                .with_body_extractor::<NewUser>()
+                .to(create_user);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let post = |body: &str| {
+            test_server
+                .client()
+                .post("http://localhost/users", body.to_owned(), mime::APPLICATION_JSON)
+                .perform()
+                .unwrap()
+                .status()
+        };
+
+        assert_eq!(post(r#"{"name":"Al","age":3}"#), StatusCode::Created);
+        assert_eq!(post(r#"{"name":"Alice","age":30}"#), StatusCode::PayloadTooLarge);
+    }
+
+    #[test]
+    fn header_extractors_run_before_body_extractors() {
+        use hyper::header::Authorization;
+        use mime;
+        use test::TestServer;
+
+        let router = build_simple_router(|route| {
+            route.associate("/users", |assoc| {
+                let mut assoc = assoc.with_header_extractor::<ApiHeaders>();
+                let mut assoc = assoc.with_body_extractor::<NewUser>();
+                assoc.post().to(create_user);
+            });
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let post = |key: Option<&str>, body: &str| {
+            let mut req = test_server.client().post(
+                "http://localhost/users",
+                body.to_owned(),
+                mime::APPLICATION_JSON,
+            );
+            if let Some(key) = key {
+                req = req.with_header(Authorization(key.to_owned()));
+            }
+
+            let response = req.perform().unwrap();
+            let status = response.status();
+            (status, response.read_utf8_body().unwrap())
+        };
+
+        assert_eq!(
+            post(Some("k1"), r#"{"name":"Alice","age":30}"#),
+            (StatusCode::Created, "Alice is 30, by k1".to_owned())
+        );
+        assert_eq!(post(None, r#"{"name":"Alice","age":30}"#).0, StatusCode::BadRequest);
+        assert_eq!(post(None, "not json").0, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn try_build_router_builds_valid_routes() {
+        let router = try_build_simple_router(|route| {
+            route.get("/users/:id:[0-9]+").to(welcome::index);
+        }).unwrap();
+
+        assert_eq!(router.routes()[0].path(), "/users/:id:[0-9]+");
+    }
 }
@@ -104,6 +104,10 @@ where
             node_builder: self.node_builder,
             pipeline_chain: self.pipeline_chain,
             pipelines: self.pipelines,
+            host: self.host,
+            fallback: self.fallback,
+            header_extraction: self.header_extraction,
+            body_extraction: self.body_extraction,
         }
     }
 }
@@ -1,11 +1,13 @@
 use std::panic::RefUnwindSafe;
 
-use extractor::{PathExtractor, QueryStringExtractor};
+use extractor::{BodyExtraction, HeaderExtraction, HeaderExtractor, PathExtractor,
+                QueryStringExtractor, RequestBodyExtractor, DEFAULT_BODY_LIMIT};
 use pipeline::chain::PipelineHandleChain;
 use router::builder::{ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor,
                       SingleRouteBuilder};
-use router::route::{Delegation, Extractors, RouteImpl};
-use router::route::matcher::RouteMatcher;
+use router::route::{Delegation, Extractors, Route, RouteImpl};
+use router::route::matcher::{AndRouteMatcher, HeaderRouteMatcher, QueryParamRouteMatcher,
+                             RouteMatcher};
 use router::route::dispatch::DispatcherImpl;
 use handler::{Handler, NewHandler};
 
@@ -305,6 +307,131 @@ pub trait DefineSingleRoute {
         Self: ReplaceQueryStringExtractor<NQSE>,
         Self::Output: DefineSingleRoute;
 
+    /// Applies a `HeaderExtractor` type to the current route, so that values from the request
+    /// headers are stored in `State` with the given type before the request is dispatched. A
+    /// request with a required header which is missing or invalid is refused with `400 Bad
+    /// Request`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate gotham_derive;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use hyper::header::UserAgent;
+    /// # use gotham::state::{State, FromState};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// #[derive(StateData, HeaderExtractor, StaticResponseExtender)]
+    /// struct MyHeaders {
+    ///     user_agent: String,
+    /// }
+    ///
+    /// fn my_handler(state: State) -> (State, Response) {
+    ///     let agent = MyHeaders::borrow_from(&state).user_agent.clone();
+    ///
+    ///     // Handler implementation elided.
+    /// #   assert_eq!(agent, "test");
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/request/path")
+    ///          .with_header_extractor::<MyHeaders>()
+    ///          .to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/request/path")
+    /// #       .with_header(UserAgent::new("test"))
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// # }
+    /// ```
+    fn with_header_extractor<T>(self) -> Self
+    where
+        T: HeaderExtractor,
+        Self: Sized;
+
+    /// Applies a `RequestBodyExtractor` type to the current route, so that the request body is
+    /// deserialized into `State` with the given type once the pipelines have been invoked, before
+    /// the `Handler` is called. Bodies larger than `DEFAULT_BODY_LIMIT` are refused.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate gotham_derive;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// # extern crate serde;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::state::{State, FromState};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// #[derive(StateData, Deserialize, StaticResponseExtender)]
+    /// struct MyBody {
+    /// #   #[allow(dead_code)]
+    ///     id: u64,
+    /// }
+    ///
+    /// fn my_handler(state: State) -> (State, Response) {
+    ///     let id = MyBody::borrow_from(&state).id;
+    ///
+    ///     // Handler implementation elided.
+    /// #   assert_eq!(id, 42);
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.post("/request/path")
+    ///          .with_body_extractor::<MyBody>()
+    ///          .to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/request/path", r#"{"id": 42}"#, mime::APPLICATION_JSON)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// # }
+    /// ```
+    fn with_body_extractor<T>(self) -> Self
+    where
+        T: RequestBodyExtractor,
+        Self: Sized,
+    {
+        self.with_body_extractor_limit::<T>(DEFAULT_BODY_LIMIT)
+    }
+
+    /// Applies a `RequestBodyExtractor` type to the current route in the same way as
+    /// `with_body_extractor`, but refuses bodies larger than `limit` bytes with `413 Payload Too
+    /// Large`.
+    fn with_body_extractor_limit<T>(self, limit: usize) -> Self
+    where
+        T: RequestBodyExtractor,
+        Self: Sized;
+
     /// ```
     /// # extern crate gotham;
     /// # extern crate hyper;
@@ -363,6 +490,168 @@ pub trait DefineSingleRoute {
         NRM: RouteMatcher + Send + Sync + 'static,
         Self: ExtendRouteMatcher<NRM>,
         Self::Output: DefineSingleRoute;
+
+    /// Adds a `HeaderRouteMatcher` to the route, so that it only matches requests made with the
+    /// header `name` set to exactly `value`. Requests without that value are treated as `404 Not
+    /// Found` by this route, which allows several routes for the same path to be selected by a
+    /// header, such as an API version.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # header! { (XApiVersion, "X-Api-Version") => [String] }
+    /// #
+    /// fn users_v1(state: State) -> (State, Response) {
+    ///     // Handler implementation elided.
+    /// #   (state, Response::new().with_status(StatusCode::Ok))
+    /// }
+    ///
+    /// fn users_v2(state: State) -> (State, Response) {
+    ///     // Handler implementation elided.
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/users").with_header("X-Api-Version", "2").to(users_v2);
+    ///     route.get("/users").to(users_v1);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/users")
+    /// #       .with_header(XApiVersion("2".to_owned()))
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/users")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Ok);
+    /// # }
+    /// ```
+    fn with_header(
+        self,
+        name: &str,
+        value: &str,
+    ) -> <Self as ExtendRouteMatcher<HeaderRouteMatcher>>::Output
+    where
+        Self: ExtendRouteMatcher<HeaderRouteMatcher> + Sized,
+        <Self as ExtendRouteMatcher<HeaderRouteMatcher>>::Output: DefineSingleRoute;
+
+    /// Adds a `QueryParamRouteMatcher` to the route, so that it only matches requests made with
+    /// the query string parameter `name` set to exactly `value`. Requests without that value are
+    /// treated as `404 Not Found` by this route.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn search(state: State) -> (State, Response) {
+    ///     // Handler implementation elided.
+    /// #   (state, Response::new().with_status(StatusCode::Ok))
+    /// }
+    ///
+    /// fn search_canary(state: State) -> (State, Response) {
+    ///     // Handler implementation elided.
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/search").with_query_param("canary", "true").to(search_canary);
+    ///     route.get("/search").to(search);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/search?q=gotham&canary=true")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/search?q=gotham")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Ok);
+    /// # }
+    /// ```
+    fn with_query_param(
+        self,
+        name: &str,
+        value: &str,
+    ) -> <Self as ExtendRouteMatcher<QueryParamRouteMatcher>>::Output
+    where
+        Self: ExtendRouteMatcher<QueryParamRouteMatcher> + Sized,
+        <Self as ExtendRouteMatcher<QueryParamRouteMatcher>>::Output: DefineSingleRoute;
+
+    /// Names the path of the route, so that it can be generated with `gotham::router::url_for`.
+    /// Names must be unique within a `Router`, including any `Router` it delegates to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::{url_for, Router};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn my_handler(state: State) -> (State, Response) {
+    ///     assert_eq!(
+    ///         url_for(&state, "user_show", &[("id", "42")]).unwrap(),
+    ///         "/users/42"
+    ///     );
+    ///     // Handler implementation elided.
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/users/:id").name("user_show").to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/users/1")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::Accepted);
+    /// # }
+    /// ```
+    fn name(self, name: &str) -> Self
+    where
+        Self: Sized;
 }
 
 impl<'a, M, C, P, PE, QSE> DefineSingleRoute for SingleRouteBuilder<'a, M, C, P, PE, QSE>
@@ -384,14 +673,31 @@ where
     where
         NH: NewHandler + 'static,
     {
-        let dispatcher = DispatcherImpl::new(new_handler, self.pipeline_chain, self.pipelines);
-        let route: RouteImpl<M, PE, QSE> = RouteImpl::new(
-            self.matcher,
-            Box::new(dispatcher),
-            Extractors::new(),
-            Delegation::Internal,
+        let dispatcher = Box::new(
+            DispatcherImpl::new(new_handler, self.pipeline_chain, self.pipelines)
+                .with_body_extraction(self.body_extraction),
         );
-        self.node_builder.add_route(Box::new(route));
+        let extractors: Extractors<PE, QSE> = Extractors::new();
+
+        let route: Box<Route + Send + Sync> = match self.host {
+            Some(host) => Box::new(RouteImpl::new(
+                AndRouteMatcher::new(host, self.matcher),
+                dispatcher,
+                extractors,
+                Delegation::Internal,
+            ).with_header_extraction(self.header_extraction)),
+            None => Box::new(RouteImpl::new(
+                self.matcher,
+                dispatcher,
+                extractors,
+                Delegation::Internal,
+            ).with_header_extraction(self.header_extraction)),
+        };
+        if self.fallback {
+            self.node_builder.set_fallback_recording_errors(route);
+        } else {
+            self.node_builder.add_route_recording_errors(route);
+        }
     }
 
     fn with_path_extractor<NPE>(self) -> <Self as ReplacePathExtractor<NPE>>::Output
@@ -410,10 +716,51 @@ where
         self.replace_query_string_extractor()
     }
 
+    fn with_header_extractor<T>(self) -> Self
+    where
+        T: HeaderExtractor,
+    {
+        SingleRouteBuilder {
+            header_extraction: Some(HeaderExtraction::new::<T>()),
+            ..self
+        }
+    }
+
+    fn with_body_extractor_limit<T>(self, limit: usize) -> Self
+    where
+        T: RequestBodyExtractor,
+    {
+        SingleRouteBuilder {
+            body_extraction: Some(BodyExtraction::new::<T>(limit)),
+            ..self
+        }
+    }
+
     fn add_route_matcher<NRM>(self, matcher: NRM) -> <Self as ExtendRouteMatcher<NRM>>::Output
     where
         NRM: RouteMatcher + Send + Sync + 'static,
     {
         self.extend_route_matcher(matcher)
     }
+
+    fn with_header(
+        self,
+        name: &str,
+        value: &str,
+    ) -> <Self as ExtendRouteMatcher<HeaderRouteMatcher>>::Output {
+        self.extend_route_matcher(HeaderRouteMatcher::equals(name, value))
+    }
+
+    fn with_query_param(
+        self,
+        name: &str,
+        value: &str,
+    ) -> <Self as ExtendRouteMatcher<QueryParamRouteMatcher>>::Output {
+        self.extend_route_matcher(QueryParamRouteMatcher::equals(name, value))
+    }
+
+    fn name(self, name: &str) -> Self {
+        self.node_builder.add_name(name);
+        self
+    }
 }
@@ -0,0 +1,84 @@
+//! Defines `RouteConflict`, which describes a route that can't be reached as it was intended.
+
+use std::fmt::{self, Display, Formatter};
+
+use hyper::Method;
+
+/// A problem with the routes of a `Router`, found when it is built. Depending on the
+/// `RouteValidation` chosen for the builder, conflicts are logged or cause the build to fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RouteConflict {
+    /// A route was registered at `path` for `methods` which an earlier route at the same path
+    /// already accepts, so the later route is never reached for those methods.
+    Duplicate {
+        /// The path pattern of both routes.
+        path: String,
+        /// The methods accepted by both routes.
+        methods: Vec<Method>,
+    },
+
+    /// Every request which could reach the routes at `path` is dispatched via `shadowed_by`
+    /// instead. This happens when an earlier sibling is a dynamic segment with routes of its own,
+    /// or when it delegates to another `Router`, which receives every request beneath it.
+    Unreachable {
+        /// The path pattern of the routes which are never reached.
+        path: String,
+        /// The path pattern which receives the requests instead.
+        shadowed_by: String,
+    },
+
+    /// The routes for the glob at `glob` only match requests with a single segment in place of
+    /// the glob, because the dynamic segment or glob at `shadowed_by` matches longer paths first.
+    Glob {
+        /// The path pattern ending in the glob.
+        glob: String,
+        /// The path pattern which receives requests with more than one segment for the glob.
+        shadowed_by: String,
+    },
+}
+
+impl RouteConflict {
+    /// The path pattern of the routes which aren't reached as intended.
+    pub fn path(&self) -> &str {
+        match *self {
+            RouteConflict::Duplicate { ref path, .. }
+            | RouteConflict::Unreachable { ref path, .. } => path,
+            RouteConflict::Glob { ref glob, .. } => glob,
+        }
+    }
+}
+
+impl Display for RouteConflict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            RouteConflict::Duplicate {
+                ref path,
+                ref methods,
+            } => {
+                let methods = methods.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{} {} is registered more than once, and only the first route is reachable",
+                    methods.join(","),
+                    path
+                )
+            }
+            RouteConflict::Unreachable {
+                ref path,
+                ref shadowed_by,
+            } => write!(
+                f,
+                "routes at {} are unreachable, as requests are dispatched via {}",
+                path, shadowed_by
+            ),
+            RouteConflict::Glob {
+                ref glob,
+                ref shadowed_by,
+            } => write!(
+                f,
+                "routes at {} only match a single segment, as longer paths are dispatched via {}",
+                glob, shadowed_by
+            ),
+        }
+    }
+}
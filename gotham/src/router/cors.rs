@@ -0,0 +1,282 @@
+//! Defines `CorsPolicy`, which the `Router` uses to answer CORS preflight requests.
+
+use std::str;
+
+use hyper::{Headers, Method};
+use hyper::header::{AccessControlAllowCredentials, AccessControlAllowMethods,
+                    AccessControlAllowOrigin, AccessControlMaxAge, AccessControlRequestMethod};
+
+/// Describes how the `Router` answers CORS preflight requests, which are the `OPTIONS` requests
+/// that a browser sends before making a cross-origin request. The `Access-Control-Allow-Methods`
+/// header of the response is derived from the routes defined for the requested path.
+///
+/// Only the preflight request is answered by the `Router`. Responses to the cross-origin requests
+/// which follow still need an `Access-Control-Allow-Origin` header added by the application.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Method, Response};
+/// # use hyper::header::{AccessControlAllowMethods, AccessControlAllowOrigin,
+/// #                     AccessControlRequestMethod, Origin};
+/// # use gotham::router::CorsPolicy;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, Response) {
+/// #   (state, Response::new())
+/// # }
+/// #
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.set_cors_policy(
+///         CorsPolicy::new()
+///             .with_allowed_origins(vec!["https://example.com".to_owned()])
+///             .with_max_age(3600),
+///     );
+///
+///     route.get("/users").to(handler);
+///     route.post("/users").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server.client()
+///     .build_request(Method::Options, "https://api.example.com/users")
+///     .with_header(Origin::new("https", "example.com", None))
+///     .with_header(AccessControlRequestMethod(Method::Post))
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(
+///     response.headers().get::<AccessControlAllowOrigin>(),
+///     Some(&AccessControlAllowOrigin::Value("https://example.com".to_owned()))
+/// );
+/// assert_eq!(
+///     response.headers().get::<AccessControlAllowMethods>(),
+///     Some(&AccessControlAllowMethods(vec![Method::Get, Method::Post, Method::Options]))
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CorsPolicy {
+    origins: Option<Vec<String>>,
+    headers: Option<Vec<String>>,
+    max_age: Option<u32>,
+    credentials: bool,
+}
+
+impl CorsPolicy {
+    /// Creates a `CorsPolicy` which allows any origin, and allows any request headers which a
+    /// preflight request asks for.
+    pub fn new() -> CorsPolicy {
+        CorsPolicy {
+            origins: None,
+            headers: None,
+            max_age: None,
+            credentials: false,
+        }
+    }
+
+    /// Restricts the origins which are allowed to make cross-origin requests, such as
+    /// `https://example.com`. Preflight requests from any other origin are answered without CORS
+    /// headers, so the browser won't make the request.
+    pub fn with_allowed_origins(self, origins: Vec<String>) -> CorsPolicy {
+        CorsPolicy {
+            origins: Some(origins),
+            ..self
+        }
+    }
+
+    /// Restricts the request headers which cross-origin requests are allowed to use.
+    pub fn with_allowed_headers(self, headers: Vec<String>) -> CorsPolicy {
+        CorsPolicy {
+            headers: Some(headers),
+            ..self
+        }
+    }
+
+    /// Allows the browser to cache the response to a preflight request for `seconds`.
+    pub fn with_max_age(self, seconds: u32) -> CorsPolicy {
+        CorsPolicy {
+            max_age: Some(seconds),
+            ..self
+        }
+    }
+
+    /// Allows cross-origin requests to include credentials, such as cookies.
+    ///
+    /// Credentials are only allowed for the origins given to `with_allowed_origins`, so that other
+    /// sites can't make requests on behalf of the user. Without them, every preflight request is
+    /// answered without CORS headers.
+    pub fn with_credentials(self, credentials: bool) -> CorsPolicy {
+        CorsPolicy {
+            credentials,
+            ..self
+        }
+    }
+
+    /// Adds the CORS headers to the response for an `OPTIONS` request, if it's a preflight request
+    /// from an allowed origin. `allow` holds the methods of the routes at the requested path.
+    pub(crate) fn apply_preflight(
+        &self,
+        request: &Headers,
+        allow: &[Method],
+        headers: &mut Headers,
+    ) {
+        if !request.has::<AccessControlRequestMethod>() {
+            return;
+        }
+
+        let origin = match raw_value(request, "Origin") {
+            Some(origin) => origin,
+            None => return,
+        };
+
+        match self.origins {
+            Some(ref origins) if !origins.iter().any(|o| o == origin) => {
+                trace!(" preflight request from disallowed origin `{}`", origin);
+                return;
+            }
+            Some(_) => {
+                headers.set(AccessControlAllowOrigin::Value(origin.to_owned()));
+                headers.set_raw("Vary", "Origin");
+            }
+            None if self.credentials => {
+                trace!(" preflight request with credentials, but no allowed origins");
+                return;
+            }
+            None => headers.set(AccessControlAllowOrigin::Any),
+        }
+
+        headers.set(AccessControlAllowMethods(allow.to_vec()));
+
+        let allowed_headers = match self.headers {
+            Some(ref allowed) => Some(allowed.join(", ")),
+            None => raw_value(request, "Access-Control-Request-Headers").map(|h| h.to_owned()),
+        };
+        if let Some(allowed_headers) = allowed_headers {
+            headers.set_raw("Access-Control-Allow-Headers", allowed_headers);
+        }
+
+        if let Some(max_age) = self.max_age {
+            headers.set(AccessControlMaxAge(max_age));
+        }
+
+        if self.credentials {
+            headers.set(AccessControlAllowCredentials);
+        }
+    }
+}
+
+impl Default for CorsPolicy {
+    fn default() -> CorsPolicy {
+        CorsPolicy::new()
+    }
+}
+
+fn raw_value<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+    headers
+        .get_raw(name)
+        .and_then(|raw| raw.one())
+        .and_then(|value| str::from_utf8(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preflight(origin: &str) -> Headers {
+        let mut headers = Headers::new();
+        headers.set_raw("Origin", origin.to_owned());
+        headers.set(AccessControlRequestMethod(Method::Put));
+        headers.set_raw("Access-Control-Request-Headers", "content-type");
+        headers
+    }
+
+    #[test]
+    fn any_origin_is_allowed_by_default() {
+        let mut headers = Headers::new();
+        CorsPolicy::new().apply_preflight(
+            &preflight("https://example.com"),
+            &[Method::Get, Method::Put],
+            &mut headers,
+        );
+
+        assert_eq!(
+            headers.get::<AccessControlAllowOrigin>(),
+            Some(&AccessControlAllowOrigin::Any)
+        );
+        assert_eq!(
+            headers.get::<AccessControlAllowMethods>(),
+            Some(&AccessControlAllowMethods(vec![Method::Get, Method::Put]))
+        );
+        assert_eq!(
+            raw_value(&headers, "Access-Control-Allow-Headers"),
+            Some("content-type")
+        );
+        assert!(!headers.has::<AccessControlMaxAge>());
+    }
+
+    #[test]
+    fn disallowed_origin_gets_no_headers() {
+        let policy = CorsPolicy::new().with_allowed_origins(vec!["https://example.com".to_owned()]);
+
+        let mut headers = Headers::new();
+        policy.apply_preflight(&preflight("https://evil.com"), &[Method::Get], &mut headers);
+        assert_eq!(headers.len(), 0);
+
+        policy.apply_preflight(&preflight("https://example.com"), &[Method::Get], &mut headers);
+        assert_eq!(
+            headers.get::<AccessControlAllowOrigin>(),
+            Some(&AccessControlAllowOrigin::Value("https://example.com".to_owned()))
+        );
+        assert_eq!(raw_value(&headers, "Vary"), Some("Origin"));
+    }
+
+    #[test]
+    fn credentials_echo_the_origin() {
+        let policy = CorsPolicy::new()
+            .with_allowed_origins(vec!["https://example.com".to_owned()])
+            .with_credentials(true)
+            .with_allowed_headers(vec!["x-token".to_owned(), "content-type".to_owned()])
+            .with_max_age(600);
+
+        let mut headers = Headers::new();
+        policy.apply_preflight(&preflight("https://example.com"), &[Method::Get], &mut headers);
+
+        assert_eq!(
+            headers.get::<AccessControlAllowOrigin>(),
+            Some(&AccessControlAllowOrigin::Value("https://example.com".to_owned()))
+        );
+        assert!(headers.has::<AccessControlAllowCredentials>());
+        assert_eq!(
+            raw_value(&headers, "Access-Control-Allow-Headers"),
+            Some("x-token, content-type")
+        );
+        assert_eq!(
+            headers.get::<AccessControlMaxAge>(),
+            Some(&AccessControlMaxAge(600))
+        );
+    }
+
+    #[test]
+    fn credentials_require_allowed_origins() {
+        let policy = CorsPolicy::new().with_credentials(true);
+
+        let mut headers = Headers::new();
+        policy.apply_preflight(&preflight("https://evil.com"), &[Method::Get], &mut headers);
+        assert_eq!(headers.len(), 0);
+    }
+
+    #[test]
+    fn non_preflight_requests_are_ignored() {
+        let mut request = Headers::new();
+        request.set_raw("Origin", "https://example.com");
+
+        let mut headers = Headers::new();
+        CorsPolicy::new().apply_preflight(&request, &[Method::Get], &mut headers);
+        assert_eq!(headers.len(), 0);
+    }
+}
@@ -0,0 +1,125 @@
+//! Defines `RouterBuildError`, which describes why `try_build_router` was unable to build a
+//! `Router`.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use router::conflict::RouteConflict;
+
+/// The problems found while building a `Router` with `try_build_router`. Every offending route is
+/// listed, rather than only the first to be found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouterBuildError {
+    errors: Vec<RouteError>,
+}
+
+impl RouterBuildError {
+    pub(crate) fn new(errors: Vec<RouteError>) -> RouterBuildError {
+        RouterBuildError { errors }
+    }
+
+    /// The problems with the routes, in the order that they were found.
+    pub fn errors(&self) -> &[RouteError] {
+        &self.errors
+    }
+}
+
+impl Display for RouterBuildError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "unable to build router:")?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RouterBuildError {
+    fn description(&self) -> &str {
+        "unable to build router"
+    }
+}
+
+/// A problem with the route at a single path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteError {
+    path: String,
+    reason: RouteErrorReason,
+}
+
+impl RouteError {
+    pub(crate) fn new(path: String, reason: RouteErrorReason) -> RouteError {
+        RouteError { path, reason }
+    }
+
+    /// The path pattern of the offending route, as it would be given to the router builder.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Why the route is invalid.
+    pub fn reason(&self) -> &RouteErrorReason {
+        &self.reason
+    }
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+/// Describes why a route is invalid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RouteErrorReason {
+    /// The regex of a constrained segment (`:name:regex`) could not be compiled.
+    InvalidRegex {
+        /// The regex, as it appears in the path.
+        regex: String,
+        /// The error reported by the `regex` crate.
+        message: String,
+    },
+
+    /// Another route was added to a path which delegates to a `Router`, or the path was delegated
+    /// after a route was added to it.
+    DelegatedWithRoutes,
+
+    /// A route was added beneath a path which delegates to a `Router`, or the path was delegated
+    /// after a route was added beneath it.
+    DelegatedWithChildren,
+
+    /// The name given to the route has already been given to another route.
+    DuplicateName(String),
+
+    /// A fallback route was added to a scope which already has one.
+    DuplicateFallback,
+
+    /// The route conflicts with another, and the builder was set to `RouteValidation::Fail`.
+    Conflict(RouteConflict),
+}
+
+impl Display for RouteErrorReason {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            RouteErrorReason::InvalidRegex {
+                ref regex,
+                ref message,
+            } => write!(f, "invalid regex `{}`: {}", regex, message),
+            RouteErrorReason::DelegatedWithRoutes => write!(
+                f,
+                "a path which delegates to another router must not have other routes"
+            ),
+            RouteErrorReason::DelegatedWithChildren => write!(
+                f,
+                "a path which delegates to another router must not have routes beneath it"
+            ),
+            RouteErrorReason::DuplicateName(ref name) => {
+                write!(f, "route name `{}` is defined more than once", name)
+            }
+            RouteErrorReason::DuplicateFallback => {
+                write!(f, "a fallback route is defined more than once")
+            }
+            RouteErrorReason::Conflict(ref conflict) => write!(f, "{}", conflict),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Defines `RouteInfo`, which describes the routes of a `Router`.
+
+use std::any::type_name;
+use std::fmt::{self, Display, Formatter};
+
+use hyper::Method;
+
+use extractor::{NoopPathExtractor, NoopQueryStringExtractor};
+use router::route::{Delegation, Route};
+
+/// Describes a single route of a `Router`, as returned by `Router::routes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteInfo {
+    path: String,
+    names: Vec<String>,
+    methods: Option<Vec<Method>>,
+    delegated: bool,
+    fallback: bool,
+    path_extractor: &'static str,
+    query_string_extractor: &'static str,
+}
+
+impl RouteInfo {
+    pub(crate) fn new(path: String, names: &[String], route: &Route) -> RouteInfo {
+        RouteInfo {
+            path,
+            names: names.to_vec(),
+            methods: route.methods(),
+            delegated: route.delegation() == Delegation::External,
+            fallback: false,
+            path_extractor: route.path_extractor_name(),
+            query_string_extractor: route.query_string_extractor_name(),
+        }
+    }
+
+    /// Describes the fallback route of the scope at `path`. See `DrawRoutes::fallback`.
+    pub(crate) fn fallback(path: String, route: &Route) -> RouteInfo {
+        RouteInfo {
+            fallback: true,
+            ..RouteInfo::new(path, &[], route)
+        }
+    }
+
+    /// Moves a route of a delegated `Router` beneath the path which it is delegated from.
+    pub(crate) fn with_prefix(self, prefix: &str) -> RouteInfo {
+        let path = match (prefix, self.path.as_str()) {
+            (prefix, "/") => prefix.to_owned(),
+            ("/", path) => path.to_owned(),
+            (prefix, path) => format!("{}{}", prefix, path),
+        };
+
+        RouteInfo { path, ..self }
+    }
+
+    /// The full path pattern of the route, as it would be given to the router builder, such as
+    /// `/users/:id:[0-9]+` or `/assets/*`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The names given to the path of the route, for use with `gotham::router::url_for`.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The request methods which the route accepts, or `None` if it doesn't consider the request
+    /// method (such as a route which delegates to another `Router`).
+    pub fn methods(&self) -> Option<&[Method]> {
+        self.methods.as_ref().map(|methods| methods.as_slice())
+    }
+
+    /// Whether the route delegates requests to another `Router`. The routes of the other `Router`
+    /// are also listed by `Router::routes`, beneath the path of the delegating route.
+    pub fn is_delegated(&self) -> bool {
+        self.delegated
+    }
+
+    /// Whether the route is the fallback of the scope at its path, which answers requests for paths
+    /// beneath the scope that match no other route.
+    pub fn is_fallback(&self) -> bool {
+        self.fallback
+    }
+
+    /// The type name of the `PathExtractor` used by the route.
+    pub fn path_extractor(&self) -> &'static str {
+        self.path_extractor
+    }
+
+    /// The type name of the `QueryStringExtractor` used by the route.
+    pub fn query_string_extractor(&self) -> &'static str {
+        self.query_string_extractor
+    }
+
+    fn methods_column(&self) -> String {
+        match self.methods {
+            Some(ref methods) => methods
+                .iter()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            None => "*".to_owned(),
+        }
+    }
+
+    fn extractors_column(&self) -> String {
+        if self.delegated {
+            return "(delegated)".to_owned();
+        }
+
+        let mut extractors = vec![];
+        if self.fallback {
+            extractors.push("(fallback)");
+        }
+        if self.path_extractor != type_name::<NoopPathExtractor>() {
+            extractors.push(self.path_extractor);
+        }
+        if self.query_string_extractor != type_name::<NoopQueryStringExtractor>() {
+            extractors.push(self.query_string_extractor);
+        }
+
+        extractors.join(", ")
+    }
+}
+
+/// Writes the routes as a table with a row per route, similar to `rake routes`.
+pub(crate) fn write_table(f: &mut Formatter, routes: &[RouteInfo]) -> fmt::Result {
+    let header = ["NAME", "METHODS", "PATH", "EXTRACTORS"];
+    let rows = routes
+        .iter()
+        .map(|route| {
+            [
+                route.names.join(","),
+                route.methods_column(),
+                route.path.clone(),
+                route.extractors_column(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = header.iter().map(|h| h.len()).collect::<Vec<_>>();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = header.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+    for row in Some(&header[..]).into_iter().chain(rows.iter().map(|r| &r[..])) {
+        let mut line = String::new();
+        for (cell, width) in row.iter().zip(widths.iter()) {
+            line.push_str(&format!("{:width$}  ", cell, width = width));
+        }
+        writeln!(f, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+impl Display for RouteInfo {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.methods_column(), self.path)
+    }
+}
@@ -5,32 +5,85 @@ pub mod tree;
 pub mod route;
 pub mod response;
 pub mod non_match;
-
+mod conflict;
+mod cors;
+mod error;
+mod info;
+mod path_policy;
+mod url_for;
+
+pub use self::conflict::RouteConflict;
+pub use self::cors::CorsPolicy;
+pub use self::error::{RouteError, RouteErrorReason, RouterBuildError};
+pub use self::info::RouteInfo;
+pub use self::path_policy::{PathNormalization, PathPolicy, RedirectStatus};
+pub use self::url_for::{url_for, UrlForError};
+
+use std::fmt::{self, Display, Formatter};
 use std::io;
 use std::sync::Arc;
 
 use futures::{future, Future};
-use hyper::{Response, StatusCode};
+use hyper::{Headers, Method, Response, StatusCode};
 use hyper::header::Allow;
 
 use handler::{Handler, HandlerFuture, IntoResponse, NewHandler};
+use http::PercentDecoded;
+use http::cookie::{CookieJar, Key};
 use http::request::path::RequestPathSegments;
 use http::response::create_response;
 use router::response::finalizer::ResponseFinalizer;
 use router::route::{Delegation, Route};
 use router::tree::{SegmentMapping, Tree};
-use state::{request_id, State};
+use router::url_for::NamedRoutes;
+use state::{request_id, FromState, State};
 
 struct RouterData {
     tree: Tree,
     response_finalizer: ResponseFinalizer,
+    named_routes: NamedRoutes,
+    settings: RouterSettings,
 }
 
 impl RouterData {
-    fn new(tree: Tree, response_finalizer: ResponseFinalizer) -> RouterData {
+    fn new(
+        tree: Tree,
+        response_finalizer: ResponseFinalizer,
+        named_routes: NamedRoutes,
+        settings: RouterSettings,
+    ) -> RouterData {
         RouterData {
             tree,
             response_finalizer,
+            named_routes,
+            settings,
+        }
+    }
+}
+
+/// The behaviour of a `Router` which is chosen via the `RouterBuilder`, rather than by the routes.
+pub(crate) struct RouterSettings {
+    /// Whether `OPTIONS` requests are answered for paths which have no `OPTIONS` route.
+    pub(crate) automatic_options: bool,
+
+    /// Used to answer CORS preflight requests when `OPTIONS` requests are answered automatically.
+    pub(crate) cors_policy: Option<CorsPolicy>,
+
+    /// Applied to every request, unless the `Router` has been delegated to by another `Router`.
+    pub(crate) path_policy: PathPolicy,
+
+    /// Used for signed and private cookies, unless the `Router` has been delegated to by another
+    /// `Router`.
+    pub(crate) cookie_key: Option<Key>,
+}
+
+impl Default for RouterSettings {
+    fn default() -> RouterSettings {
+        RouterSettings {
+            automatic_options: true,
+            cors_policy: None,
+            path_policy: PathPolicy::default(),
+            cookie_key: None,
         }
     }
 }
@@ -68,9 +121,38 @@ impl Handler for Router {
     fn handle(self, mut state: State) -> Box<HandlerFuture> {
         trace!("[{}] starting", request_id(&state));
 
+        // A delegated `Router` leaves the named routes of the parent `Router` in place, as they
+        // include its own routes with the delegation prefix.
+        if !state.has::<NamedRoutes>() {
+            state.put(self.data.named_routes.clone());
+        }
+
+        // The `CookieJar` is shared with delegated `Router` instances in the same way, and its
+        // changes are written to the response once, by the `Router` which created it.
+        let owns_cookie_jar = !state.has::<CookieJar>();
+        if owns_cookie_jar {
+            let key = self.data.settings.cookie_key.clone();
+            let jar = CookieJar::from_headers(Headers::borrow_from(&state), key);
+            state.put(jar);
+        }
+
+        // Likewise, the `PathPolicy` of the parent `Router` has already been applied to the
+        // request, and is used by the delegated `Router` when traversing its tree.
+        if !state.has::<PathPolicy>() {
+            let path_policy = self.data.settings.path_policy.clone();
+            if let Some(res) = path_policy.refuse(&state) {
+                let f = Box::new(future::ok((state, res)));
+                return self.finalize_response(f, owns_cookie_jar);
+            }
+            state.put(path_policy);
+        }
+        let case_insensitive = PathPolicy::borrow_from(&state).case_insensitive();
+
         let future = match state.try_take::<RequestPathSegments>() {
             Some(rps) => {
-                if let Some((_, leaf, sp, sm)) = self.data.tree.traverse(&rps.segments()) {
+                let segments = rps.segments();
+                let traversal = self.data.tree.traverse(&segments, case_insensitive);
+                if let Some((_, leaf, sp, sm)) = traversal {
                     match leaf.select_route(&state) {
                         Ok(route) => match route.delegation() {
                             Delegation::External => {
@@ -90,18 +172,33 @@ impl Handler for Router {
                         Err(non_match) => {
                             let (status, allow) = non_match.deconstruct();
 
-                            trace!("[{}] responding with error status", request_id(&state));
-                            let mut res = create_response(&state, status, None);
-                            if let StatusCode::MethodNotAllowed = status {
-                                res.headers_mut().set(Allow(allow));
+                            if status == StatusCode::NotFound {
+                                self.not_found(state, &segments, case_insensitive)
+                            } else {
+                                let res = if self.answers_options(&state, status) {
+                                    trace!(
+                                        "[{}] responding to OPTIONS request",
+                                        request_id(&state)
+                                    );
+                                    self.options_response(&state, allow)
+                                } else {
+                                    trace!(
+                                        "[{}] responding with error status",
+                                        request_id(&state)
+                                    );
+                                    let mut res = create_response(&state, status, None);
+                                    if let StatusCode::MethodNotAllowed = status {
+                                        res.headers_mut().set(Allow(allow));
+                                    }
+                                    res
+                                };
+                                Box::new(future::ok((state, res)))
                             }
-                            Box::new(future::ok((state, res)))
                         }
                     }
                 } else {
                     trace!("[{}] did not find routable node", request_id(&state));
-                    let res = create_response(&state, StatusCode::NotFound, None);
-                    Box::new(future::ok((state, res)))
+                    self.not_found(state, &segments, case_insensitive)
                 }
             }
             None => {
@@ -111,7 +208,7 @@ impl Handler for Router {
             }
         };
 
-        self.finalize_response(future)
+        self.finalize_response(future, owns_cookie_jar)
     }
 }
 
@@ -120,17 +217,74 @@ impl Router {
     #[deprecated(since = "0.2.0",
                  note = "use the new `gotham::router::builder` API to construct a Router")]
     pub fn new(tree: Tree, response_finalizer: ResponseFinalizer) -> Router {
-        Router::internal_new(tree, response_finalizer)
+        Router::internal_new(
+            tree,
+            response_finalizer,
+            NamedRoutes::default(),
+            RouterSettings::default(),
+        )
     }
 
     /// Same as `new`, but private and not deprecated.
-    fn internal_new(tree: Tree, response_finalizer: ResponseFinalizer) -> Router {
-        let router_data = RouterData::new(tree, response_finalizer);
+    fn internal_new(
+        tree: Tree,
+        response_finalizer: ResponseFinalizer,
+        named_routes: NamedRoutes,
+        settings: RouterSettings,
+    ) -> Router {
+        let router_data = RouterData::new(tree, response_finalizer, named_routes, settings);
         Router {
             data: Arc::new(router_data),
         }
     }
 
+    /// Dispatches a request which matched no route to the fallback route chosen as described by
+    /// `DrawRoutes::fallback`, or responds with `404 Not Found` when there is none.
+    fn not_found(
+        &self,
+        state: State,
+        segments: &[&PercentDecoded],
+        case_insensitive: bool,
+    ) -> Box<HandlerFuture> {
+        let fallback = self.data
+            .tree
+            .select_fallback(segments, case_insensitive, &state);
+
+        match fallback {
+            Some((route, sm)) => {
+                trace!("[{}] dispatching to fallback route", request_id(&state));
+                self.dispatch(state, sm, route)
+            }
+            None => {
+                trace!("[{}] responding with not found", request_id(&state));
+                let res = create_response(&state, StatusCode::NotFound, None);
+                Box::new(future::ok((state, res)))
+            }
+        }
+    }
+
+    /// Determines if a request which matched no route at a routable path is an `OPTIONS` request
+    /// that the `Router` answers itself, as no `OPTIONS` route was defined for the path.
+    fn answers_options(&self, state: &State, status: StatusCode) -> bool {
+        self.data.settings.automatic_options && status == StatusCode::MethodNotAllowed
+            && *Method::borrow_from(state) == Method::Options
+    }
+
+    /// Creates the response to an `OPTIONS` request for a path whose routes accept the methods in
+    /// `allow`, including the CORS headers for a preflight request when a `CorsPolicy` is set.
+    fn options_response(&self, state: &State, mut allow: Vec<Method>) -> Response {
+        if !allow.contains(&Method::Options) {
+            allow.push(Method::Options);
+        }
+
+        let mut res = create_response(state, StatusCode::Ok, None);
+        if let Some(ref cors_policy) = self.data.settings.cors_policy {
+            cors_policy.apply_preflight(Headers::borrow_from(state), &allow, res.headers_mut());
+        }
+        res.headers_mut().set(Allow(allow));
+        res
+    }
+
     fn dispatch(
         &self,
         mut state: State,
@@ -168,7 +322,51 @@ impl Router {
         }
     }
 
-    fn finalize_response(&self, result: Box<HandlerFuture>) -> Box<HandlerFuture> {
+    /// Describes each route of this `Router`, including the fallback routes of its scopes and the
+    /// routes of any `Router` it delegates to. The `Display` implementation of `Router` prints the
+    /// same information as a table.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Method, Response};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// #
+    /// # fn handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// let router = build_simple_router(|route| {
+    ///     route.get("/users/:id:[0-9]+").name("user_show").to(handler);
+    /// });
+    ///
+    /// let routes = router.routes();
+    /// assert_eq!(routes[0].path(), "/users/:id:[0-9]+");
+    /// assert_eq!(routes[0].methods(), Some(&[Method::Get][..]));
+    /// assert_eq!(routes[0].names(), &["user_show".to_owned()]);
+    ///
+    /// println!("{}", router);
+    /// // NAME       METHODS  PATH               EXTRACTORS
+    /// // user_show  GET      /users/:id:[0-9]+
+    /// # }
+    /// ```
+    pub fn routes(&self) -> Vec<RouteInfo> {
+        self.data.tree.describe_routes()
+    }
+
+    /// The named routes of this `Router`, for a parent `Router` which delegates to it.
+    pub(crate) fn named_routes(&self) -> NamedRoutes {
+        self.data.named_routes.clone()
+    }
+
+    fn finalize_response(
+        &self,
+        result: Box<HandlerFuture>,
+        owns_cookie_jar: bool,
+    ) -> Box<HandlerFuture> {
         let response_finalizer = self.data.response_finalizer.clone();
         let f = result
             .or_else(|(state, err)| {
@@ -181,8 +379,14 @@ impl Router {
                 let response = err.into_response(&state);
                 future::ok((state, response))
             })
-            .and_then(move |(state, res)| {
+            .and_then(move |(state, mut res)| {
                 trace!("[{}] handler complete", request_id(&state));
+                if owns_cookie_jar {
+                    if let Some(jar) = state.try_borrow::<CookieJar>() {
+                        jar.write_changes(res.headers_mut());
+                    }
+                }
+
                 response_finalizer.finalize(state, res)
             });
 
@@ -190,6 +394,12 @@ impl Router {
     }
 }
 
+impl Display for Router {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        info::write_table(f, &self.routes())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
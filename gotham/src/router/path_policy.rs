@@ -0,0 +1,210 @@
+//! Defines `PathPolicy`, which determines how the `Router` treats request paths that are not in
+//! their canonical form.
+
+use hyper::{Response, StatusCode, Uri};
+
+use http::response::{create_response, set_redirect_headers};
+use state::{request_id, FromState, State, StateData};
+
+/// Describes how the `Router` treats request paths which are not canonical, and how the literal
+/// segments of routes are compared with the request path.
+///
+/// A canonical path has no empty segments, so it has no trailing slash and no repeated slashes.
+/// The canonical form of `/users//42/` is `/users/42`. The root path `/` is always canonical.
+///
+/// The `PathPolicy` of the outermost `Router` is used for the whole request, so a `Router` which
+/// is delegated to inherits the policy of the `Router` delegating to it.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use hyper::header::Location;
+/// # use gotham::router::{PathNormalization, PathPolicy, RedirectStatus};
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, Response) {
+/// #   (state, Response::new())
+/// # }
+/// #
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.set_path_policy(
+///         PathPolicy::new()
+///             .with_normalization(PathNormalization::Redirect(RedirectStatus::MovedPermanently))
+///             .with_case_insensitive_literals(true),
+///     );
+///
+///     route.get("/users/:id").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server.client()
+///     .get("https://example.com/Users//42/?expand=true")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), StatusCode::MovedPermanently);
+/// assert_eq!(
+///     response.headers().get::<Location>(),
+///     Some(&Location::new("/Users/42?expand=true"))
+/// );
+///
+/// let response = test_server.client()
+///     .get("https://example.com/Users/42")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), StatusCode::Ok);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathPolicy {
+    normalization: PathNormalization,
+    case_insensitive: bool,
+}
+
+/// Determines what the `Router` does with a request for a path which is not canonical.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathNormalization {
+    /// The request is routed as though its path was canonical, so `/users/` and `/users` are
+    /// dispatched to the same route. This is the default.
+    Ignore,
+
+    /// The request is answered with `404 Not Found`.
+    Strict,
+
+    /// The request is answered with a redirect to the canonical path, keeping the query string.
+    Redirect(RedirectStatus),
+}
+
+/// The status of the redirect to the canonical path, used by `PathNormalization::Redirect`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RedirectStatus {
+    /// `301 Moved Permanently`, which clients may follow with a `GET` request regardless of the
+    /// original request method.
+    MovedPermanently,
+
+    /// `308 Permanent Redirect`, which clients follow with the original request method and body.
+    PermanentRedirect,
+}
+
+impl RedirectStatus {
+    fn status(self) -> StatusCode {
+        match self {
+            RedirectStatus::MovedPermanently => StatusCode::MovedPermanently,
+            RedirectStatus::PermanentRedirect => StatusCode::PermanentRedirect,
+        }
+    }
+}
+
+impl PathPolicy {
+    /// Creates a `PathPolicy` which routes paths as though they were canonical, and compares
+    /// literal segments exactly.
+    pub fn new() -> PathPolicy {
+        PathPolicy {
+            normalization: PathNormalization::Ignore,
+            case_insensitive: false,
+        }
+    }
+
+    /// Sets what is done with requests for paths which are not canonical.
+    pub fn with_normalization(self, normalization: PathNormalization) -> PathPolicy {
+        PathPolicy {
+            normalization,
+            ..self
+        }
+    }
+
+    /// Sets whether the literal segments of routes match request path segments which differ from
+    /// them only in ASCII case, so that `/users` matches a request for `/Users`. Dynamic and
+    /// constrained segments are not affected.
+    pub fn with_case_insensitive_literals(self, case_insensitive: bool) -> PathPolicy {
+        PathPolicy {
+            case_insensitive,
+            ..self
+        }
+    }
+
+    /// Whether literal segments are compared regardless of ASCII case.
+    pub(crate) fn case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Creates the response to a request whose path is refused by this policy, or `None` when the
+    /// request should be routed.
+    pub(crate) fn refuse(&self, state: &State) -> Option<Response> {
+        if self.normalization == PathNormalization::Ignore {
+            return None;
+        }
+
+        let uri = Uri::borrow_from(state);
+        let canonical = canonical_path(uri.path());
+        if canonical == uri.path() {
+            return None;
+        }
+
+        match self.normalization {
+            PathNormalization::Redirect(redirect) => {
+                let location = match uri.query() {
+                    Some(query) => format!("{}?{}", canonical, query),
+                    None => canonical,
+                };
+                trace!("[{}] redirecting to `{}`", request_id(state), location);
+
+                let mut res = Response::new().with_status(redirect.status());
+                set_redirect_headers(state, &mut res, location);
+                Some(res)
+            }
+            _ => {
+                trace!("[{}] refusing path which is not canonical", request_id(state));
+                Some(create_response(state, StatusCode::NotFound, None))
+            }
+        }
+    }
+}
+
+impl Default for PathPolicy {
+    fn default() -> PathPolicy {
+        PathPolicy::new()
+    }
+}
+
+impl StateData for PathPolicy {}
+
+/// Removes the empty segments from `path`.
+fn canonical_path(path: &str) -> String {
+    let segments = path.split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    format!("/{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_path_tests() {
+        assert_eq!(canonical_path("/"), "/");
+        assert_eq!(canonical_path(""), "/");
+        assert_eq!(canonical_path("/users"), "/users");
+        assert_eq!(canonical_path("/users/"), "/users");
+        assert_eq!(canonical_path("//users//42/"), "/users/42");
+    }
+
+    #[test]
+    fn canonical_paths_are_never_refused() {
+        let mut state = State::new();
+        state.put("/users/42?a=b".parse::<Uri>().unwrap());
+
+        let strict = PathPolicy::new().with_normalization(PathNormalization::Strict);
+        assert!(strict.refuse(&state).is_none());
+
+        state.put("/users/42/".parse::<Uri>().unwrap());
+        assert!(PathPolicy::new().refuse(&state).is_none());
+    }
+}
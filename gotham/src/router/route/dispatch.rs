@@ -1,8 +1,9 @@
 //! Defines the route `Dispatcher` and supporting types.
 
 use std::panic::RefUnwindSafe;
-use futures::future;
+use futures::{future, Future};
 
+use extractor::BodyExtraction;
 use handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
 use pipeline::chain::PipelineHandleChain;
 use pipeline::set::PipelineSet;
@@ -24,6 +25,7 @@ where
     new_handler: H,
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
+    body_extraction: Option<BodyExtraction>,
 }
 
 impl<H, C, P> DispatcherImpl<H, C, P>
@@ -45,6 +47,17 @@ where
             new_handler,
             pipeline_chain,
             pipelines,
+            body_extraction: None,
+        }
+    }
+
+    /// Sets how the request body is extracted once the `pipeline_chain` is complete, before the
+    /// `Handler` is called, if at all. The body is read after the middleware of the pipelines, so
+    /// that a `BodyLimitMiddleware` limits the body which is read.
+    pub(crate) fn with_body_extraction(self, body_extraction: Option<BodyExtraction>) -> Self {
+        DispatcherImpl {
+            body_extraction,
+            ..self
         }
     }
 }
@@ -60,8 +73,13 @@ where
         match self.new_handler.new_handler() {
             Ok(h) => {
                 trace!("[{}] cloning handler", request_id(&state));
-                self.pipeline_chain
-                    .call(&self.pipelines, state, move |state| h.handle(state))
+                let body_extraction = self.body_extraction;
+                self.pipeline_chain.call(&self.pipelines, state, move |state| {
+                    match body_extraction {
+                        Some(body_extraction) => handle_with_body(body_extraction, state, h),
+                        None => h.handle(state),
+                    }
+                })
             }
             Err(e) => {
                 trace!("[{}] error cloning handler", request_id(&state));
@@ -71,6 +89,22 @@ where
     }
 }
 
+/// Extracts the request body, and then calls the `Handler` unless the body was refused.
+fn handle_with_body<H>(body_extraction: BodyExtraction, state: State, h: H) -> Box<HandlerFuture>
+where
+    H: Handler + 'static,
+{
+    let f = body_extraction.extract(state).then(move |result| match result {
+        Ok(state) => h.handle(state),
+        Err((state, res)) => {
+            trace!("[{}] request body was refused", request_id(&state));
+            Box::new(future::ok((state, res)))
+        }
+    });
+
+    Box::new(f)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
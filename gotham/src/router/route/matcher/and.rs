@@ -1,5 +1,7 @@
 //! Defines the type `AndRouteMatcher`
 
+use hyper::Method;
+
 use router::non_match::RouteNonMatch;
 use router::route::RouteMatcher;
 use state::State;
@@ -82,4 +84,22 @@ where
             (Err(e), Err(e1)) => Err(e.intersection(e1)),
         }
     }
+
+    fn methods(&self) -> Option<Vec<Method>> {
+        match (self.t.methods(), self.u.methods()) {
+            (Some(t), Some(u)) => Some(t.into_iter().filter(|m| u.contains(m)).collect()),
+            (Some(methods), None) | (None, Some(methods)) => Some(methods),
+            (None, None) => None,
+        }
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.t.is_method_only() && self.u.is_method_only()
+    }
+
+    fn captures(&self, state: &State) -> Vec<(String, String)> {
+        let mut captures = self.t.captures(state);
+        captures.extend(self.u.captures(state));
+        captures
+    }
 }
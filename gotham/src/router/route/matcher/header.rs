@@ -0,0 +1,150 @@
+//! Defines the `HeaderRouteMatcher`.
+
+use std::str;
+
+use hyper::StatusCode;
+use hyper::header::Headers;
+
+use router::non_match::RouteNonMatch;
+use router::route::RouteMatcher;
+use router::route::matcher::value::ValueMatch;
+use router::tree::regex::ConstrainedSegmentRegex;
+use state::{request_id, FromState, State};
+
+/// A `RouteMatcher` that succeeds when the `Request` has been made with a header of the given
+/// name, optionally requiring that its value is exactly a given string, or matches a regex. The
+/// header name is compared regardless of case. When the header is given more than once, the
+/// matcher succeeds if any of its values is accepted. A request without an accepted value is
+/// treated as `404 Not Found`, so that another route for the same path can be matched instead.
+///
+/// The builder helper `DefineSingleRoute::with_header` adds a matcher requiring an exact value to
+/// a route.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::header::Headers;
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::{HeaderRouteMatcher, RouteMatcher};
+/// #
+/// #   State::with_new(|state| {
+/// #
+/// let matcher = HeaderRouteMatcher::regex("X-Api-Version", "2(\\.[0-9]+)?");
+///
+/// let mut headers = Headers::new();
+/// headers.set_raw("X-Api-Version", "2.1");
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// let mut headers = Headers::new();
+/// headers.set_raw("X-Api-Version", "1");
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_err());
+///
+/// // No X-Api-Version header
+/// state.put(Headers::new());
+/// assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct HeaderRouteMatcher {
+    name: String,
+    value: ValueMatch,
+}
+
+impl HeaderRouteMatcher {
+    /// Creates a new `HeaderRouteMatcher` which accepts any value of the header, and so only
+    /// requires that the header is present.
+    pub fn present(name: &str) -> Self {
+        HeaderRouteMatcher {
+            name: name.to_owned(),
+            value: ValueMatch::Present,
+        }
+    }
+
+    /// Creates a new `HeaderRouteMatcher` which requires that the header has exactly the given
+    /// value.
+    pub fn equals(name: &str, value: &str) -> Self {
+        HeaderRouteMatcher {
+            name: name.to_owned(),
+            value: ValueMatch::Equals(value.to_owned()),
+        }
+    }
+
+    /// Creates a new `HeaderRouteMatcher` which requires that the whole value of the header
+    /// matches the given regex.
+    ///
+    /// # Panics
+    ///
+    /// If `regex` is not a valid regex.
+    pub fn regex(name: &str, regex: &str) -> Self {
+        HeaderRouteMatcher {
+            name: name.to_owned(),
+            value: ValueMatch::Regex(ConstrainedSegmentRegex::new(regex)),
+        }
+    }
+
+    /// The name of the header which this matcher considers.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl RouteMatcher for HeaderRouteMatcher {
+    /// Determines if the `Request` was made with an accepted value of the header.
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        let values = Headers::borrow_from(state)
+            .get_raw(&self.name)
+            .map(|raw| {
+                raw.iter()
+                    .filter_map(|line| str::from_utf8(line).ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(Vec::new);
+
+        if self.value.any(values) {
+            Ok(())
+        } else {
+            trace!(
+                "[{}] header `{}` is not {}",
+                request_id(&state),
+                self.name,
+                self.value
+            );
+            Err(RouteNonMatch::new(StatusCode::NotFound))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(matcher: &HeaderRouteMatcher, values: &[&str]) -> bool {
+        let mut state = State::new();
+        let mut headers = Headers::new();
+        for value in values {
+            headers.append_raw("x-api-version", value.to_string());
+        }
+        state.put(headers);
+        matcher.is_match(&state).is_ok()
+    }
+
+    #[test]
+    fn header_matcher_tests() {
+        let present = HeaderRouteMatcher::present("X-Api-Version");
+        assert!(is_match(&present, &["1"]));
+        assert!(!is_match(&present, &[]));
+
+        let equals = HeaderRouteMatcher::equals("X-Api-Version", "2");
+        assert!(is_match(&equals, &["2"]));
+        assert!(is_match(&equals, &["1", "2"]));
+        assert!(!is_match(&equals, &["1"]));
+        assert!(!is_match(&equals, &[]));
+    }
+}
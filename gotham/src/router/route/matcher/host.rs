@@ -0,0 +1,214 @@
+//! Defines the `HostRouteMatcher`.
+
+use hyper::{StatusCode, Uri};
+use hyper::header::{Headers, Host};
+
+use router::non_match::RouteNonMatch;
+use router::route::RouteMatcher;
+use state::{request_id, FromState, State};
+
+/// A `RouteMatcher` that succeeds when the `Request` has been made for a host matching a pattern.
+/// The host is taken from the `Host` header, or the request URI when it has no `Host` header, and
+/// is compared without its port and regardless of case. A request for any other host is treated
+/// as `404 Not Found`.
+///
+/// The pattern is a host name, where each label (the parts between the `.` separators) is one of:
+///
+/// * A literal, such as `api` in `api.example.com`, which must appear in the host.
+/// * A parameter, such as `:tenant` in `:tenant.example.com`, which matches a single label of the
+///   host. The labels captured by parameters are given to the `PathExtractor` of the route, in the
+///   same way as the dynamic segments of the request path.
+/// * A wildcard `*`, which may only be the first label, and matches one or more labels. The
+///   pattern `*.example.com` matches `www.example.com` and `a.b.example.com`, but not
+///   `example.com`.
+///
+/// The builder helper `DrawRoutes::host` applies this matcher to a group of routes.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::header::{Headers, Host};
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::{HostRouteMatcher, RouteMatcher};
+/// #
+/// #   State::with_new(|state| {
+/// #
+/// let matcher = HostRouteMatcher::new(":tenant.example.com");
+///
+/// let mut headers = Headers::new();
+/// headers.set(Host::new("acme.example.com", Some(8080)));
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// let mut headers = Headers::new();
+/// headers.set(Host::new("www.example.org", None));
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HostRouteMatcher {
+    pattern: String,
+    labels: Vec<HostLabel>,
+    wildcard: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum HostLabel {
+    Static(String),
+    Param(String),
+}
+
+impl HostRouteMatcher {
+    /// Creates a new `HostRouteMatcher` for the given pattern.
+    pub fn new(pattern: &str) -> Self {
+        let pattern = pattern.trim_end_matches('.').to_lowercase();
+
+        let mut labels = pattern.split('.').collect::<Vec<_>>();
+        let wildcard = labels.first() == Some(&"*");
+        if wildcard {
+            labels.remove(0);
+        }
+
+        let labels = labels
+            .into_iter()
+            .map(|label| {
+                if label.starts_with(':') {
+                    HostLabel::Param(label[1..].to_owned())
+                } else {
+                    HostLabel::Static(label.to_owned())
+                }
+            })
+            .collect();
+
+        HostRouteMatcher {
+            pattern,
+            labels,
+            wildcard,
+        }
+    }
+
+    /// The pattern which this matcher was created from.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Matches `host` against the pattern, returning the labels captured by its parameters.
+    fn match_host(&self, host: &str) -> Option<Vec<(String, String)>> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let host_labels = host.split('.').collect::<Vec<_>>();
+
+        let matches_len = if self.wildcard {
+            host_labels.len() > self.labels.len()
+        } else {
+            host_labels.len() == self.labels.len()
+        };
+
+        if !matches_len {
+            return None;
+        }
+
+        let offset = host_labels.len() - self.labels.len();
+        let mut captures = vec![];
+        for (label, host_label) in self.labels.iter().zip(&host_labels[offset..]) {
+            match *label {
+                HostLabel::Static(ref s) if s == host_label => (),
+                HostLabel::Static(_) => return None,
+                HostLabel::Param(ref name) => {
+                    captures.push((name.clone(), (*host_label).to_owned()))
+                }
+            }
+        }
+
+        Some(captures)
+    }
+
+    fn request_host(state: &State) -> Option<String> {
+        match Headers::borrow_from(state).get::<Host>() {
+            Some(host) => Some(host.hostname().to_owned()),
+            None => Uri::try_borrow_from(state).and_then(|uri| uri.host().map(|h| h.to_owned())),
+        }
+    }
+}
+
+impl RouteMatcher for HostRouteMatcher {
+    /// Determines if the `Request` was made for a host which matches the pattern.
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        match HostRouteMatcher::request_host(state) {
+            Some(ref host) if self.match_host(host).is_some() => Ok(()),
+            host => {
+                trace!(
+                    "[{}] host {:?} does not match pattern `{}`",
+                    request_id(&state),
+                    host,
+                    self.pattern
+                );
+                Err(RouteNonMatch::new(StatusCode::NotFound))
+            }
+        }
+    }
+
+    fn captures(&self, state: &State) -> Vec<(String, String)> {
+        HostRouteMatcher::request_host(state)
+            .and_then(|host| self.match_host(&host))
+            .unwrap_or_else(Vec::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(pattern: &str, host: &str) -> Option<Vec<(String, String)>> {
+        HostRouteMatcher::new(pattern).match_host(host)
+    }
+
+    #[test]
+    fn exact_hosts() {
+        assert_eq!(captures("api.example.com", "api.example.com"), Some(vec![]));
+        assert_eq!(captures("api.example.com", "API.Example.com."), Some(vec![]));
+        assert_eq!(captures("api.example.com", "www.example.com"), None);
+        assert_eq!(captures("api.example.com", "a.api.example.com"), None);
+        assert_eq!(captures("api.example.com", "example.com"), None);
+    }
+
+    #[test]
+    fn wildcard_subdomains() {
+        assert_eq!(captures("*.example.com", "www.example.com"), Some(vec![]));
+        assert_eq!(captures("*.example.com", "a.b.example.com"), Some(vec![]));
+        assert_eq!(captures("*.example.com", "example.com"), None);
+        assert_eq!(captures("*.example.com", "www.example.org"), None);
+    }
+
+    #[test]
+    fn captured_labels() {
+        assert_eq!(
+            captures(":tenant.:region.example.com", "acme.eu.example.com"),
+            Some(vec![
+                ("tenant".to_owned(), "acme".to_owned()),
+                ("region".to_owned(), "eu".to_owned()),
+            ])
+        );
+        assert_eq!(captures(":tenant.example.com", "example.com"), None);
+        assert_eq!(
+            captures("*.:tenant.example.com", "www.acme.example.com"),
+            Some(vec![("tenant".to_owned(), "acme".to_owned())])
+        );
+    }
+
+    #[test]
+    fn host_is_read_from_uri_without_header() {
+        State::with_new(|state| {
+            state.put(Headers::new());
+            state.put("http://api.example.com:8080/".parse::<Uri>().unwrap());
+
+            assert!(HostRouteMatcher::new("api.example.com").is_match(state).is_ok());
+            assert!(HostRouteMatcher::new("www.example.com").is_match(state).is_err());
+        });
+    }
+}
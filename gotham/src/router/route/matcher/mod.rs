@@ -4,10 +4,22 @@ pub mod any;
 pub mod and;
 pub mod accept;
 pub mod content_type;
+pub mod header;
+pub mod host;
+pub mod not;
+pub mod or;
+pub mod query_param;
+
+mod value;
 
 pub use self::any::AnyRouteMatcher;
 pub use self::and::AndRouteMatcher;
 pub use self::accept::AcceptHeaderRouteMatcher;
+pub use self::header::HeaderRouteMatcher;
+pub use self::host::HostRouteMatcher;
+pub use self::not::NotRouteMatcher;
+pub use self::or::OrRouteMatcher;
+pub use self::query_param::QueryParamRouteMatcher;
 
 use std::panic::RefUnwindSafe;
 
@@ -21,6 +33,25 @@ use router::non_match::RouteNonMatch;
 pub trait RouteMatcher: RefUnwindSafe {
     /// Determines if the `Request` meets pre-defined conditions.
     fn is_match(&self, state: &State) -> Result<(), RouteNonMatch>;
+
+    /// The request methods which this `RouteMatcher` can accept, or `None` if it doesn't consider
+    /// the request method. Used to describe the route via `Router::routes`.
+    fn methods(&self) -> Option<Vec<Method>> {
+        None
+    }
+
+    /// Whether this `RouteMatcher` considers nothing but the request method, and so matches every
+    /// request made with one of its `methods`. Used to find routes which can never be reached.
+    fn is_method_only(&self) -> bool {
+        false
+    }
+
+    /// Values which this `RouteMatcher` captures from the request, as pairs of names and values,
+    /// such as the labels of a host matched by `HostRouteMatcher`. They are given to the
+    /// `PathExtractor` of the route, along with the dynamic segments of the request path.
+    fn captures(&self, _state: &State) -> Vec<(String, String)> {
+        vec![]
+    }
 }
 
 /// Allow various types to represent themselves as a `RouteMatcher`
@@ -109,4 +140,12 @@ impl RouteMatcher for MethodOnlyRouteMatcher {
                 .with_allow_list(self.methods.as_slice()))
         }
     }
+
+    fn methods(&self) -> Option<Vec<Method>> {
+        Some(self.methods.clone())
+    }
+
+    fn is_method_only(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,185 @@
+//! Defines the type `NotRouteMatcher`
+
+use hyper::{Method, StatusCode};
+
+use router::non_match::RouteNonMatch;
+use router::route::RouteMatcher;
+use state::{request_id, State};
+
+/// Negates a `RouteMatcher`, so that a request is matched when the wrapped `RouteMatcher` does
+/// not match it.
+///
+/// When the wrapped `RouteMatcher` considers only the request method, such as a
+/// `MethodOnlyRouteMatcher`, a matching request is refused with `405 Method Not Allowed`, and the
+/// `Allow` list names every other standard method. Otherwise, a matching request is refused with
+/// `404 Not Found`, or the status given to `with_status`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::Method;
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::{RouteMatcher, MethodOnlyRouteMatcher,
+/// #                                        NotRouteMatcher};
+/// #
+/// #   State::with_new(|state| {
+/// #
+///   let matcher = NotRouteMatcher::new(MethodOnlyRouteMatcher::new(vec![Method::Trace]));
+///
+///   state.put(Method::Get);
+///   assert!(matcher.is_match(&state).is_ok());
+///
+///   state.put(Method::Trace);
+///   assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+pub struct NotRouteMatcher<T>
+where
+    T: RouteMatcher,
+{
+    t: T,
+    status: Option<StatusCode>,
+}
+
+impl<T> NotRouteMatcher<T>
+where
+    T: RouteMatcher,
+{
+    /// Creates a new `NotRouteMatcher`
+    pub fn new(t: T) -> Self {
+        NotRouteMatcher { t, status: None }
+    }
+
+    /// Sets the status which a request matched by the wrapped `RouteMatcher` is refused with,
+    /// such as `415 Unsupported Media Type` when negating a `ContentTypeHeaderRouteMatcher`.
+    pub fn with_status(self, status: StatusCode) -> Self {
+        NotRouteMatcher {
+            status: Some(status),
+            ..self
+        }
+    }
+
+    /// The standard methods which the wrapped `RouteMatcher` refuses, when it considers only the
+    /// request method.
+    fn complement_methods(&self) -> Option<Vec<Method>> {
+        if !self.t.is_method_only() {
+            return None;
+        }
+
+        self.t.methods().map(|methods| {
+            STANDARD_METHODS
+                .iter()
+                .filter(|m| !methods.contains(*m))
+                .cloned()
+                .collect()
+        })
+    }
+}
+
+const STANDARD_METHODS: [Method; 9] = [
+    Method::Options,
+    Method::Get,
+    Method::Post,
+    Method::Put,
+    Method::Delete,
+    Method::Head,
+    Method::Trace,
+    Method::Connect,
+    Method::Patch,
+];
+
+impl<T> RouteMatcher for NotRouteMatcher<T>
+where
+    T: RouteMatcher,
+{
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        if self.t.is_match(state).is_err() {
+            return Ok(());
+        }
+
+        trace!("[{}] request matched a negated matcher", request_id(&state));
+        match (self.status, self.complement_methods()) {
+            (Some(status), _) => Err(RouteNonMatch::new(status)),
+            (None, Some(allow)) => {
+                Err(RouteNonMatch::new(StatusCode::MethodNotAllowed).with_allow_list(&allow))
+            }
+            (None, None) => Err(RouteNonMatch::new(StatusCode::NotFound)),
+        }
+    }
+
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.complement_methods()
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.t.is_method_only()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::{ContentType, Headers};
+    use mime;
+
+    use router::route::matcher::MethodOnlyRouteMatcher;
+    use router::route::matcher::content_type::ContentTypeHeaderRouteMatcher;
+
+    #[test]
+    fn negated_methods_are_not_allowed() {
+        let matcher = NotRouteMatcher::new(MethodOnlyRouteMatcher::new(vec![
+            Method::Trace,
+            Method::Connect,
+        ]));
+        assert_eq!(
+            matcher.methods(),
+            Some(vec![
+                Method::Options,
+                Method::Get,
+                Method::Post,
+                Method::Put,
+                Method::Delete,
+                Method::Head,
+                Method::Patch,
+            ])
+        );
+
+        let mut state = State::new();
+        state.put(Method::Patch);
+        assert!(matcher.is_match(&state).is_ok());
+
+        state.put(Method::Trace);
+        let (status, allow) = matcher.is_match(&state).unwrap_err().deconstruct();
+        assert_eq!(status, StatusCode::MethodNotAllowed);
+        assert_eq!(allow.len(), 7);
+        assert!(!allow.contains(&Method::Trace));
+    }
+
+    #[test]
+    fn negated_matchers_use_given_status() {
+        let json = || ContentTypeHeaderRouteMatcher::new(vec![mime::APPLICATION_JSON]);
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        let mut state = State::new();
+        state.put(headers);
+
+        let matcher = NotRouteMatcher::new(json());
+        assert_eq!(matcher.methods(), None);
+        assert_eq!(
+            StatusCode::from(matcher.is_match(&state).unwrap_err()),
+            StatusCode::NotFound
+        );
+
+        let matcher = NotRouteMatcher::new(json()).with_status(StatusCode::UnsupportedMediaType);
+        assert_eq!(
+            StatusCode::from(matcher.is_match(&state).unwrap_err()),
+            StatusCode::UnsupportedMediaType
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Defines the type `OrRouteMatcher`
+
+use hyper::Method;
+
+use router::non_match::RouteNonMatch;
+use router::route::RouteMatcher;
+use state::State;
+
+/// Allows two `RouteMatcher` values to be combined, so that a request is matched when either of
+/// them matches it. When neither matches, their `RouteNonMatch` values are combined with
+/// `RouteNonMatch::union`, so that the `Allow` list of a `405 Method Not Allowed` response names
+/// the methods accepted by both.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// # fn main() {
+/// #   use hyper::header::{ContentType, Headers};
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::{RouteMatcher, OrRouteMatcher};
+/// #   use gotham::router::route::matcher::content_type::ContentTypeHeaderRouteMatcher;
+/// #
+/// #   State::with_new(|state| {
+/// #
+///   let msgpack: mime::Mime = "application/msgpack".parse().unwrap();
+///   let matcher = OrRouteMatcher::new(
+///       ContentTypeHeaderRouteMatcher::new(vec![mime::APPLICATION_JSON]),
+///       ContentTypeHeaderRouteMatcher::new(vec![msgpack.clone()]),
+///   );
+///
+///   let mut headers = Headers::new();
+///   headers.set(ContentType::json());
+///   state.put(headers);
+///   assert!(matcher.is_match(&state).is_ok());
+///
+///   let mut headers = Headers::new();
+///   headers.set(ContentType(msgpack));
+///   state.put(headers);
+///   assert!(matcher.is_match(&state).is_ok());
+///
+///   let mut headers = Headers::new();
+///   headers.set(ContentType::plaintext());
+///   state.put(headers);
+///   assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+pub struct OrRouteMatcher<T, U>
+where
+    T: RouteMatcher,
+    U: RouteMatcher,
+{
+    t: T,
+    u: U,
+}
+
+impl<T, U> OrRouteMatcher<T, U>
+where
+    T: RouteMatcher,
+    U: RouteMatcher,
+{
+    /// Creates a new `OrRouteMatcher`
+    pub fn new(t: T, u: U) -> Self {
+        OrRouteMatcher { t, u }
+    }
+}
+
+impl<T, U> RouteMatcher for OrRouteMatcher<T, U>
+where
+    T: RouteMatcher,
+    U: RouteMatcher,
+{
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        match self.t.is_match(state) {
+            Ok(_) => Ok(()),
+            Err(e) => match self.u.is_match(state) {
+                Ok(_) => Ok(()),
+                Err(e1) => Err(e.union(e1)),
+            },
+        }
+    }
+
+    fn methods(&self) -> Option<Vec<Method>> {
+        match (self.t.methods(), self.u.methods()) {
+            (Some(mut t), Some(u)) => {
+                for m in u {
+                    if !t.contains(&m) {
+                        t.push(m);
+                    }
+                }
+                Some(t)
+            }
+            // Either side may accept any method.
+            _ => None,
+        }
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.t.is_method_only() && self.u.is_method_only()
+    }
+
+    fn captures(&self, state: &State) -> Vec<(String, String)> {
+        if self.t.is_match(state).is_ok() {
+            self.t.captures(state)
+        } else {
+            self.u.captures(state)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::StatusCode;
+    use router::route::matcher::MethodOnlyRouteMatcher;
+
+    #[test]
+    fn or_route_matcher_tests() {
+        let matcher = OrRouteMatcher::new(
+            MethodOnlyRouteMatcher::new(vec![Method::Get, Method::Head]),
+            MethodOnlyRouteMatcher::new(vec![Method::Post, Method::Get]),
+        );
+        assert_eq!(
+            matcher.methods(),
+            Some(vec![Method::Get, Method::Head, Method::Post])
+        );
+        assert!(matcher.is_method_only());
+
+        let mut state = State::new();
+        state.put(Method::Post);
+        assert!(matcher.is_match(&state).is_ok());
+
+        state.put(Method::Delete);
+        let (status, allow) = matcher.is_match(&state).unwrap_err().deconstruct();
+        assert_eq!(status, StatusCode::MethodNotAllowed);
+        assert_eq!(allow, vec![Method::Get, Method::Head, Method::Post]);
+    }
+}
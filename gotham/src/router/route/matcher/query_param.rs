@@ -0,0 +1,150 @@
+//! Defines the `QueryParamRouteMatcher`.
+
+use hyper::{StatusCode, Uri};
+
+use http::request::query_string;
+use router::non_match::RouteNonMatch;
+use router::route::RouteMatcher;
+use router::route::matcher::value::ValueMatch;
+use router::tree::regex::ConstrainedSegmentRegex;
+use state::{request_id, FromState, State};
+
+/// A `RouteMatcher` that succeeds when the `Request` has been made with a query string parameter
+/// of the given name, optionally requiring that its value is exactly a given string, or matches a
+/// regex. Values are compared after they have been decoded. When the parameter is given more than
+/// once, the matcher succeeds if any of its values is accepted. A parameter given without a value,
+/// as in `?beta`, is present with an empty value.
+///
+/// A request without an accepted value is treated as `404 Not Found`, so that another route for
+/// the same path can be matched instead.
+///
+/// The builder helper `DefineSingleRoute::with_query_param` adds a matcher requiring an exact
+/// value to a route.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::Uri;
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::{QueryParamRouteMatcher, RouteMatcher};
+/// #
+/// #   State::with_new(|state| {
+/// #
+/// let matcher = QueryParamRouteMatcher::equals("canary", "true");
+///
+/// state.put("/search?q=gotham&canary=true".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// state.put("/search?q=gotham&canary=false".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_err());
+///
+/// state.put("/search?q=gotham".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct QueryParamRouteMatcher {
+    name: String,
+    value: ValueMatch,
+}
+
+impl QueryParamRouteMatcher {
+    /// Creates a new `QueryParamRouteMatcher` which accepts any value of the parameter, and so
+    /// only requires that the parameter is present.
+    pub fn present(name: &str) -> Self {
+        QueryParamRouteMatcher {
+            name: name.to_owned(),
+            value: ValueMatch::Present,
+        }
+    }
+
+    /// Creates a new `QueryParamRouteMatcher` which requires that the parameter has exactly the
+    /// given value.
+    pub fn equals(name: &str, value: &str) -> Self {
+        QueryParamRouteMatcher {
+            name: name.to_owned(),
+            value: ValueMatch::Equals(value.to_owned()),
+        }
+    }
+
+    /// Creates a new `QueryParamRouteMatcher` which requires that the whole value of the
+    /// parameter matches the given regex.
+    ///
+    /// # Panics
+    ///
+    /// If `regex` is not a valid regex.
+    pub fn regex(name: &str, regex: &str) -> Self {
+        QueryParamRouteMatcher {
+            name: name.to_owned(),
+            value: ValueMatch::Regex(ConstrainedSegmentRegex::new(regex)),
+        }
+    }
+
+    /// The name of the query string parameter which this matcher considers.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl RouteMatcher for QueryParamRouteMatcher {
+    /// Determines if the `Request` was made with an accepted value of the query string parameter.
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        let query = Uri::try_borrow_from(state).and_then(|uri| uri.query());
+        let mapping = query_string::split(query);
+        let mut values = mapping
+            .get(&self.name)
+            .map(|values| values.iter().map(|v| v.as_ref()).collect::<Vec<_>>())
+            .unwrap_or_else(Vec::new);
+
+        if query_string::keys_without_values(query).contains(&self.name) {
+            values.push("");
+        }
+
+        if self.value.any(values) {
+            Ok(())
+        } else {
+            trace!(
+                "[{}] query string parameter `{}` is not {}",
+                request_id(&state),
+                self.name,
+                self.value
+            );
+            Err(RouteNonMatch::new(StatusCode::NotFound))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(matcher: &QueryParamRouteMatcher, uri: &str) -> bool {
+        let mut state = State::new();
+        state.put(uri.parse::<Uri>().unwrap());
+        matcher.is_match(&state).is_ok()
+    }
+
+    #[test]
+    fn query_param_matcher_tests() {
+        let present = QueryParamRouteMatcher::present("beta");
+        assert!(is_match(&present, "/?beta="));
+        assert!(is_match(&present, "/?a=1&beta=yes"));
+        assert!(is_match(&present, "/?beta"));
+        assert!(is_match(&present, "/?a=1&beta"));
+        assert!(!is_match(&present, "/?betas"));
+        assert!(!is_match(&present, "/"));
+
+        let empty = QueryParamRouteMatcher::equals("beta", "");
+        assert!(is_match(&empty, "/?beta"));
+        assert!(!is_match(&empty, "/?beta=yes"));
+
+        let regex = QueryParamRouteMatcher::regex("tag", "[a-z ]+");
+        assert!(is_match(&regex, "/?tag=1&tag=two+words"));
+        assert!(!is_match(&regex, "/?tag=1"));
+    }
+}
@@ -0,0 +1,64 @@
+//! Defines `ValueMatch`, the comparison shared by the header and query parameter matchers.
+
+use std::fmt::{self, Display, Formatter};
+
+use router::tree::regex::ConstrainedSegmentRegex;
+
+/// How a value taken from the request is compared by `HeaderRouteMatcher` and
+/// `QueryParamRouteMatcher`.
+#[derive(Clone)]
+pub(crate) enum ValueMatch {
+    /// Any value is accepted, so only the presence of the value is required.
+    Present,
+
+    /// The value must be exactly the given string.
+    Equals(String),
+
+    /// The whole value must match the regex.
+    Regex(ConstrainedSegmentRegex),
+}
+
+impl ValueMatch {
+    /// Determines if any of `values` is accepted.
+    pub(crate) fn any<'a, I>(&self, values: I) -> bool
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut values = values.into_iter();
+        match *self {
+            ValueMatch::Present => values.next().is_some(),
+            ValueMatch::Equals(ref expected) => values.any(|value| value == expected),
+            ValueMatch::Regex(ref regex) => values.any(|value| regex.is_match(value)),
+        }
+    }
+}
+
+impl Display for ValueMatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ValueMatch::Present => write!(f, "present"),
+            ValueMatch::Equals(ref expected) => write!(f, "equal to `{}`", expected),
+            ValueMatch::Regex(ref regex) => write!(f, "matching `{}`", regex.pattern()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_match_tests() {
+        assert!(ValueMatch::Present.any(vec!["1"]));
+        assert!(!ValueMatch::Present.any(vec![]));
+
+        let equals = ValueMatch::Equals("2".to_owned());
+        assert!(equals.any(vec!["1", "2"]));
+        assert!(!equals.any(vec!["1", "20"]));
+
+        let regex = ValueMatch::Regex(ConstrainedSegmentRegex::new("v[0-9]+"));
+        assert!(regex.any(vec!["v2"]));
+        assert!(!regex.any(vec!["v2-beta"]));
+        assert!(!regex.any(vec![]));
+    }
+}
@@ -7,14 +7,17 @@
 pub mod matcher;
 pub mod dispatch;
 
+use std::any::type_name;
 use std::marker::PhantomData;
 use std::panic::RefUnwindSafe;
 
-use hyper::{Response, Uri};
+use futures::future;
+use hyper::{Method, Response, Uri};
 
 use handler::HandlerFuture;
+use http::PercentDecoded;
 use http::request::query_string;
-use extractor::{self, PathExtractor, QueryStringExtractor};
+use extractor::{self, HeaderExtraction, PathExtractor, QueryStringExtractor};
 use router::non_match::RouteNonMatch;
 use router::route::dispatch::Dispatcher;
 use router::route::matcher::RouteMatcher;
@@ -44,7 +47,10 @@ pub enum Delegation {
 ///    processing and dispatch to the inner `Router`;
 /// 3. Run `PathExtractor` and `QueryStringExtractor` logic to popuate `State` with the necessary
 ///    request data. If either of these extractors fail, the request is halted here;
-/// 4. Dispatch the request via `Route::dispatch`.
+/// 4. Dispatch the request via `Route::dispatch`. When the route has a `HeaderExtractor`, the
+///    request headers are extracted first, and the request is halted here if this fails. When the
+///    route has a `RequestBodyExtractor`, the request body is extracted after the pipelines, before
+///    the `Handler` is called.
 ///
 /// `Route` exists as a trait to allow abstraction over the generic types in `RouteImpl`. This
 /// trait should not be implemented outside of Gotham.
@@ -55,6 +61,19 @@ pub trait Route: RefUnwindSafe {
     /// Determines if this `Route` intends to delegate requests to a secondary `Router` instance.
     fn delegation(&self) -> Delegation;
 
+    /// The request methods which this `Route` can accept, or `None` if it doesn't consider the
+    /// request method.
+    fn methods(&self) -> Option<Vec<Method>>;
+
+    /// Whether this `Route` matches every request made with one of its `methods`.
+    fn is_method_only(&self) -> bool;
+
+    /// The type name of the `PathExtractor` used by this `Route`.
+    fn path_extractor_name(&self) -> &'static str;
+
+    /// The type name of the `QueryStringExtractor` used by this `Route`.
+    fn query_string_extractor_name(&self) -> &'static str;
+
     /// Extracts dynamic components of the `Request` path and stores the `PathExtractor` in `State`.
     fn extract_request_path(
         &self,
@@ -92,6 +111,7 @@ where
     dispatcher: Box<Dispatcher + Send + Sync>,
     _extractors: Extractors<PE, QSE>,
     delegation: Delegation,
+    header_extraction: Option<HeaderExtraction>,
 }
 
 /// Extractors used by `RouteImpl` to acquire request data and change into a type safe form
@@ -123,8 +143,21 @@ where
             dispatcher,
             _extractors,
             delegation,
+            header_extraction: None,
         }
     }
+
+    /// Sets how the request headers are extracted before the request is dispatched, if at all.
+    pub(crate) fn with_header_extraction(
+        self,
+        header_extraction: Option<HeaderExtraction>,
+    ) -> Self {
+        RouteImpl {
+            header_extraction,
+            ..self
+        }
+    }
+
 }
 
 impl<PE, QSE> Extractors<PE, QSE>
@@ -155,7 +188,33 @@ where
         self.delegation
     }
 
-    fn dispatch(&self, state: State) -> Box<HandlerFuture> {
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.matcher.methods()
+    }
+
+    fn is_method_only(&self) -> bool {
+        self.matcher.is_method_only()
+    }
+
+    fn path_extractor_name(&self) -> &'static str {
+        type_name::<PE>()
+    }
+
+    fn query_string_extractor_name(&self) -> &'static str {
+        type_name::<QSE>()
+    }
+
+    fn dispatch(&self, mut state: State) -> Box<HandlerFuture> {
+        if let Some(header_extraction) = self.header_extraction {
+            state = match header_extraction.extract(state) {
+                Ok(state) => state,
+                Err((state, res)) => {
+                    trace!("[{}] request headers were refused", request_id(&state));
+                    return Box::new(future::ok((state, res)));
+                }
+            };
+        }
+
         self.dispatcher.dispatch(state)
     }
 
@@ -164,6 +223,21 @@ where
         state: &mut State,
         segment_mapping: SegmentMapping,
     ) -> Result<(), ExtractorFailed> {
+        // Values captured by the matcher are extracted alongside the request path, unless a
+        // segment of the path has the same name.
+        let captures = self.matcher
+            .captures(state)
+            .into_iter()
+            .filter_map(|(name, value)| PercentDecoded::new(&value).map(|value| (name, value)))
+            .collect::<Vec<_>>();
+
+        let mut segment_mapping: SegmentMapping = segment_mapping;
+        for &(ref name, ref value) in &captures {
+            segment_mapping
+                .entry(name.as_str())
+                .or_insert_with(|| vec![value]);
+        }
+
         match extractor::internal::from_segment_mapping::<PE>(segment_mapping) {
             Ok(val) => Ok(state.put(val)),
             Err(e) => {
@@ -3,8 +3,13 @@
 use std::collections::HashMap;
 
 use http::PercentDecoded;
+use router::conflict::RouteConflict;
+use router::error::RouteError;
+use router::info::RouteInfo;
 use router::route::Route;
 use router::tree::node::{Node, NodeBuilder, SegmentType};
+use router::url_for::{NamedRoutes, NamedRoutesBuilder};
+use state::State;
 
 pub mod node;
 pub mod regex;
@@ -30,13 +35,42 @@ pub struct Tree {
 }
 
 impl Tree {
+    /// Describes the routes in the `Tree`, ordered by path.
+    pub(crate) fn describe_routes(&self) -> Vec<RouteInfo> {
+        let mut routes = vec![];
+        self.root.describe_routes(&mut vec![], &mut routes);
+        routes
+    }
+
+    /// Finds the routes in the `Tree` which conflict, such that they can never be reached.
+    pub(crate) fn find_conflicts(&self) -> Vec<RouteConflict> {
+        let mut conflicts = vec![];
+        self.root.find_conflicts(&mut vec![], &mut conflicts);
+        conflicts
+    }
+
     /// Attempt to acquire a path from the `Tree` which matches the `Request` path and is routable.
+    ///
+    /// When `case_insensitive` is set, `Static` segments match request path segments which differ
+    /// from them only in ASCII case.
     pub(crate) fn traverse<'r>(
         &'r self,
         req_path_segments: &'r [&PercentDecoded],
+        case_insensitive: bool,
     ) -> Option<(Path<'r>, &Node, SegmentsProcessed, SegmentMapping<'r>)> {
         trace!(" starting tree traversal");
-        self.root.traverse(req_path_segments)
+        self.root.traverse(req_path_segments, case_insensitive)
+    }
+
+    /// Finds the fallback `Route` for a request which matched no route. See `DrawRoutes::fallback`
+    /// for how the fallback is chosen.
+    pub(crate) fn select_fallback<'r>(
+        &'r self,
+        req_path_segments: &'r [&PercentDecoded],
+        case_insensitive: bool,
+        state: &State,
+    ) -> Option<(&'r Box<Route + Send + Sync>, SegmentMapping<'r>)> {
+        self.root.select_fallback(req_path_segments, case_insensitive, state)
     }
 }
 
@@ -77,6 +111,20 @@ impl TreeBuilder {
         self.root.add_route(route);
     }
 
+    /// Collects the paths of the named routes in the `Tree`.
+    pub(crate) fn named_routes(&self) -> Result<NamedRoutes, Vec<RouteError>> {
+        let mut builder = NamedRoutesBuilder::default();
+        self.root.collect_names(&mut vec![], &mut builder);
+        builder.finalize()
+    }
+
+    /// The problems recorded while routes were added to the tree, such as an invalid regex.
+    pub(crate) fn errors(&self) -> Vec<RouteError> {
+        let mut errors = vec![];
+        self.root.collect_errors(&mut vec![], &mut errors);
+        errors
+    }
+
     /// Finalizes and sorts all internal data and creates a Tree for use with a `Router`.
     pub fn finalize(self) -> Tree {
         Tree {
@@ -130,7 +178,7 @@ mod tests {
         let tree = tree_builder.finalize();
 
         let request_path_segments = RequestPathSegments::new("/%61ctiv%61te/workflow5");
-        match tree.traverse(request_path_segments.segments().as_slice()) {
+        match tree.traverse(request_path_segments.segments().as_slice(), false) {
             Some((path, leaf, segments_processed, segment_mapping)) => {
                 assert!(path.last().unwrap().is_routable());
                 assert_eq!(path.last().unwrap().segment(), leaf.segment());
@@ -149,11 +197,11 @@ mod tests {
         }
 
         assert!(
-            tree.traverse(&[&PercentDecoded::new("/").unwrap()])
+            tree.traverse(&[&PercentDecoded::new("/").unwrap()], false)
                 .is_none()
         );
         assert!(
-            tree.traverse(&[&PercentDecoded::new("/activate").unwrap()])
+            tree.traverse(&[&PercentDecoded::new("/activate").unwrap()], false)
                 .is_none()
         );
     }
@@ -2,15 +2,34 @@
 
 use std::cmp::Ordering;
 use std::borrow::Borrow;
-use hyper::StatusCode;
+use hyper::{Method, StatusCode};
 
 use http::PercentDecoded;
+use router::Router;
+use router::conflict::RouteConflict;
+use router::error::{RouteError, RouteErrorReason};
+use router::info::RouteInfo;
 use router::non_match::RouteNonMatch;
 use router::route::{Delegation, Route};
 use router::tree::{Path, SegmentMapping, SegmentsProcessed};
 use router::tree::regex::ConstrainedSegmentRegex;
+use router::url_for::{NamedRoutesBuilder, PathSegment};
 use state::{request_id, State};
 
+/// The pattern which would be given to the router builder for a segment, used when describing
+/// routes.
+fn segment_pattern(segment: &str, segment_type: &SegmentType) -> String {
+    match *segment_type {
+        SegmentType::Static => match segment.chars().next() {
+            Some(':') | Some('*') | Some('\\') => format!("\\{}", segment),
+            _ => segment.to_owned(),
+        },
+        SegmentType::Constrained { ref regex } => format!(":{}:{}", segment, regex.pattern()),
+        SegmentType::Dynamic => format!(":{}", segment),
+        SegmentType::Glob => segment.to_owned(),
+    }
+}
+
 /// Indicates the type of segment which is being represented by this Node.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum SegmentType {
@@ -43,6 +62,9 @@ pub struct Node {
     segment_type: SegmentType,
 
     routes: Vec<Box<Route + Send + Sync>>,
+    fallback: Option<Box<Route + Send + Sync>>,
+    names: Vec<String>,
+    delegated_router: Option<Router>,
 
     delegating: bool,
     children: Vec<Node>,
@@ -103,6 +125,175 @@ impl Node {
         }
     }
 
+    /// Finds the fallback `Route` for a request which matched no route, as set by the deepest node
+    /// along the request path which has a fallback that accepts the request. The `SegmentMapping`
+    /// holds the segments of the request path matched by the dynamic segments leading to that
+    /// node.
+    pub(crate) fn select_fallback<'r>(
+        &'r self,
+        req_path_segments: &'r [&PercentDecoded],
+        case_insensitive: bool,
+        state: &State,
+    ) -> Option<(&'r Box<Route + Send + Sync>, SegmentMapping<'r>)> {
+        self.inner_select_fallback(req_path_segments, vec![], case_insensitive, state)
+    }
+
+    fn inner_select_fallback<'r>(
+        &'r self,
+        req_path_segments: &'r [&PercentDecoded],
+        mut consumed_segments: Vec<&'r PercentDecoded>,
+        case_insensitive: bool,
+        state: &State,
+    ) -> Option<(&'r Box<Route + Send + Sync>, SegmentMapping<'r>)> {
+        match req_path_segments.split_first() {
+            Some((x, xs)) if self.is_match(x, case_insensitive) => {
+                let deeper = self.children
+                    .iter()
+                    .filter_map(|c| c.inner_select_fallback(xs, vec![], case_insensitive, state))
+                    .next();
+
+                let found = match deeper {
+                    Some(found) => Some(found),
+                    // A glob consumes the next segment, and may consume more.
+                    None if self.segment_type == SegmentType::Glob && !xs.is_empty() => {
+                        consumed_segments.push(x);
+                        return self.inner_select_fallback(
+                            xs,
+                            consumed_segments,
+                            case_insensitive,
+                            state,
+                        );
+                    }
+                    None => match self.fallback {
+                        Some(ref route) if route.is_match(state).is_ok() => {
+                            trace!("[{}] found fallback route", request_id(state));
+                            Some((route, SegmentMapping::new()))
+                        }
+                        _ => None,
+                    },
+                };
+
+                found.map(|(route, mut sm)| {
+                    if self.segment_type != SegmentType::Static {
+                        consumed_segments.push(x);
+                        sm.insert(self.segment(), consumed_segments);
+                    }
+
+                    (route, sm)
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds descriptions of the routes in this sub-tree to `routes`, where `path` holds the
+    /// patterns of the segments leading to this node.
+    pub(crate) fn describe_routes(&self, path: &mut Vec<String>, routes: &mut Vec<RouteInfo>) {
+        let full_path = format!("/{}", path.join("/"));
+
+        for route in &self.routes {
+            routes.push(RouteInfo::new(full_path.clone(), &self.names, &**route));
+        }
+
+        if let Some(ref route) = self.fallback {
+            routes.push(RouteInfo::fallback(full_path.clone(), &**route));
+        }
+
+        if let Some(ref router) = self.delegated_router {
+            for info in router.routes() {
+                routes.push(info.with_prefix(&full_path));
+            }
+        }
+
+        for child in &self.children {
+            path.push(child.pattern());
+            child.describe_routes(path, routes);
+            path.pop();
+        }
+    }
+
+    /// Adds the conflicts between routes in this sub-tree to `conflicts`, where `path` holds the
+    /// patterns of the segments leading to this node.
+    pub(crate) fn find_conflicts(
+        &self,
+        path: &mut Vec<String>,
+        conflicts: &mut Vec<RouteConflict>,
+    ) {
+        let full_path = format!("/{}", path.join("/"));
+
+        for (i, route) in self.routes.iter().enumerate() {
+            let earlier = self.routes[..i]
+                .iter()
+                .filter(|r| r.is_method_only())
+                .filter_map(|r| r.methods())
+                .flat_map(|methods| methods)
+                .collect::<Vec<Method>>();
+
+            let methods = route
+                .methods()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|m| earlier.contains(m))
+                .collect::<Vec<Method>>();
+
+            if !methods.is_empty() {
+                conflicts.push(RouteConflict::Duplicate {
+                    path: full_path.clone(),
+                    methods,
+                });
+            }
+        }
+
+        let child_paths = self.children
+            .iter()
+            .map(|child| format!("{}/{}", full_path.trim_end_matches('/'), child.pattern()))
+            .collect::<Vec<_>>();
+
+        for (j, child) in self.children.iter().enumerate() {
+            if let Some(i) = self.children[..j].iter().position(|c| c.shadows(child)) {
+                conflicts.push(RouteConflict::Unreachable {
+                    path: child_paths[j].clone(),
+                    shadowed_by: child_paths[i].clone(),
+                });
+            }
+
+            let captures_glob = match child.segment_type {
+                SegmentType::Dynamic | SegmentType::Glob => child.is_routable(),
+                _ => false,
+            };
+
+            if self.segment_type == SegmentType::Glob && self.is_routable() && captures_glob {
+                conflicts.push(RouteConflict::Glob {
+                    glob: full_path.clone(),
+                    shadowed_by: child_paths[j].clone(),
+                });
+            }
+        }
+
+        for child in &self.children {
+            path.push(child.pattern());
+            child.find_conflicts(path, conflicts);
+            path.pop();
+        }
+    }
+
+    /// Determines if every request which could reach `later`, a sibling which is tried after this
+    /// node, is instead captured by this node.
+    fn shadows(&self, later: &Node) -> bool {
+        match (&self.segment_type, &later.segment_type) {
+            (&SegmentType::Dynamic, &SegmentType::Dynamic) => {
+                self.delegating || (self.is_routable() && later.is_routable())
+            }
+            (&SegmentType::Dynamic, &SegmentType::Glob) => self.delegating,
+            _ => false,
+        }
+    }
+
+    /// The segment as it would be written in a path given to the router builder.
+    fn pattern(&self) -> String {
+        segment_pattern(&self.segment, &self.segment_type)
+    }
+
     /// True is there is a least one `Route` represented by this `Node`, that is it can act as a
     /// leaf in a single path through the tree.
     pub(crate) fn is_routable(&self) -> bool {
@@ -122,11 +313,15 @@ impl Node {
     /// 2. Constrained
     /// 3. Dynamic
     /// 4. Glob
+    ///
+    /// When `case_insensitive` is set, `Static` segments match request path segments which differ
+    /// from them only in ASCII case.
     pub(crate) fn traverse<'r>(
         &'r self,
         req_path_segments: &'r [&PercentDecoded],
+        case_insensitive: bool,
     ) -> Option<(Path<'r>, &Node, SegmentsProcessed, SegmentMapping<'r>)> {
-        match self.inner_traverse(req_path_segments, vec![]) {
+        match self.inner_traverse(req_path_segments, vec![], case_insensitive) {
             Some((mut path, leaf, c, sm)) => {
                 path.reverse();
                 Some((path, leaf, c, sm))
@@ -140,9 +335,10 @@ impl Node {
         &'r self,
         req_path_segments: &'r [&PercentDecoded],
         mut consumed_segments: Vec<&'r PercentDecoded>,
+        case_insensitive: bool,
     ) -> Option<(Vec<&Node>, &Node, SegmentsProcessed, SegmentMapping<'r>)> {
         match req_path_segments.split_first() {
-            Some((x, _)) if self.is_delegating(x) => {
+            Some((x, _)) if self.is_delegating(x, case_insensitive) => {
                 // A delegated node terminates processing, start building result
                 trace!(" found delegator node `{}`", self.segment);
 
@@ -154,7 +350,7 @@ impl Node {
 
                 Some((vec![self], self, 0, sm))
             }
-            Some((x, xs)) if self.is_leaf(x, xs) => {
+            Some((x, xs)) if self.is_leaf(x, xs, case_insensitive) => {
                 trace!(" found leaf node `{}`", self.segment);
 
                 let mut sm = SegmentMapping::new();
@@ -165,12 +361,12 @@ impl Node {
 
                 Some((vec![self], self, 0, sm))
             }
-            Some((x, xs)) if self.is_match(x) => {
+            Some((x, xs)) if self.is_match(x, case_insensitive) => {
                 trace!(" found node `{}`", self.segment);
 
                 let child = self.children
                     .iter()
-                    .filter_map(|c| c.inner_traverse(xs, vec![]))
+                    .filter_map(|c| c.inner_traverse(xs, vec![], case_insensitive))
                     .next();
 
                 match child {
@@ -190,7 +386,7 @@ impl Node {
                     None if self.segment_type == SegmentType::Glob => {
                         trace!(" continuing with glob match for segment `{}`", self.segment);
                         consumed_segments.push(x);
-                        match self.inner_traverse(xs, consumed_segments) {
+                        match self.inner_traverse(xs, consumed_segments, case_insensitive) {
                             Some((nodes, n, sp, sm)) => Some((nodes, n, sp + 1, sm)),
                             None => None,
                         }
@@ -203,20 +399,23 @@ impl Node {
         }
     }
 
-    fn is_delegating(&self, req_path_segment: &PercentDecoded) -> bool {
-        self.is_match(req_path_segment) && self.delegating
+    fn is_delegating(&self, req_path_segment: &PercentDecoded, case_insensitive: bool) -> bool {
+        self.is_match(req_path_segment, case_insensitive) && self.delegating
     }
 
-    fn is_match(&self, req_path_segment: &PercentDecoded) -> bool {
+    fn is_match(&self, req_path_segment: &PercentDecoded, case_insensitive: bool) -> bool {
         match self.segment_type {
+            SegmentType::Static if case_insensitive => {
+                self.segment.eq_ignore_ascii_case(req_path_segment.as_ref())
+            }
             SegmentType::Static => self.segment == req_path_segment.as_ref(),
             SegmentType::Constrained { ref regex } => regex.is_match(req_path_segment.as_ref()),
             SegmentType::Dynamic | SegmentType::Glob => true,
         }
     }
 
-    fn is_leaf(&self, s: &PercentDecoded, rs: &[&PercentDecoded]) -> bool {
-        rs.is_empty() && self.is_match(s) && self.is_routable()
+    fn is_leaf(&self, s: &PercentDecoded, rs: &[&PercentDecoded], case_insensitive: bool) -> bool {
+        rs.is_empty() && self.is_match(s, case_insensitive) && self.is_routable()
     }
 }
 
@@ -225,6 +424,10 @@ pub struct NodeBuilder {
     segment: String,
     segment_type: SegmentType,
     routes: Vec<Box<Route + Send + Sync>>,
+    fallback: Option<Box<Route + Send + Sync>>,
+    names: Vec<String>,
+    delegated_router: Option<Router>,
+    errors: Vec<(Option<String>, RouteErrorReason)>,
 
     delegating: bool,
     children: Vec<NodeBuilder>,
@@ -241,6 +444,10 @@ impl NodeBuilder {
             segment,
             segment_type,
             routes: vec![],
+            fallback: None,
+            names: vec![],
+            delegated_router: None,
+            errors: vec![],
             children: vec![],
             delegating: false,
         }
@@ -254,28 +461,148 @@ impl NodeBuilder {
     /// Adds a `Route` be evaluated by the `Router` when the built `Node` is acting as a leaf in a
     /// single path through the `Tree`.
     pub fn add_route(&mut self, route: Box<Route + Send + Sync>) {
+        match self.check_route(&*route) {
+            Some(RouteErrorReason::DelegatedWithRoutes) => {
+                panic!("Node which is externally delegating must have single Route")
+            }
+            Some(_) => {
+                panic!("Node which is externally delegating must not have existing children")
+            }
+            None => self.push_route(route),
+        }
+    }
+
+    /// Adds a `Route` as `add_route` does, except that a `Route` which this node can't accept is
+    /// recorded as an error for `try_build_router` to report, rather than causing a panic.
+    pub(crate) fn add_route_recording_errors(&mut self, route: Box<Route + Send + Sync>) {
+        if let Some(reason) = self.check_route(&*route) {
+            self.add_error(None, reason);
+        }
+
+        self.push_route(route);
+    }
+
+    /// Sets the `Route` which the `Router` dispatches to when no route matches a request for a path
+    /// beneath this node. A second fallback for the same node is recorded as an error for
+    /// `try_build_router` to report.
+    pub(crate) fn set_fallback_recording_errors(&mut self, route: Box<Route + Send + Sync>) {
+        if self.fallback.is_some() {
+            self.add_error(None, RouteErrorReason::DuplicateFallback);
+        }
+
+        trace!(" adding fallback route to `{}`", self.segment());
+        self.fallback = Some(route);
+    }
+
+    fn check_route(&self, route: &Route) -> Option<RouteErrorReason> {
         if route.delegation() == Delegation::External {
             if !self.routes.is_empty() {
-                panic!("Node which is externally delegating must have single Route");
+                return Some(RouteErrorReason::DelegatedWithRoutes);
             }
 
             if !self.children.is_empty() {
-                panic!("Node which is externally delegating must not have existing children");
+                return Some(RouteErrorReason::DelegatedWithChildren);
             }
+        }
+
+        None
+    }
 
+    fn push_route(&mut self, route: Box<Route + Send + Sync>) {
+        if route.delegation() == Delegation::External {
             self.delegating = true;
-        };
+        }
 
         trace!(" adding route to `{}`", self.segment());
         self.routes.push(route);
     }
 
+    /// Records a problem with the routes at this node, or at its child segment `child` when given,
+    /// for `try_build_router` to report.
+    pub(crate) fn add_error(&mut self, child: Option<&str>, reason: RouteErrorReason) {
+        let error = (child.map(|c| c.to_owned()), reason);
+        if !self.errors.contains(&error) {
+            trace!(" recording error at `{}`: {}", self.segment(), error.1);
+            self.errors.push(error);
+        }
+    }
+
+    /// Adds the errors recorded in this sub-tree to `errors`, where `path` holds the patterns of
+    /// the segments leading to this node.
+    pub(crate) fn collect_errors(&self, path: &mut Vec<String>, errors: &mut Vec<RouteError>) {
+        for &(ref child, ref reason) in &self.errors {
+            let mut full_path = format!("/{}", path.join("/"));
+            if let Some(ref child) = *child {
+                if !path.is_empty() {
+                    full_path.push('/');
+                }
+                full_path.push_str(child);
+            }
+
+            errors.push(RouteError::new(full_path, reason.clone()));
+        }
+
+        for child in &self.children {
+            path.push(segment_pattern(&child.segment, &child.segment_type));
+            child.collect_errors(path, errors);
+            path.pop();
+        }
+    }
+
+    /// Names the path to this node, so that `url_for` can generate it.
+    pub(crate) fn add_name(&mut self, name: &str) {
+        trace!(" naming `{}` as `{}`", self.segment(), name);
+        self.names.push(name.to_owned());
+    }
+
+    /// Records the `Router` which this node is delegating to, so that its named routes and route
+    /// descriptions can be included in those of the parent `Router`.
+    pub(crate) fn set_delegated_router(&mut self, router: Router) {
+        self.delegated_router = Some(router);
+    }
+
+    /// Adds the named paths in this sub-tree to `builder`, where `path` leads to this node.
+    pub(crate) fn collect_names(
+        &self,
+        path: &mut Vec<PathSegment>,
+        builder: &mut NamedRoutesBuilder,
+    ) {
+        for name in &self.names {
+            builder.add(name, path);
+        }
+
+        if let Some(ref router) = self.delegated_router {
+            builder.add_delegated(path, &router.named_routes());
+        }
+
+        for child in &self.children {
+            path.push(PathSegment::new(&child.segment, &child.segment_type));
+            child.collect_names(path, builder);
+            path.pop();
+        }
+    }
+
     /// Adds a new child to this sub-tree structure
     pub fn add_child(&mut self, child: NodeBuilder) {
         if self.delegating {
             panic!("Node which is externally delegating must not have existing children")
         }
 
+        self.push_child(child);
+    }
+
+    /// Adds a new child as `add_child` does, except that adding a child to a node which is
+    /// delegating is recorded as an error for `try_build_router` to report, rather than causing a
+    /// panic.
+    pub(crate) fn add_child_recording_errors(&mut self, child: NodeBuilder) {
+        if self.delegating {
+            self.add_error(None, RouteErrorReason::DelegatedWithChildren);
+        }
+
+        self.push_child(child);
+    }
+
+    fn push_child(&mut self, child: NodeBuilder) {
         trace!(
             " adding child `{}` to `{}`",
             child.segment(),
@@ -326,6 +653,9 @@ impl NodeBuilder {
             segment: self.segment,
             segment_type: self.segment_type,
             routes: self.routes,
+            fallback: self.fallback,
+            names: self.names,
+            delegated_router: self.delegated_router,
             delegating: self.delegating,
             children,
         }
@@ -552,13 +882,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn traverses_static_segments_ignoring_case() {
+        let root = test_structure().finalize();
+
+        let rs = RequestPathSegments::new("/SEG3/Seg4");
+        assert!(root.traverse(&rs.segments(), false).is_none());
+        match root.traverse(&rs.segments(), true) {
+            Some((_, leaf, _, _)) => assert_eq!(leaf.segment(), "seg4"),
+            None => panic!("traversal should have succeeded here"),
+        }
+    }
+
     #[test]
     fn traverses_children() {
         let root = test_structure().finalize();
 
         // GET /seg3/seg4
         let rs = RequestPathSegments::new("/seg3/seg4");
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((path, leaf, sp, _)) => {
                 assert_eq!(path.last().unwrap().segment(), "seg4");
                 assert_eq!(path.last().unwrap().segment(), leaf.segment());
@@ -569,11 +911,11 @@ mod tests {
 
         // GET /seg3/seg4/seg5
         let rs = RequestPathSegments::new("/seg3/seg4/seg5");
-        assert!(root.traverse(&rs.segments()).is_none());
+        assert!(root.traverse(&rs.segments(), false).is_none());
 
         // GET /seg5/seg6
         let rs = RequestPathSegments::new("/seg5/seg6");
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((path, _, sp, _)) => {
                 assert_eq!(path.last().unwrap().segment(), "seg6");
                 assert_eq!(sp, 2);
@@ -583,7 +925,7 @@ mod tests {
 
         // GET /seg5/someval/seg7
         let rs = RequestPathSegments::new("/seg5/someval/seg7");
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((path, _, sp, _)) => {
                 assert_eq!(path.last().unwrap().segment(), "seg7");
                 assert_eq!(sp, 3);
@@ -593,7 +935,7 @@ mod tests {
 
         // GET /some/path/seg9/another/path
         let rs = RequestPathSegments::new("/some/path/seg9/another/branch");
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((path, _, sp, _)) => {
                 assert_eq!(path.last().unwrap().segment(), "seg10");
                 assert_eq!(sp, 5);
@@ -603,7 +945,7 @@ mod tests {
 
         let rs = RequestPathSegments::new("/resource/5001");
         let expected_segment = "id";
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((path, _, sp, _)) => {
                 assert_eq!(path.last().unwrap().segment(), expected_segment);
                 assert_eq!(sp, 2);
@@ -622,7 +964,7 @@ mod tests {
         set_request_id(&mut state);
 
         let rs = RequestPathSegments::new("/seg2");
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((_, node, _, _)) => match node.select_route(&state) {
                 Err(e) => {
                     let (status, mut allow_list) = e.deconstruct();
@@ -636,7 +978,7 @@ mod tests {
         }
 
         let rs = RequestPathSegments::new("/resource/100");
-        match root.traverse(&rs.segments()) {
+        match root.traverse(&rs.segments(), false) {
             Some((_, node, _, _)) => match node.select_route(&state) {
                 Err(e) => {
                     let (status, mut allow_list) = e.deconstruct();
@@ -708,7 +1050,7 @@ mod tests {
             &PercentDecoded::new("/").unwrap(),
             &PercentDecoded::new("activate").unwrap(),
             &PercentDecoded::new("workflow").unwrap(),
-        ]) {
+        ], false) {
             Some((path, _leaf, segments_processed, _segment_mapping)) => {
                 assert!(path.last().unwrap().is_routable());
                 assert_eq!(segments_processed, 2);
@@ -1,8 +1,9 @@
 //! Defines the wrapping type for a segment-matching regex.
 
-use regex::Regex;
+use regex::{Error, Regex};
 
 use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::process;
 
@@ -22,9 +23,22 @@ impl ConstrainedSegmentRegex {
     /// It wraps the string in begin and end of line anchors to prevent it from matching more than
     /// intended.
     pub fn new(regex: &str) -> Self {
-        ConstrainedSegmentRegex {
-            regex: AssertUnwindSafe(Regex::new(&format!("^{pattern}$", pattern = regex)).unwrap()),
-        }
+        ConstrainedSegmentRegex::try_new(regex).unwrap()
+    }
+
+    /// Creates a new ConstrainedSegmentRegex as `new` does, returning the error from the `regex`
+    /// crate instead of panicking when the string isn't a valid regex.
+    pub(crate) fn try_new(regex: &str) -> Result<Self, Error> {
+        let regex = Regex::new(&format!("^{pattern}$", pattern = regex))?;
+        Ok(ConstrainedSegmentRegex {
+            regex: AssertUnwindSafe(regex),
+        })
+    }
+
+    /// The pattern this regex was created from, without the anchors added by `new`.
+    pub(crate) fn pattern(&self) -> &str {
+        let anchored = self.regex.as_str();
+        &anchored[1..anchored.len() - 1]
     }
 
     /// Wraps `regex::Regex::is_match` to return true if and only if the regex matches the string
@@ -72,3 +86,11 @@ impl Clone for ConstrainedSegmentRegex {
         }
     }
 }
+
+impl Debug for ConstrainedSegmentRegex {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("ConstrainedSegmentRegex")
+            .field(&self.pattern())
+            .finish()
+    }
+}
@@ -0,0 +1,407 @@
+//! Defines `url_for`, which generates the path of a named route.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+use router::error::{RouteError, RouteErrorReason};
+use router::tree::node::SegmentType;
+use router::tree::regex::ConstrainedSegmentRegex;
+use state::{FromState, State, StateData};
+
+/// A single segment of the path to a named route.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PathSegment {
+    /// Appears in the generated path as-is.
+    Static(String),
+
+    /// Replaced by the value of the parameter with the given name.
+    Param(String),
+
+    /// Replaced by the value of the parameter with the given name, which must match the regex.
+    Constrained(String, ConstrainedSegmentRegex),
+
+    /// Replaced by the value of the parameter with the given name, which may span several
+    /// segments.
+    Glob(String),
+}
+
+impl PathSegment {
+    pub(crate) fn new(segment: &str, segment_type: &SegmentType) -> PathSegment {
+        match *segment_type {
+            SegmentType::Static => PathSegment::Static(segment.to_owned()),
+            SegmentType::Constrained { ref regex } => {
+                PathSegment::Constrained(segment.to_owned(), regex.clone())
+            }
+            SegmentType::Dynamic => PathSegment::Param(segment.to_owned()),
+            SegmentType::Glob => PathSegment::Glob(segment.to_owned()),
+        }
+    }
+
+    fn param(&self) -> Option<&str> {
+        match *self {
+            PathSegment::Static(_) => None,
+            PathSegment::Param(ref name)
+            | PathSegment::Constrained(ref name, _)
+            | PathSegment::Glob(ref name) => Some(name),
+        }
+    }
+}
+
+/// The paths to the named routes of a `Router`, including those of any delegated `Router`.
+#[derive(Clone, Default)]
+pub(crate) struct NamedRoutes {
+    routes: Arc<HashMap<String, Vec<PathSegment>>>,
+}
+
+impl StateData for NamedRoutes {}
+
+/// Collects the named routes while a `Router` is being built.
+#[derive(Default)]
+pub(crate) struct NamedRoutesBuilder {
+    routes: HashMap<String, Vec<PathSegment>>,
+    errors: Vec<RouteError>,
+}
+
+impl NamedRoutesBuilder {
+    /// Adds a route named `name`, found at `path`.
+    ///
+    /// If a route has already been given the same name, an error is recorded instead.
+    pub(crate) fn add(&mut self, name: &str, path: &[PathSegment]) {
+        if self.routes.contains_key(name) {
+            let reason = RouteErrorReason::DuplicateName(name.to_owned());
+            self.errors.push(RouteError::new(describe(path), reason));
+            return;
+        }
+
+        self.routes.insert(name.to_owned(), path.to_vec());
+    }
+
+    /// Adds the named routes of a `Router` which has been delegated to at `prefix`.
+    pub(crate) fn add_delegated(&mut self, prefix: &[PathSegment], delegated: &NamedRoutes) {
+        for (name, path) in delegated.routes.iter() {
+            let mut full = prefix.to_vec();
+            full.extend_from_slice(path);
+            self.add(name, &full);
+        }
+    }
+
+    /// Creates the `NamedRoutes`, or returns the errors recorded while adding them.
+    pub(crate) fn finalize(self) -> Result<NamedRoutes, Vec<RouteError>> {
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok(NamedRoutes {
+            routes: Arc::new(self.routes),
+        })
+    }
+}
+
+/// Describes `path` in the form given to the router builder, for reporting errors.
+fn describe(path: &[PathSegment]) -> String {
+    let segments = path.iter()
+        .map(|segment| match *segment {
+            PathSegment::Static(ref s) => s.clone(),
+            PathSegment::Param(ref name) => format!(":{}", name),
+            PathSegment::Constrained(ref name, ref regex) => {
+                format!(":{}:{}", name, regex.pattern())
+            }
+            PathSegment::Glob(ref name) => name.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    format!("/{}", segments.join("/"))
+}
+
+/// Describes why `url_for` was unable to generate a path.
+#[derive(Debug, PartialEq)]
+pub enum UrlForError {
+    /// No route with the given name has been defined in the `Router`.
+    UnknownRoute(String),
+
+    /// The path to the route has a segment with the given name, but no value was provided for it.
+    MissingParam(String),
+
+    /// A value was provided for a parameter with the given name, which the path to the route
+    /// doesn't have.
+    UnknownParam(String),
+
+    /// The value provided for the parameter with the given name is empty, or doesn't match the
+    /// regex constraining the segment, so the path wouldn't be routed to the named route.
+    InvalidParam(String),
+}
+
+impl Display for UrlForError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            UrlForError::UnknownRoute(ref name) => write!(f, "no route is named `{}`", name),
+            UrlForError::MissingParam(ref name) => {
+                write!(f, "no value was provided for parameter `{}`", name)
+            }
+            UrlForError::UnknownParam(ref name) => {
+                write!(f, "the route has no parameter named `{}`", name)
+            }
+            UrlForError::InvalidParam(ref name) => {
+                write!(f, "the value for parameter `{}` is not valid for the route", name)
+            }
+        }
+    }
+}
+
+impl Error for UrlForError {
+    fn description(&self) -> &str {
+        match *self {
+            UrlForError::UnknownRoute(_) => "unknown route name",
+            UrlForError::MissingParam(_) => "missing route parameter",
+            UrlForError::UnknownParam(_) => "unknown route parameter",
+            UrlForError::InvalidParam(_) => "invalid route parameter",
+        }
+    }
+}
+
+/// Generates the path to the route which was given `name` when the `Router` was built, replacing
+/// its dynamic segments with the values from `params`. The values are percent-encoded, except for
+/// the `/` separators in the value of a glob (`*`) segment.
+///
+/// Named routes in scopes and delegated `Router` instances are included, with the path prefix of
+/// the scope or delegation.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::http::response::create_response;
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::router::url_for;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn current_user(state: State) -> (State, Response) {
+///     let location = url_for(&state, "user_show", &[("id", "42")]).unwrap();
+///     assert_eq!(location, "/api/users/42");
+///     // Implementation elided.
+/// #   let response = create_response(&state, StatusCode::Found, None);
+/// #   (state, response)
+/// }
+/// #
+/// # fn show_user(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Ok))
+/// # }
+///
+/// # fn router() -> Router {
+/// build_simple_router(|route| {
+///     route.scope("/api", |route| {
+///         route.get("/users/me").to(current_user);
+///         route.get("/users/:id").name("user_show").to(show_user);
+///     });
+/// })
+/// # }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client()
+/// #       .get("https://example.com/api/users/me")
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::Found);
+/// # }
+/// ```
+pub fn url_for(
+    state: &State,
+    name: &str,
+    params: &[(&str, &str)],
+) -> Result<String, UrlForError> {
+    let path = NamedRoutes::try_borrow_from(state)
+        .and_then(|named| named.routes.get(name))
+        .ok_or_else(|| UrlForError::UnknownRoute(name.to_owned()))?;
+
+    for &(key, _) in params {
+        if !path.iter().any(|segment| segment.param() == Some(key)) {
+            return Err(UrlForError::UnknownParam(key.to_owned()));
+        }
+    }
+
+    let value = |key: &str| match params.iter().find(|&&(k, _)| k == key) {
+        Some(&(_, "")) => Err(UrlForError::InvalidParam(key.to_owned())),
+        Some(&(_, v)) => Ok(v),
+        None => Err(UrlForError::MissingParam(key.to_owned())),
+    };
+
+    let mut url = String::new();
+    for segment in path {
+        match *segment {
+            PathSegment::Static(ref s) => {
+                url.push('/');
+                url.extend(utf8_percent_encode(s, PATH_SEGMENT_ENCODE_SET));
+            }
+            PathSegment::Param(ref key) => {
+                url.push('/');
+                url.extend(utf8_percent_encode(value(key)?, PATH_SEGMENT_ENCODE_SET));
+            }
+            PathSegment::Constrained(ref key, ref regex) => {
+                let value = value(key)?;
+                if !regex.is_match(value) {
+                    return Err(UrlForError::InvalidParam(key.to_owned()));
+                }
+
+                url.push('/');
+                url.extend(utf8_percent_encode(value, PATH_SEGMENT_ENCODE_SET));
+            }
+            PathSegment::Glob(ref key) => for part in value(key)?.split('/') {
+                url.push('/');
+                url.extend(utf8_percent_encode(part, PATH_SEGMENT_ENCODE_SET));
+            },
+        }
+    }
+
+    if url.is_empty() {
+        url.push('/');
+    }
+
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(routes: &[(&str, Vec<PathSegment>)]) -> State {
+        let mut builder = NamedRoutesBuilder::default();
+        for &(name, ref path) in routes {
+            builder.add(name, path);
+        }
+
+        let mut state = State::new();
+        state.put(builder.finalize().unwrap());
+        state
+    }
+
+    fn user_show() -> Vec<PathSegment> {
+        vec![
+            PathSegment::Static("users".to_owned()),
+            PathSegment::Param("id".to_owned()),
+        ]
+    }
+
+    #[test]
+    fn substitutes_and_encodes_params() {
+        let state = state_with(&[("user_show", user_show())]);
+
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "42")]),
+            Ok("/users/42".to_owned())
+        );
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "a b/c?")]),
+            Ok("/users/a%20b%2Fc%3F".to_owned())
+        );
+    }
+
+    #[test]
+    fn glob_keeps_separators() {
+        let path = vec![
+            PathSegment::Static("files".to_owned()),
+            PathSegment::Glob("*".to_owned()),
+        ];
+        let state = state_with(&[("file", path)]);
+
+        assert_eq!(
+            url_for(&state, "file", &[("*", "docs/read me.txt")]),
+            Ok("/files/docs/read%20me.txt".to_owned())
+        );
+    }
+
+    #[test]
+    fn constrained_params_must_match() {
+        let path = vec![
+            PathSegment::Static("users".to_owned()),
+            PathSegment::Constrained("id".to_owned(), ConstrainedSegmentRegex::new("[0-9]+")),
+        ];
+        let state = state_with(&[("user_show", path)]);
+
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "42")]),
+            Ok("/users/42".to_owned())
+        );
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "me")]),
+            Err(UrlForError::InvalidParam("id".to_owned()))
+        );
+    }
+
+    #[test]
+    fn root_route() {
+        let state = state_with(&[("root", vec![])]);
+        assert_eq!(url_for(&state, "root", &[]), Ok("/".to_owned()));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let state = state_with(&[("user_show", user_show())]);
+
+        assert_eq!(
+            url_for(&state, "user_edit", &[]),
+            Err(UrlForError::UnknownRoute("user_edit".to_owned()))
+        );
+        assert_eq!(
+            url_for(&state, "user_show", &[]),
+            Err(UrlForError::MissingParam("id".to_owned()))
+        );
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "")]),
+            Err(UrlForError::InvalidParam("id".to_owned()))
+        );
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "1"), ("format", "json")]),
+            Err(UrlForError::UnknownParam("format".to_owned()))
+        );
+        assert_eq!(
+            url_for(&State::new(), "user_show", &[("id", "1")]),
+            Err(UrlForError::UnknownRoute("user_show".to_owned()))
+        );
+    }
+
+    #[test]
+    fn delegated_routes_are_prefixed() {
+        let mut delegated = NamedRoutesBuilder::default();
+        delegated.add("user_show", &user_show());
+        let delegated = delegated.finalize().unwrap();
+
+        let mut builder = NamedRoutesBuilder::default();
+        builder.add_delegated(&[PathSegment::Static("admin".to_owned())], &delegated);
+
+        let mut state = State::new();
+        state.put(builder.finalize().unwrap());
+
+        assert_eq!(
+            url_for(&state, "user_show", &[("id", "7")]),
+            Ok("/admin/users/7".to_owned())
+        );
+    }
+
+    #[test]
+    fn duplicate_names_are_reported() {
+        let mut builder = NamedRoutesBuilder::default();
+        builder.add("user_show", &user_show());
+        builder.add("user_show", &[]);
+
+        let errors = builder.finalize().err().unwrap();
+        assert_eq!(
+            errors,
+            vec![
+                RouteError::new(
+                    "/".to_owned(),
+                    RouteErrorReason::DuplicateName("user_show".to_owned()),
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Defines `ServerBuilder`, which configures the connection handling of a Gotham application before
+//! starting it.
+
+use std::net::{TcpListener, ToSocketAddrs};
+#[cfg(unix)]
+use std::path::Path;
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::server::Http;
+use num_cpus;
+use tokio_core;
+use tokio_core::reactor::Handle;
+
+use error::StartError;
+use handler::NewHandler;
+use os::{self, Serve};
+use state::Scheme;
+#[cfg(feature = "tls")]
+use tls::TlsConfig;
+
+/// Configures and starts a Gotham application.
+///
+/// The `gotham::start` family of functions use the default settings, which are appropriate for
+/// most applications. `ServerBuilder` allows the connection handling to be tuned for a particular
+/// deployment.
+///
+/// ```rust,no_run
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::State;
+/// # use gotham::ServerBuilder;
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Accepted))
+/// # }
+/// #
+/// # fn main() {
+/// ServerBuilder::new()
+///     .with_threads(4)
+///     .with_idle_timeout(Duration::from_secs(60))
+///     .with_max_connections(10_000)
+///     .with_max_header_size(16 * 1024)
+///     .with_max_body_size(1024 * 1024)
+///     .run("127.0.0.1:7878", || Ok(my_handler))
+///     .expect("unable to start server");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServerBuilder {
+    pub(crate) threads: usize,
+    pub(crate) keep_alive: bool,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) handshake_timeout: Duration,
+    pub(crate) max_connections: Option<usize>,
+    pub(crate) pipeline: bool,
+    pub(crate) max_header_size: Option<usize>,
+    pub(crate) max_body_size: Option<usize>,
+    pub(crate) drain_timeout: Duration,
+    pub(crate) proxy_protocol: bool,
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<TlsConfig>,
+    #[cfg(feature = "http2")]
+    pub(crate) http2: bool,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> ServerBuilder {
+        ServerBuilder {
+            threads: num_cpus::get(),
+            keep_alive: true,
+            idle_timeout: None,
+            handshake_timeout: Duration::from_secs(10),
+            max_connections: None,
+            pipeline: false,
+            max_header_size: None,
+            max_body_size: None,
+            drain_timeout: Duration::from_secs(30),
+            proxy_protocol: false,
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(feature = "http2")]
+            http2: false,
+        }
+    }
+}
+
+impl ServerBuilder {
+    /// Creates a `ServerBuilder` with the default settings, which are:
+    ///
+    /// * One thread per CPU;
+    /// * HTTP keep-alive enabled, with no idle timeout;
+    /// * A handshake timeout of 10 seconds;
+    /// * No limit on the number of concurrent connections;
+    /// * Pipelined response flushing disabled;
+    /// * Hyper's default limit on the size of the request line and headers;
+    /// * No limit on the size of request bodies;
+    /// * A drain timeout of 30 seconds when shutting down;
+    /// * No PROXY protocol header expected on connections.
+    pub fn new() -> ServerBuilder {
+        ServerBuilder::default()
+    }
+
+    /// Sets the number of threads which will accept and serve connections. Starting the
+    /// application fails with `StartError::InvalidThreadCount` when `threads` is zero.
+    ///
+    /// ## Windows
+    ///
+    /// An additional thread is used on Windows to accept connections.
+    pub fn with_threads(self, threads: usize) -> ServerBuilder {
+        ServerBuilder { threads, ..self }
+    }
+
+    /// Enables or disables HTTP keep-alive. When disabled, each connection is closed after serving
+    /// a single request.
+    pub fn with_keep_alive(self, keep_alive: bool) -> ServerBuilder {
+        ServerBuilder { keep_alive, ..self }
+    }
+
+    /// Closes connections which have had no request in flight, and no data sent or received, for
+    /// longer than `idle_timeout`.
+    pub fn with_idle_timeout(self, idle_timeout: Duration) -> ServerBuilder {
+        ServerBuilder {
+            idle_timeout: Some(idle_timeout),
+            ..self
+        }
+    }
+
+    /// Closes connections which have not completed the steps needed before the first request can
+    /// be read within `handshake_timeout`, regardless of the idle timeout. These steps are reading
+    /// the PROXY protocol header, the TLS handshake, and reading the HTTP/2 connection preface,
+    /// and each of them is given `handshake_timeout` to complete.
+    pub fn with_handshake_timeout(self, handshake_timeout: Duration) -> ServerBuilder {
+        ServerBuilder {
+            handshake_timeout,
+            ..self
+        }
+    }
+
+    /// Limits the number of connections each thread will serve concurrently. When a thread reaches
+    /// the limit, it stops accepting connections until one of its existing connections is closed.
+    /// Connections are counted from when they are accepted, including while their PROXY protocol
+    /// header or TLS handshake is still being read.
+    pub fn with_max_connections(self, max_connections: usize) -> ServerBuilder {
+        ServerBuilder {
+            max_connections: Some(max_connections),
+            ..self
+        }
+    }
+
+    /// Enables or disables aggregating the flushes of pipelined responses, which improves
+    /// throughput for clients that pipeline requests.
+    pub fn with_pipelining(self, pipeline: bool) -> ServerBuilder {
+        ServerBuilder { pipeline, ..self }
+    }
+
+    /// Sets the maximum size, in bytes, of the buffer used to read the request line and headers.
+    /// Requests with a larger head are rejected, and the connection is closed.
+    pub fn with_max_header_size(self, max_header_size: usize) -> ServerBuilder {
+        ServerBuilder {
+            max_header_size: Some(max_header_size),
+            ..self
+        }
+    }
+
+    /// Refuses requests whose body is larger than `max_body_size` bytes with `413 Payload Too
+    /// Large`, in the same way as a `BodyLimitMiddleware` which is applied to every request.
+    ///
+    /// A request with a larger `Content-Length` is refused without invoking the handler, and the
+    /// `Body` in `State` stops yielding chunks once the limit is exceeded, so chunked bodies are
+    /// limited without any change to the handler.
+    pub fn with_max_body_size(self, max_body_size: usize) -> ServerBuilder {
+        ServerBuilder {
+            max_body_size: Some(max_body_size),
+            ..self
+        }
+    }
+
+    /// Sets how long in-flight requests are given to complete when the application is shut down
+    /// via `run_until`.
+    pub fn with_drain_timeout(self, drain_timeout: Duration) -> ServerBuilder {
+        ServerBuilder {
+            drain_timeout,
+            ..self
+        }
+    }
+
+    /// Enables or disables reading a PROXY protocol header (version 1 or 2) at the start of each
+    /// connection, as sent by load balancers such as HAProxy or AWS NLB. When enabled, the client
+    /// address reported via `gotham::state::client_addr` is the source address from the header
+    /// rather than the address of the load balancer.
+    ///
+    /// Every connection must begin with a valid header, and connections which don't are closed,
+    /// so this must only be enabled when the application is reachable solely through the proxy.
+    pub fn with_proxy_protocol(self, proxy_protocol: bool) -> ServerBuilder {
+        ServerBuilder {
+            proxy_protocol,
+            ..self
+        }
+    }
+
+    /// Terminates TLS on every connection using the certificate from `tls`. Only available when
+    /// the `tls` feature is enabled.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(self, tls: TlsConfig) -> ServerBuilder {
+        ServerBuilder {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    /// Enables or disables HTTP/2. Only available when the `http2` feature is enabled.
+    ///
+    /// When enabled, HTTP/2 is offered via ALPN on TLS connections, and cleartext connections
+    /// which begin with the HTTP/2 connection preface (known as "prior knowledge") are served
+    /// with HTTP/2. Other connections continue to be served with HTTP/1.
+    #[cfg(feature = "http2")]
+    pub fn with_http2(self, http2: bool) -> ServerBuilder {
+        ServerBuilder { http2, ..self }
+    }
+
+    /// Starts the application, serving requests with `new_handler`. This function only returns if
+    /// the application cannot be started.
+    ///
+    /// Only the first address resolved from `addr` is used. Use `run_all` to listen on every
+    /// resolved address.
+    pub fn run<NH, A>(self, addr: A, new_handler: NH) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        A: ToSocketAddrs,
+    {
+        self.run_until(addr, new_handler, future::empty())
+    }
+
+    /// Starts the application, serving requests with `new_handler` until `shutdown_signal`
+    /// resolves.
+    ///
+    /// Once shutdown begins, no further connections are accepted and idle connections are closed.
+    /// Requests which are in flight are given up to the drain timeout to complete, after which any
+    /// remaining connections are dropped and this function returns.
+    pub fn run_until<NH, A, F>(
+        self,
+        addr: A,
+        new_handler: NH,
+        shutdown_signal: F,
+    ) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        A: ToSocketAddrs,
+        F: Future<Item = (), Error = ()>,
+    {
+        let (listener, _) = ::tcp_listener(addr)?;
+        self.run_listeners_until(vec![listener], new_handler, shutdown_signal)
+    }
+
+    /// Starts the application on every address resolved from `addrs`, serving requests with the
+    /// same `new_handler` on each of them. This function only returns if the application cannot be
+    /// started.
+    ///
+    /// Each of `addrs` may resolve to several addresses, such as `"localhost:7878"` resolving to
+    /// both an IPv4 and an IPv6 address, and a listener is bound to each one. Different ports can
+    /// be combined, to serve a public port and an internal port from the same application:
+    ///
+    /// ```rust,no_run
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::ServerBuilder;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response) {
+    /// #   (state, Response::new().with_status(StatusCode::Accepted))
+    /// # }
+    /// #
+    /// # fn main() {
+    /// ServerBuilder::new()
+    ///     .run_all(vec!["0.0.0.0:80", "127.0.0.1:9000"], || Ok(my_handler))
+    ///     .expect("unable to start server");
+    /// # }
+    /// ```
+    pub fn run_all<NH, I>(self, addrs: I, new_handler: NH) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        I: IntoIterator,
+        I::Item: ToSocketAddrs,
+    {
+        self.run_all_until(addrs, new_handler, future::empty())
+    }
+
+    /// Starts the application on every address resolved from `addrs`, serving requests with the
+    /// same `new_handler` on each of them until `shutdown_signal` resolves.
+    ///
+    /// See `run_until` for details of how shutdown is handled.
+    pub fn run_all_until<NH, I, F>(
+        self,
+        addrs: I,
+        new_handler: NH,
+        shutdown_signal: F,
+    ) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        I: IntoIterator,
+        I::Item: ToSocketAddrs,
+        F: Future<Item = (), Error = ()>,
+    {
+        let listeners = ::tcp_listeners(addrs)?;
+        self.run_listeners_until(listeners, new_handler, shutdown_signal)
+    }
+
+    /// Starts the application on listeners which have already been bound, serving requests with
+    /// `new_handler`. This allows a parent process to bind privileged ports, or to hand over its
+    /// listeners for a restart without refusing connections. This function only returns if the
+    /// application cannot be started.
+    ///
+    /// On Unix, `gotham::listen_fds` provides the listeners passed by systemd socket activation.
+    pub fn run_listeners<NH, I>(self, listeners: I, new_handler: NH) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        I: IntoIterator<Item = TcpListener>,
+    {
+        self.run_listeners_until(listeners, new_handler, future::empty())
+    }
+
+    /// Starts the application on listeners which have already been bound, serving requests with
+    /// `new_handler` until `shutdown_signal` resolves.
+    ///
+    /// See `run_until` for details of how shutdown is handled.
+    pub fn run_listeners_until<NH, I, F>(
+        self,
+        listeners: I,
+        new_handler: NH,
+        shutdown_signal: F,
+    ) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        I: IntoIterator<Item = TcpListener>,
+        F: Future<Item = (), Error = ()>,
+    {
+        let listeners = listeners.into_iter().collect();
+        os::current::start(self, listeners, new_handler, shutdown_signal)
+    }
+
+    /// Starts the application on a Unix domain socket at `path`, serving requests with
+    /// `new_handler`. The socket must not already exist. This function only returns if the
+    /// application cannot be started.
+    #[cfg(unix)]
+    pub fn run_unix<NH, P>(self, path: P, new_handler: NH) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        P: AsRef<Path>,
+    {
+        self.run_unix_until(path, new_handler, future::empty())
+    }
+
+    /// Starts the application on a Unix domain socket at `path`, serving requests with
+    /// `new_handler` until `shutdown_signal` resolves. The socket is removed once the application
+    /// has shut down.
+    ///
+    /// See `run_until` for details of how shutdown is handled.
+    #[cfg(unix)]
+    pub fn run_unix_until<NH, P, F>(
+        self,
+        path: P,
+        new_handler: NH,
+        shutdown_signal: F,
+    ) -> Result<(), StartError>
+    where
+        NH: NewHandler + 'static,
+        P: AsRef<Path>,
+        F: Future<Item = (), Error = ()>,
+    {
+        os::unix::start_unix(self, path, new_handler, shutdown_signal)
+    }
+
+    /// Serves the application on a reactor which is managed by the caller, rather than starting
+    /// threads and reactors for it. The returned future can be composed with other futures on the
+    /// same reactor.
+    ///
+    /// The thread count and drain timeout are not used, as the caller controls the reactor. To
+    /// stop the application, drop the returned future: the listener is closed immediately, and
+    /// connections are closed once their in-flight requests complete.
+    pub fn serve<NH>(
+        self,
+        listener: tokio_core::net::TcpListener,
+        handle: &Handle,
+        new_handler: NH,
+    ) -> Serve
+    where
+        NH: NewHandler + 'static,
+    {
+        os::serve(self, listener, handle, new_handler)
+    }
+
+    /// Creates the hyper protocol settings used for each connection.
+    pub(crate) fn protocol(&self) -> Http {
+        let mut protocol = Http::new();
+        protocol.keep_alive(self.keep_alive).pipeline(self.pipeline);
+
+        if let Some(max) = self.max_header_size {
+            protocol.max_buf_size(max);
+        }
+
+        protocol
+    }
+
+    /// Determines whether HTTP/2 is enabled, which is never the case without the `http2` feature.
+    #[cfg(feature = "tls")]
+    pub(crate) fn http2_enabled(&self) -> bool {
+        #[cfg(feature = "http2")]
+        {
+            if self.http2 {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// The URL scheme of the connections served, used when logging the listener address.
+    pub(crate) fn scheme(&self) -> Scheme {
+        #[cfg(feature = "tls")]
+        {
+            if self.tls.is_some() {
+                return Scheme::Https;
+            }
+        }
+
+        Scheme::Http
+    }
+}
@@ -2,7 +2,6 @@
 //! Hyper.
 
 use std::thread;
-use std::net::SocketAddr;
 use std::sync::Arc;
 use std::panic::AssertUnwindSafe;
 
@@ -14,7 +13,8 @@ use tokio_core::reactor::Handle;
 
 use handler::NewHandler;
 use state::{request_id, set_request_id, State};
-use state::client_addr::put_client_addr;
+use state::client_addr::{put_client_addr, PeerAddr};
+use state::scheme::{put_scheme, Scheme};
 use http::request::path::RequestPathSegments;
 
 mod timing;
@@ -28,6 +28,7 @@ where
 {
     t: Arc<T>,
     handle: Handle,
+    max_body_size: Option<u64>,
 }
 
 impl<T> GothamService<T>
@@ -35,14 +36,29 @@ where
     T: NewHandler + 'static,
 {
     pub(crate) fn new(t: Arc<T>, handle: Handle) -> GothamService<T> {
-        GothamService { t, handle }
+        GothamService {
+            t,
+            handle,
+            max_body_size: None,
+        }
+    }
+
+    /// Sets the largest request body which will be accepted, as set by
+    /// `ServerBuilder::with_max_body_size`.
+    pub(crate) fn with_max_body_size(self, max_body_size: Option<usize>) -> GothamService<T> {
+        GothamService {
+            max_body_size: max_body_size.map(|size| size as u64),
+            ..self
+        }
     }
 
-    pub(crate) fn connect(&self, client_addr: SocketAddr) -> ConnectedGothamService<T> {
+    pub(crate) fn connect(&self, client_addr: PeerAddr) -> ConnectedGothamService<T> {
         ConnectedGothamService {
             t: self.t.clone(),
             handle: self.handle.clone(),
+            max_body_size: self.max_body_size,
             client_addr,
+            scheme: Scheme::Http,
         }
     }
 }
@@ -55,7 +71,28 @@ where
 {
     t: Arc<T>,
     handle: Handle,
-    client_addr: SocketAddr,
+    max_body_size: Option<u64>,
+    client_addr: PeerAddr,
+    scheme: Scheme,
+}
+
+impl<T> ConnectedGothamService<T>
+where
+    T: NewHandler + 'static,
+{
+    /// Sets the scheme which will be reported to handlers for requests on this connection.
+    #[cfg(feature = "tls")]
+    pub(crate) fn with_scheme(self, scheme: Scheme) -> ConnectedGothamService<T> {
+        ConnectedGothamService { scheme, ..self }
+    }
+
+    /// Replaces the client address, such as with the source address from a PROXY protocol header.
+    pub(crate) fn with_client_addr(self, client_addr: PeerAddr) -> ConnectedGothamService<T> {
+        ConnectedGothamService {
+            client_addr,
+            ..self
+        }
+    }
 }
 
 impl<T> Service for ConnectedGothamService<T>
@@ -70,7 +107,8 @@ where
     fn call(&self, req: Self::Request) -> Self::Future {
         let mut state = State::new();
 
-        put_client_addr(&mut state, self.client_addr);
+        put_client_addr(&mut state, self.client_addr.clone());
+        put_scheme(&mut state, self.scheme);
 
         let (method, uri, version, headers, body) = req.deconstruct();
 
@@ -89,7 +127,7 @@ where
             thread::current().id(),
         );
 
-        trap::call_handler(self.t.as_ref(), AssertUnwindSafe(state))
+        trap::call_handler(self.t.as_ref(), AssertUnwindSafe(state), self.max_body_size)
     }
 }
 
@@ -97,6 +135,8 @@ where
 mod tests {
     use super::*;
 
+    use std::net::SocketAddr;
+
     use hyper::{Method, StatusCode};
     use tokio_core::reactor::Core;
 
@@ -116,7 +156,7 @@ mod tests {
 
         let req = Request::new(Method::Get, "http://localhost/".parse().unwrap());
         let f = service
-            .connect("127.0.0.1:10000".parse().unwrap())
+            .connect("127.0.0.1:10000".parse::<SocketAddr>().unwrap().into())
             .call(req);
         let response = core.run(f).unwrap();
         assert_eq!(response.status(), StatusCode::Accepted);
@@ -133,7 +173,7 @@ mod tests {
 
         let req = Request::new(Method::Get, "http://localhost/".parse().unwrap());
         let f = service
-            .connect("127.0.0.1:10000".parse().unwrap())
+            .connect("127.0.0.1:10000".parse::<SocketAddr>().unwrap().into())
             .call(req);
         let response = core.run(f).unwrap();
         assert_eq!(response.status(), StatusCode::Accepted);
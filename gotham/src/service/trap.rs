@@ -11,6 +11,7 @@ use futures::Async;
 use futures::future::{self, Future, FutureResult};
 
 use handler::{Handler, HandlerError, IntoResponse, NewHandler};
+use middleware::body_limit::limit_request_body;
 use service::timing::Timer;
 use state::{request_id, State};
 
@@ -18,11 +19,14 @@ use state::{request_id, State};
 /// panic occurs from `NewHandler::new_handler` or `Handler::handle`, it is trapped and will result
 /// in a `500 Internal Server Error` response.
 ///
+/// When `max_body_size` is given, larger request bodies are refused before reaching the handler.
+///
 /// Timing information is recorded and logged, except in the case of a panic where the timer is
 /// moved and cannot be recovered.
 pub(super) fn call_handler<T>(
     t: &T,
     state: AssertUnwindSafe<State>,
+    max_body_size: Option<u64>,
 ) -> Box<Future<Item = Response, Error = hyper::Error>>
 where
     T: NewHandler,
@@ -39,7 +43,12 @@ where
             Ok(handler) => {
                 let AssertUnwindSafe(state) = state;
 
-                let f = handler.handle(state).then(move |result| match result {
+                let f = match max_body_size {
+                    Some(limit) => limit_request_body(state, limit, |state| handler.handle(state)),
+                    None => handler.handle(state),
+                };
+
+                let f = f.then(move |result| match result {
                     Ok((state, res)) => finalize_success_response(timer, state, res),
                     Err((state, err)) => finalize_error_response(timer, state, err),
                 });
@@ -209,7 +218,7 @@ mod tests {
         state.put(Headers::new());
         set_request_id(&mut state);
 
-        let r = call_handler(&new_handler, AssertUnwindSafe(state));
+        let r = call_handler(&new_handler, AssertUnwindSafe(state), None);
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::Accepted);
     }
@@ -235,7 +244,7 @@ mod tests {
         state.put(Headers::new());
         set_request_id(&mut state);
 
-        let r = call_handler(&new_handler, AssertUnwindSafe(state));
+        let r = call_handler(&new_handler, AssertUnwindSafe(state), None);
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::Accepted);
     }
@@ -255,7 +264,7 @@ mod tests {
         state.put(Headers::new());
         set_request_id(&mut state);
 
-        let r = call_handler(&new_handler, AssertUnwindSafe(state));
+        let r = call_handler(&new_handler, AssertUnwindSafe(state), None);
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::InternalServerError);
     }
@@ -273,7 +282,7 @@ mod tests {
         state.put(Headers::new());
         set_request_id(&mut state);
 
-        let r = call_handler(&new_handler, AssertUnwindSafe(state));
+        let r = call_handler(&new_handler, AssertUnwindSafe(state), None);
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::InternalServerError);
     }
@@ -291,7 +300,7 @@ mod tests {
         state.put(Headers::new());
         set_request_id(&mut state);
 
-        let r = call_handler(&new_handler, AssertUnwindSafe(state));
+        let r = call_handler(&new_handler, AssertUnwindSafe(state), None);
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::InternalServerError);
     }
@@ -313,7 +322,7 @@ mod tests {
         state.put(Headers::new());
         set_request_id(&mut state);
 
-        let r = call_handler(&new_handler, AssertUnwindSafe(state));
+        let r = call_handler(&new_handler, AssertUnwindSafe(state), None);
         let response = r.wait().unwrap();
         assert_eq!(response.status(), StatusCode::InternalServerError);
     }
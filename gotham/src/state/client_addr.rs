@@ -1,20 +1,50 @@
 //! Defines storage for the remote address of the client
 
+use std::fmt::{self, Display, Formatter};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use state::{FromState, State, StateData};
 
+/// The address of the peer which opened a connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerAddr {
+    /// The client connected over TCP, from the given address.
+    Tcp(SocketAddr),
+
+    /// The client connected over a Unix domain socket. The path is only present when the client
+    /// bound its own socket to a path, which is rare.
+    Unix(Option<PathBuf>),
+}
+
+impl From<SocketAddr> for PeerAddr {
+    fn from(addr: SocketAddr) -> PeerAddr {
+        PeerAddr::Tcp(addr)
+    }
+}
+
+impl Display for PeerAddr {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            PeerAddr::Tcp(ref addr) => write!(f, "{}", addr),
+            PeerAddr::Unix(Some(ref path)) => write!(f, "unix:{}", path.display()),
+            PeerAddr::Unix(None) => write!(f, "unix:(unnamed)"),
+        }
+    }
+}
+
 struct ClientAddr {
-    addr: SocketAddr,
+    addr: PeerAddr,
 }
 
 impl StateData for ClientAddr {}
 
-pub(crate) fn put_client_addr(state: &mut State, addr: SocketAddr) {
+pub(crate) fn put_client_addr(state: &mut State, addr: PeerAddr) {
     state.put(ClientAddr { addr })
 }
 
 /// Returns the client `SocketAddr` as reported by hyper, if one was present. Certain connections
-/// do not report a client address, in which case this will return `None`.
+/// do not report a client address, such as those accepted on a Unix domain socket, in which case
+/// this will return `None`. Use `peer_addr` to determine the address of any connection.
 ///
 /// # Examples
 ///
@@ -55,5 +85,46 @@ pub(crate) fn put_client_addr(state: &mut State, addr: SocketAddr) {
 /// #   assert_eq!(buf.as_slice(), b"127.0.0.1:9816");
 /// # }
 pub fn client_addr(state: &State) -> Option<SocketAddr> {
-    ClientAddr::try_borrow_from(&state).map(|c| c.addr)
+    match peer_addr(state) {
+        Some(&PeerAddr::Tcp(addr)) => Some(addr),
+        _ => None,
+    }
+}
+
+/// Returns the address of the peer which opened the connection, whether it connected over TCP or
+/// a Unix domain socket.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{State, peer_addr, PeerAddr};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let status = match peer_addr(&state) {
+///         Some(&PeerAddr::Tcp(_)) => StatusCode::Ok,
+///         Some(&PeerAddr::Unix(_)) => StatusCode::Accepted,
+///         None => StatusCode::InternalServerError,
+///     };
+///
+///     (state, Response::new().with_status(status))
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.status(), StatusCode::Ok);
+/// # }
+/// ```
+pub fn peer_addr(state: &State) -> Option<&PeerAddr> {
+    ClientAddr::try_borrow_from(&state).map(|c| &c.addr)
 }
@@ -4,6 +4,7 @@ mod data;
 mod from_state;
 pub mod request_id;
 pub(crate) mod client_addr;
+pub(crate) mod scheme;
 
 use std::collections::HashMap;
 use std::any::{Any, TypeId};
@@ -11,7 +12,8 @@ use std::any::{Any, TypeId};
 pub use state::data::StateData;
 pub use state::from_state::FromState;
 pub use state::request_id::request_id;
-pub use state::client_addr::client_addr;
+pub use state::client_addr::{client_addr, peer_addr, PeerAddr};
+pub use state::scheme::{scheme, Scheme};
 
 pub(crate) use state::request_id::set_request_id;
 
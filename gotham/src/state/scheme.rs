@@ -0,0 +1,73 @@
+//! Defines storage for the scheme of the connection the request was received on
+
+use std::fmt::{self, Display, Formatter};
+
+use state::{FromState, State, StateData};
+
+/// The scheme of the connection that a request was received on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// The request was received over plaintext HTTP.
+    Http,
+
+    /// The request was received over a TLS connection.
+    Https,
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Scheme::Http => f.write_str("http"),
+            Scheme::Https => f.write_str("https"),
+        }
+    }
+}
+
+struct RequestScheme {
+    scheme: Scheme,
+}
+
+impl StateData for RequestScheme {}
+
+pub(crate) fn put_scheme(state: &mut State, scheme: Scheme) {
+    state.put(RequestScheme { scheme })
+}
+
+/// Returns the scheme of the connection that the request was received on. Requests which were not
+/// received via a Gotham server (e.g. in tests) are reported as `Scheme::Http`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::{State, scheme, Scheme};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response) {
+///     let status = match scheme(&state) {
+///         Scheme::Https => StatusCode::Ok,
+///         Scheme::Http => StatusCode::Forbidden,
+///     };
+///
+///     (state, Response::new().with_status(status))
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.status(), StatusCode::Forbidden);
+/// # }
+/// ```
+pub fn scheme(state: &State) -> Scheme {
+    RequestScheme::try_borrow_from(state)
+        .map(|s| s.scheme)
+        .unwrap_or(Scheme::Http)
+}
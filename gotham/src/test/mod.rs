@@ -166,7 +166,7 @@ where
         let ss = mio::net::TcpStream::from_stream(ss)?;
         let ss = PollEvented::new(ss, &handle)?;
 
-        let service = self.data.gotham_service.connect(client_addr);
+        let service = self.data.gotham_service.connect(client_addr.into());
         let f = self.data
             .http
             .serve_connection(ss, service)
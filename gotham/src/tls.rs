@@ -0,0 +1,577 @@
+//! Defines TLS support for the Gotham server, using `rustls`. This module is only available when
+//! the `tls` feature is enabled.
+//!
+//! TLS is enabled by providing a `TlsConfig` to `ServerBuilder::with_tls`. Handlers can determine
+//! whether a request arrived over TLS via `gotham::state::scheme`.
+
+use std::fmt::{self, Debug, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::{Async, Future, Poll};
+use rustls::{Certificate, NoClientAuth, PrivateKey, ServerConfig, ServerSession, Session};
+use rustls::internal::pemfile;
+use tokio_io::{AsyncRead, AsyncWrite};
+#[cfg(test)]
+use rustls::{ClientConfig, ClientSession, RootCertStore};
+#[cfg(test)]
+use webpki::DNSNameRef;
+
+/// The certificate chain and private key used to terminate TLS connections.
+///
+/// Cloned values share the loaded certificate, so a clone retained by the application can be used
+/// to `reload` the certificate while the server is running. Connections which are established
+/// after a reload use the new certificate, while existing connections are unaffected.
+///
+/// ```rust,no_run
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::{Response, StatusCode};
+/// # use gotham::state::State;
+/// # use gotham::ServerBuilder;
+/// # use gotham::tls::TlsConfig;
+/// #
+/// # fn my_handler(state: State) -> (State, Response) {
+/// #   (state, Response::new().with_status(StatusCode::Accepted))
+/// # }
+/// #
+/// # fn main() {
+/// let tls = TlsConfig::from_pem_files("/etc/app/cert.pem", "/etc/app/key.pem")
+///     .expect("unable to load certificate")
+///     .with_reload_on_change(Duration::from_secs(60));
+///
+/// ServerBuilder::new()
+///     .with_tls(tls)
+///     .run("0.0.0.0:443", || Ok(my_handler))
+///     .expect("unable to start server");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TlsConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    reload_interval: Option<Duration>,
+    loaded: Arc<Mutex<Loaded>>,
+}
+
+struct Loaded {
+    config: Arc<ServerConfig>,
+    h2_config: Arc<ServerConfig>,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl TlsConfig {
+    /// Loads a PEM encoded certificate chain and private key from the given files. The private
+    /// key may be either PKCS#8 or RSA encoded.
+    pub fn from_pem_files<C, K>(cert_path: C, key_path: K) -> io::Result<TlsConfig>
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        let cert_path = cert_path.as_ref().to_owned();
+        let key_path = key_path.as_ref().to_owned();
+
+        let config = load(&cert_path, &key_path)?;
+        let loaded = Loaded {
+            h2_config: Arc::new(with_h2(&config)),
+            config: Arc::new(config),
+            modified: modified(&cert_path, &key_path),
+            checked: Instant::now(),
+        };
+
+        Ok(TlsConfig {
+            cert_path,
+            key_path,
+            reload_interval: None,
+            loaded: Arc::new(Mutex::new(loaded)),
+        })
+    }
+
+    /// Reloads the certificate and private key when either file is modified. The files are
+    /// checked as new connections are accepted, at most once per `interval`.
+    pub fn with_reload_on_change(self, interval: Duration) -> TlsConfig {
+        TlsConfig {
+            reload_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Reloads the certificate and private key from their files. If the files cannot be loaded,
+    /// the error is returned and the existing certificate remains in use.
+    pub fn reload(&self) -> io::Result<()> {
+        let config = load(&self.cert_path, &self.key_path)?;
+
+        let mut loaded = self.loaded
+            .lock()
+            .expect("mutex poisoned, TLS certificate reload panicked?");
+
+        loaded.h2_config = Arc::new(with_h2(&config));
+        loaded.config = Arc::new(config);
+        loaded.modified = modified(&self.cert_path, &self.key_path);
+        loaded.checked = Instant::now();
+
+        info!(
+            target: "gotham::start",
+            " Gotham reloaded TLS certificate from {}",
+            self.cert_path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Returns the `rustls` configuration to use for a new connection, reloading the certificate
+    /// first if it has changed. When `h2` is set, the configuration offers HTTP/2 via ALPN.
+    pub(crate) fn server_config(&self, h2: bool) -> Arc<ServerConfig> {
+        let mut loaded = self.loaded
+            .lock()
+            .expect("mutex poisoned, TLS certificate reload panicked?");
+
+        if let Some(interval) = self.reload_interval {
+            if loaded.checked.elapsed() >= interval {
+                loaded.checked = Instant::now();
+
+                let modified = modified(&self.cert_path, &self.key_path);
+                if modified != loaded.modified {
+                    match load(&self.cert_path, &self.key_path) {
+                        Ok(config) => {
+                            loaded.h2_config = Arc::new(with_h2(&config));
+                            loaded.config = Arc::new(config);
+                            loaded.modified = modified;
+
+                            info!(
+                                target: "gotham::start",
+                                " Gotham reloaded TLS certificate from {}",
+                                self.cert_path.display()
+                            );
+                        }
+                        Err(e) => error!(
+                            "[ERROR][Unable to reload TLS certificate, continuing with the \
+                             existing certificate: {}]",
+                            e
+                        ),
+                    }
+                }
+            }
+        }
+
+        if h2 {
+            loaded.h2_config.clone()
+        } else {
+            loaded.config.clone()
+        }
+    }
+}
+
+impl Debug for TlsConfig {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("cert_path", &self.cert_path)
+            .field("key_path", &self.key_path)
+            .field("reload_interval", &self.reload_interval)
+            .finish()
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn load(cert_path: &Path, key_path: &Path) -> io::Result<ServerConfig> {
+    let certs = load_certs(cert_path)?;
+    let key = load_private_key(key_path)?;
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    config.set_protocols(&[b"http/1.1".to_vec()]);
+
+    Ok(config)
+}
+
+/// Creates a copy of `config` which prefers HTTP/2 over HTTP/1.1 when negotiating via ALPN.
+fn with_h2(config: &ServerConfig) -> ServerConfig {
+    let mut config = config.clone();
+    config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+    config
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs = pemfile::certs(&mut reader)
+        .map_err(|()| invalid_data("unable to parse PEM certificates"))?;
+
+    if certs.is_empty() {
+        return Err(invalid_data("no certificates found"));
+    }
+
+    Ok(certs)
+}
+
+fn load_private_key(path: &Path) -> io::Result<PrivateKey> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let pkcs8 = pemfile::pkcs8_private_keys(&mut reader)
+        .map_err(|()| invalid_data("unable to parse PEM private key"))?;
+
+    if let Some(key) = pkcs8.into_iter().next() {
+        return Ok(key);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let rsa = pemfile::rsa_private_keys(&mut reader)
+        .map_err(|()| invalid_data("unable to parse PEM private key"))?;
+
+    rsa.into_iter()
+        .next()
+        .ok_or_else(|| invalid_data("no private key found"))
+}
+
+/// Determines when the certificate or private key was last modified, if the platform supports it.
+fn modified(cert_path: &Path, key_path: &Path) -> Option<SystemTime> {
+    let cert = fs::metadata(cert_path).and_then(|m| m.modified()).ok();
+    let key = fs::metadata(key_path).and_then(|m| m.modified()).ok();
+    cert.into_iter().chain(key).max()
+}
+
+fn would_block(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock
+}
+
+/// Begins a TLS handshake on the given stream, which completes when the handshake has finished.
+pub(crate) fn accept<S>(io: S, config: &Arc<ServerConfig>) -> TlsAccept<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    TlsAccept {
+        stream: Some(TlsStream {
+            io,
+            session: ServerSession::new(config),
+            eof: false,
+            closing: false,
+        }),
+    }
+}
+
+/// The self-signed certificate for `localhost`, and its private key, used by the tests.
+#[cfg(test)]
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+/// Loads the test certificate for `localhost`.
+#[cfg(test)]
+pub(crate) fn test_config() -> TlsConfig {
+    TlsConfig::from_pem_files(fixture("cert.pem"), fixture("key.pem")).unwrap()
+}
+
+/// Creates a client configuration which trusts the test certificate, and offers the given
+/// protocols via ALPN.
+#[cfg(test)]
+fn client_config(alpn_protocols: &[&[u8]]) -> Arc<ClientConfig> {
+    let mut config = ClientConfig::new();
+    config.root_store = RootCertStore::empty();
+    for cert in load_certs(&fixture("cert.pem")).unwrap() {
+        config.root_store.add(&cert).unwrap();
+    }
+
+    config.set_protocols(&alpn_protocols
+        .iter()
+        .map(|protocol| protocol.to_vec())
+        .collect::<Vec<_>>());
+    Arc::new(config)
+}
+
+/// Begins a TLS handshake with a server using the test certificate, as a client connecting to
+/// `localhost`.
+#[cfg(all(test, feature = "http2"))]
+pub(crate) fn connect<S>(io: S, alpn_protocols: &[&[u8]]) -> TlsAccept<S, ClientSession>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let name = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+
+    TlsAccept {
+        stream: Some(TlsStream {
+            io,
+            session: ClientSession::new(&client_config(alpn_protocols), name),
+            eof: false,
+            closing: false,
+        }),
+    }
+}
+
+/// Drives the TLS handshake for a new connection.
+pub(crate) struct TlsAccept<S, T = ServerSession> {
+    stream: Option<TlsStream<S, T>>,
+}
+
+impl<S, T> Future for TlsAccept<S, T>
+where
+    S: AsyncRead + AsyncWrite,
+    T: Session,
+{
+    type Item = TlsStream<S, T>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<TlsStream<S, T>, io::Error> {
+        {
+            let stream = self.stream
+                .as_mut()
+                .expect("TlsAccept polled after completion");
+
+            if let Async::NotReady = stream.poll_handshake()? {
+                return Ok(Async::NotReady);
+            }
+        }
+
+        Ok(Async::Ready(self.stream.take().unwrap()))
+    }
+}
+
+/// A stream which has completed a TLS handshake, and encrypts and decrypts the data passing
+/// through it. The session is only a `ClientSession` in tests, which connect to the server.
+pub(crate) struct TlsStream<S, T = ServerSession> {
+    io: S,
+    session: T,
+    eof: bool,
+    closing: bool,
+}
+
+impl<S, T> TlsStream<S, T>
+where
+    S: AsyncRead + AsyncWrite,
+    T: Session,
+{
+    /// Reads TLS records from the underlying stream, and processes them.
+    fn read_tls(&mut self) -> io::Result<()> {
+        if self.session.read_tls(&mut self.io)? == 0 {
+            self.eof = true;
+        }
+
+        if let Err(e) = self.session.process_new_packets() {
+            // Make a best effort to send the alert describing the failure before the connection is
+            // closed.
+            let _ = self.write_tls();
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+
+        Ok(())
+    }
+
+    /// Writes all pending TLS records to the underlying stream.
+    fn write_tls(&mut self) -> io::Result<()> {
+        while self.session.wants_write() {
+            self.session.write_tls(&mut self.io)?;
+        }
+
+        Ok(())
+    }
+
+    fn poll_handshake(&mut self) -> Poll<(), io::Error> {
+        loop {
+            match self.write_tls() {
+                Ok(()) => (),
+                Err(ref e) if would_block(e) => return Ok(Async::NotReady),
+                Err(e) => return Err(e),
+            }
+
+            if !self.session.is_handshaking() {
+                return Ok(Async::Ready(()));
+            }
+
+            if self.eof {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed during TLS handshake",
+                ));
+            }
+
+            match self.read_tls() {
+                Ok(()) => (),
+                Err(ref e) if would_block(e) => return Ok(Async::NotReady),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The protocol negotiated via ALPN during the handshake, if any.
+    #[cfg(feature = "http2")]
+    pub(crate) fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.session.get_alpn_protocol()
+    }
+}
+
+impl<S, T> Read for TlsStream<S, T>
+where
+    S: AsyncRead + AsyncWrite,
+    T: Session,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.session.read(buf) {
+                Ok(0) if !buf.is_empty() && !self.eof => (),
+                Ok(n) => return Ok(n),
+                // A close_notify alert from the client is a clean end of the stream.
+                Err(ref e) if e.kind() == io::ErrorKind::ConnectionAborted => return Ok(0),
+                Err(e) => return Err(e),
+            }
+
+            self.read_tls()?;
+
+            // Processing records may have produced a response, such as an alert.
+            match self.write_tls() {
+                Ok(()) => (),
+                Err(ref e) if would_block(e) => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<S, T> Write for TlsStream<S, T>
+where
+    S: AsyncRead + AsyncWrite,
+    T: Session,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Records which haven't been sent yet must be written before accepting more data, so that
+        // a slow client applies back pressure rather than causing unbounded buffering.
+        self.write_tls()?;
+
+        let n = self.session.write(buf)?;
+
+        match self.write_tls() {
+            Ok(()) => Ok(n),
+            Err(ref e) if would_block(e) => Ok(n),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.session.flush()?;
+        self.write_tls()?;
+        self.io.flush()
+    }
+}
+
+impl<S, T> AsyncRead for TlsStream<S, T>
+where
+    S: AsyncRead + AsyncWrite,
+    T: Session,
+{
+}
+
+impl<S, T> AsyncWrite for TlsStream<S, T>
+where
+    S: AsyncRead + AsyncWrite,
+    T: Session,
+{
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        if !self.closing {
+            self.session.send_close_notify();
+            self.closing = true;
+        }
+
+        match self.write_tls() {
+            Ok(()) => self.io.shutdown(),
+            Err(ref e) if would_block(e) => Ok(Async::NotReady),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::net::{self, TcpStream};
+    use std::thread;
+
+    use hyper::{Response, StatusCode};
+    use mime;
+    use rustls::Stream;
+    use tokio_core::net::TcpListener;
+    use tokio_core::reactor::Core;
+
+    use http::response::create_response;
+    use server::ServerBuilder;
+    use state::{scheme, State};
+
+    #[test]
+    fn handshake_completes_and_scheme_is_https() {
+        fn handler(state: State) -> (State, Response) {
+            let body = scheme(&state).to_string().into_bytes();
+            let res = create_response(&state, StatusCode::Ok, Some((body, mime::TEXT_PLAIN)));
+            (state, res)
+        }
+
+        let tls = test_config();
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut core = Core::new().unwrap();
+            let handle = core.handle();
+            let listener = TcpListener::from_listener(listener, &addr, &handle).unwrap();
+            let serve = ServerBuilder::new()
+                .with_tls(tls)
+                .serve(listener, &handle, || Ok(handler));
+            core.run(serve).unwrap();
+        });
+
+        let name = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+        let mut session = ClientSession::new(&client_config(&[]), name);
+        let mut socket = TcpStream::connect(addr).unwrap();
+        let mut stream = Stream::new(&mut session, &mut socket);
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        // The server ends the connection with a close_notify alert, which is reported as an error.
+        let mut response = Vec::new();
+        let mut buf = [0; 1024];
+        while let Ok(n) = stream.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("\r\n\r\nhttps"), "{}", response);
+    }
+
+    #[test]
+    fn missing_certificate_is_reported() {
+        let dir = env::temp_dir();
+        let err = TlsConfig::from_pem_files(
+            dir.join("gotham-missing-cert.pem"),
+            dir.join("gotham-missing-key.pem"),
+        ).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn file_without_certificates_is_rejected() {
+        let path = env::temp_dir().join("gotham-tls-empty.pem");
+        File::create(&path)
+            .and_then(|mut f| f.write_all(b"not a certificate\n"))
+            .unwrap();
+
+        let err = TlsConfig::from_pem_files(&path, &path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
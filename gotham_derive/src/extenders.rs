@@ -10,10 +10,15 @@ pub(crate) fn bad_request_static_response_extender(ast: &syn::DeriveInput) -> qu
             #ty_generics #where_clause
         {
             fn extend(state: &mut ::gotham::state::State, res: &mut ::hyper::Response) {
-                ::gotham::http::response::extend_response(state,
-                                                          res,
-                                                          ::hyper::StatusCode::BadRequest,
-                                                          None);
+                // A more specific client error, such as `413 Payload Too Large` from a request
+                // body extractor, is kept.
+                let status = if res.status().is_client_error() {
+                    res.status()
+                } else {
+                    ::hyper::StatusCode::BadRequest
+                };
+
+                ::gotham::http::response::extend_response(state, res, status, None);
             }
         }
     }
@@ -16,3 +16,130 @@ pub(crate) fn base_query_string(_ast: &syn::DeriveInput) -> quote::Tokens {
                         derives are still required.");
     }
 }
+
+pub(crate) fn header_extractor(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let fields = match ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(ref fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("#[derive(HeaderExtractor)] is only supported for structs with named fields"),
+    };
+
+    let values = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let header = header_name(field);
+
+        if is_option(&field.ty) {
+            quote! { #ident: ::gotham::extractor::optional_header(headers, #header)? }
+        } else {
+            quote! { #ident: ::gotham::extractor::required_header(headers, #header)? }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::gotham::extractor::HeaderExtractor for #name #ty_generics
+            #where_clause
+        {
+            fn extract(
+                headers: &::hyper::Headers,
+            ) -> ::std::result::Result<Self, ::gotham::extractor::HeaderExtractorError> {
+                ::std::result::Result::Ok(#name { #(#values,)* })
+            }
+        }
+    }
+}
+
+/// The header read into `field`, which is given by `#[header(rename = "...")]`, or otherwise
+/// derived from the field name, so that `api_key` is read from `Api-Key`.
+fn header_name(field: &syn::Field) -> String {
+    for attr in &field.attrs {
+        let list = match attr.interpret_meta() {
+            Some(syn::Meta::List(ref list)) if list.ident == "header" => list.clone(),
+            _ => continue,
+        };
+
+        let rename = match list.nested.iter().next() {
+            Some(&syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref ident,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            }))) if ident == "rename" && list.nested.len() == 1 => lit.value(),
+            _ => panic!("expected #[header(rename = \"Header-Name\")]"),
+        };
+        return rename;
+    }
+
+    field
+        .ident
+        .as_ref()
+        .unwrap()
+        .as_ref()
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Whether `ty` is an `Option`, which makes the header optional.
+fn is_option(ty: &syn::Type) -> bool {
+    match *ty {
+        syn::Type::Path(syn::TypePath { ref path, .. }) => match path.segments.iter().last() {
+            Some(segment) => segment.ident == "Option",
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(input: &str) -> Vec<syn::Field> {
+        match syn::parse_str::<syn::DeriveInput>(input).unwrap().data {
+            syn::Data::Struct(syn::DataStruct {
+                fields: syn::Fields::Named(fields),
+                ..
+            }) => fields.named.into_iter().collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn header_names_are_derived_from_field_names() {
+        let fields = fields("struct Headers { api_key: String, etag: String, x__id: String }");
+        let names = fields.iter().map(header_name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Api-Key", "Etag", "X-Id"]);
+    }
+
+    #[test]
+    fn header_names_can_be_renamed() {
+        let fields = fields(r#"struct Headers { #[header(rename = "X-Page")] page: u32 }"#);
+        assert_eq!(header_name(&fields[0]), "X-Page");
+    }
+
+    #[test]
+    fn option_fields_are_optional_headers() {
+        let ast = syn::parse_str(
+            r#"struct Headers {
+                api_key: String,
+                #[header(rename = "X-Page")] page: Option<u32>,
+            }"#,
+        ).unwrap();
+
+        let tokens = header_extractor(&ast).to_string().replace(' ', "");
+        assert!(tokens.contains(r#"required_header(headers,"Api-Key")"#));
+        assert!(tokens.contains(r#"optional_header(headers,"X-Page")"#));
+    }
+}
@@ -22,6 +22,12 @@ pub fn base_query_string_extractor(input: proc_macro::TokenStream) -> proc_macro
     extractors::base_query_string(&ast).into()
 }
 
+#[proc_macro_derive(HeaderExtractor, attributes(header))]
+pub fn header_extractor(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = syn::parse(input).unwrap();
+    extractors::header_extractor(&ast).into()
+}
+
 #[proc_macro_derive(StaticResponseExtender)]
 pub fn static_response_extender(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = syn::parse(input).unwrap();